@@ -0,0 +1,520 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::backend::Backend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Gauge, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use crate::args::{Args, OnError};
+use crate::manifest::Manifest;
+use crate::pipeline::{self, GroupOutcome, Reporter};
+use crate::planner::PlannedGroup;
+
+// ── Color palette ────────────────────────────────────────────────────
+const ACCENT: Color = Color::Indexed(75);        // soft blue — titles, focused border
+const HIGHLIGHT_BG: Color = Color::Indexed(236);  // dark gray — selected row background
+const SUCCESS: Color = Color::Indexed(114);       // soft green — merged groups
+const ERROR: Color = Color::Indexed(203);         // soft red — failed groups, errors
+const WARNING: Color = Color::Indexed(179);       // amber — in-progress and warnings
+const DIM: Color = Color::Indexed(242);           // gray — help text, skipped groups
+
+/// Number of worker log lines kept for the log panel.
+const MAX_LOG_LINES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupStatus {
+    Pending,
+    Queued,
+    Merging,
+    Done(GroupOutcome),
+}
+
+/// A planned group plus the user's choices for it.
+#[derive(Debug, Clone)]
+pub struct GroupRow {
+    pub group: PlannedGroup,
+    pub checked: bool,
+    pub status: GroupStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Focus {
+    Groups,
+    Files,
+}
+
+/// Progress sent from the merge worker thread.
+enum WorkerEvent {
+    Started(usize),
+    Finished(usize, GroupOutcome),
+    Log(String),
+    Done,
+}
+
+/// Forwards pipeline progress to the UI thread.
+struct ChannelReporter {
+    tx: Sender<WorkerEvent>,
+    on_error: OnError,
+}
+
+impl Reporter for ChannelReporter {
+    fn group_started(&mut self, index: usize, _group: &PlannedGroup) {
+        let _ = self.tx.send(WorkerEvent::Started(index));
+    }
+
+    fn group_finished(&mut self, index: usize, outcome: GroupOutcome) {
+        let _ = self.tx.send(WorkerEvent::Finished(index, outcome));
+    }
+
+    fn info(&mut self, message: &str) {
+        let _ = self.tx.send(WorkerEvent::Log(message.to_string()));
+    }
+
+    fn warn(&mut self, message: &str) {
+        let _ = self.tx.send(WorkerEvent::Log(format!("Warning: {}", message)));
+    }
+
+    fn error(&mut self, message: &str) -> bool {
+        let _ = self.tx.send(WorkerEvent::Log(message.to_string()));
+        self.on_error != OnError::Halt
+    }
+}
+
+/// Main application state
+pub struct App {
+    args: Args,
+    source_root: PathBuf,
+    rows: Vec<GroupRow>,
+    group_list_state: ListState,
+    file_list_state: ListState,
+    focus: Focus,
+    should_quit: bool,
+    status_message: Option<String>,
+    log: Vec<String>,
+    worker: Option<Receiver<WorkerEvent>>,
+    /// Row index of each group in the running batch, by batch position.
+    batch: Vec<usize>,
+}
+
+impl App {
+    pub fn new(plan: Vec<PlannedGroup>, source_root: PathBuf, args: Args) -> Self {
+        let rows: Vec<GroupRow> = plan.into_iter()
+            .map(|group| GroupRow { group, checked: true, status: GroupStatus::Pending })
+            .collect();
+        let mut group_list_state = ListState::default();
+        let mut file_list_state = ListState::default();
+        if !rows.is_empty() {
+            group_list_state.select(Some(0));
+            file_list_state.select(Some(0));
+        }
+
+        Self {
+            args,
+            source_root,
+            rows,
+            group_list_state,
+            file_list_state,
+            focus: Focus::Groups,
+            should_quit: false,
+            status_message: None,
+            log: Vec::new(),
+            worker: None,
+            batch: Vec::new(),
+        }
+    }
+
+    /// Main event loop - draw, drain worker progress and handle keys
+    pub fn run(&mut self, terminal: &mut Terminal<impl Backend>) -> io::Result<()> {
+        while !self.should_quit {
+            terminal.draw(|f| self.draw(f))?;
+            self.drain_worker_events();
+            if event::poll(Duration::from_millis(100))?
+                && let Event::Key(key) = event::read()?
+            {
+                self.handle_key_event(key);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_merging(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    fn selected_row(&self) -> Option<usize> {
+        self.group_list_state.selected().filter(|i| *i < self.rows.len())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        // Only handle key press events (Windows compatibility)
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        self.status_message = None;
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if self.is_merging() {
+                    self.status_message = Some("A merge is in progress; wait for it to finish.".to_string());
+                } else {
+                    self.should_quit = true;
+                }
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Groups => Focus::Files,
+                    Focus::Files => Focus::Groups,
+                };
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.select_offset(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select_offset(-1),
+            KeyCode::Char(' ') if self.focus == Focus::Groups => self.toggle_selected_group(),
+            KeyCode::Char('a') if self.focus == Focus::Groups => self.toggle_all_groups(),
+            KeyCode::Char('J') if self.focus == Focus::Files => self.move_selected_file(1),
+            KeyCode::Char('K') if self.focus == Focus::Files => self.move_selected_file(-1),
+            KeyCode::Char('d') | KeyCode::Delete if self.focus == Focus::Files => self.drop_selected_file(),
+            KeyCode::Enter | KeyCode::Char('m') => self.start_merge(),
+            _ => {}
+        }
+    }
+
+    /// Moves the selection in the focused list, wrapping around.
+    fn select_offset(&mut self, offset: isize) {
+        let (state, len) = match self.focus {
+            Focus::Groups => (&mut self.group_list_state, self.rows.len()),
+            Focus::Files => {
+                let len = self.group_list_state.selected()
+                    .and_then(|i| self.rows.get(i))
+                    .map_or(0, |row| row.group.files.len());
+                (&mut self.file_list_state, len)
+            }
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + offset).rem_euclid(len as isize) as usize));
+
+        if self.focus == Focus::Groups {
+            self.file_list_state.select(Some(0));
+        }
+    }
+
+    fn toggle_selected_group(&mut self) {
+        if let Some(i) = self.selected_row() && !self.is_merging() {
+            self.rows[i].checked = !self.rows[i].checked;
+        }
+    }
+
+    fn toggle_all_groups(&mut self) {
+        if self.is_merging() {
+            return;
+        }
+        let check = !self.rows.iter().all(|row| row.checked);
+        for row in &mut self.rows {
+            row.checked = check;
+        }
+    }
+
+    /// Swaps the highlighted file with its neighbour, keeping it highlighted.
+    fn move_selected_file(&mut self, offset: isize) {
+        if self.is_merging() {
+            return;
+        }
+        let (Some(row), Some(file)) = (self.selected_row(), self.file_list_state.selected()) else {
+            return;
+        };
+        let files = &mut self.rows[row].group.files;
+        let target = file as isize + offset;
+        if target < 0 || target as usize >= files.len() {
+            return;
+        }
+        files.swap(file, target as usize);
+        self.file_list_state.select(Some(target as usize));
+    }
+
+    /// Removes the highlighted file from its group. The file itself is untouched.
+    fn drop_selected_file(&mut self) {
+        if self.is_merging() {
+            return;
+        }
+        let (Some(row), Some(file)) = (self.selected_row(), self.file_list_state.selected()) else {
+            return;
+        };
+        let files = &mut self.rows[row].group.files;
+        if file >= files.len() {
+            return;
+        }
+        let removed = files.remove(file);
+        if files.is_empty() {
+            self.rows[row].checked = false;
+        }
+        let remaining = self.rows[row].group.files.len();
+        self.file_list_state.select(if remaining == 0 { None } else { Some(file.min(remaining - 1)) });
+        self.status_message = Some(format!(
+            "Dropped {} from this merge",
+            removed.file_name().unwrap_or_default().to_string_lossy()
+        ));
+    }
+
+    /// Merges every checked, non-empty group on a worker thread.
+    fn start_merge(&mut self) {
+        if self.is_merging() {
+            return;
+        }
+        if self.args.dry_run {
+            self.status_message = Some("Dry run: nothing will be merged.".to_string());
+            return;
+        }
+
+        self.batch = self.rows.iter()
+            .enumerate()
+            .filter(|(_, row)| row.checked && !row.group.files.is_empty())
+            .map(|(i, _)| i)
+            .collect();
+        if self.batch.is_empty() {
+            self.status_message = Some("No groups selected. Use Space to select groups first.".to_string());
+            return;
+        }
+        for &i in &self.batch {
+            self.rows[i].status = GroupStatus::Queued;
+        }
+
+        let plan: Vec<PlannedGroup> = self.batch.iter().map(|&i| self.rows[i].group.clone()).collect();
+        let args = self.args.clone();
+        let source_root = self.source_root.clone();
+        let (tx, rx) = mpsc::channel();
+        self.worker = Some(rx);
+
+        thread::spawn(move || {
+            let mut run_manifest = args.manifest.as_ref().map(|_| Manifest::new(std::slice::from_ref(&source_root), &args.output));
+            let mut reporter = ChannelReporter { tx: tx.clone(), on_error: args.on_error.clone() };
+            pipeline::merge_plan(&plan, &source_root, &args, &mut run_manifest, &mut reporter);
+            let _ = tx.send(WorkerEvent::Done);
+        });
+    }
+
+    fn drain_worker_events(&mut self) {
+        let Some(rx) = &self.worker else {
+            return;
+        };
+        let events: Vec<WorkerEvent> = rx.try_iter().collect();
+        for event in events {
+            match event {
+                WorkerEvent::Started(i) => self.rows[self.batch[i]].status = GroupStatus::Merging,
+                WorkerEvent::Finished(i, outcome) => self.rows[self.batch[i]].status = GroupStatus::Done(outcome),
+                WorkerEvent::Log(message) => {
+                    self.log.extend(message.lines().map(String::from));
+                    let excess = self.log.len().saturating_sub(MAX_LOG_LINES);
+                    self.log.drain(..excess);
+                }
+                WorkerEvent::Done => {
+                    self.worker = None;
+                    // Anything still queued was never reached (halted)
+                    for &i in &self.batch {
+                        if self.rows[i].status == GroupStatus::Queued {
+                            self.rows[i].status = GroupStatus::Pending;
+                        }
+                    }
+                    self.status_message = Some("Merge finished.".to_string());
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),
+                Constraint::Length(3),
+                Constraint::Length(6),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(chunks[0]);
+
+        self.draw_groups(frame, panes[0]);
+        self.draw_files(frame, panes[1]);
+        self.draw_progress(frame, chunks[1]);
+        self.draw_log(frame, chunks[2]);
+
+        let help = match &self.status_message {
+            Some(message) => Line::from(Span::styled(message.clone(), Style::default().fg(WARNING))),
+            None => Line::from(Span::styled(
+                match self.focus {
+                    Focus::Groups => "j/k: move  Space: toggle  a: toggle all  Tab: files  Enter: merge  q: quit",
+                    Focus::Files => "j/k: move  J/K: reorder  d: drop file  Tab: groups  Enter: merge  q: quit",
+                },
+                Style::default().fg(DIM),
+            )),
+        };
+        frame.render_widget(Paragraph::new(help), chunks[3]);
+    }
+
+    fn pane_block(&self, title: &str, focus: Focus) -> Block<'static> {
+        let border = if self.focus == focus { ACCENT } else { DIM };
+        Block::default()
+            .title(Span::styled(format!(" {} ", title), Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(border))
+    }
+
+    fn draw_groups(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let items: Vec<ListItem> = self.rows.iter()
+            .map(|row| {
+                let checkbox = if row.checked { "[x]" } else { "[ ]" };
+                let (label, color) = match row.status {
+                    GroupStatus::Pending => ("", Color::Reset),
+                    GroupStatus::Queued => (" queued", DIM),
+                    GroupStatus::Merging => (" merging…", WARNING),
+                    GroupStatus::Done(GroupOutcome::Merged) => (" merged", SUCCESS),
+                    GroupStatus::Done(GroupOutcome::Skipped) => (" skipped", DIM),
+                    GroupStatus::Done(GroupOutcome::Failed) => (" failed", ERROR),
+                };
+                let name = row.group.output.file_name().unwrap_or_default().to_string_lossy().to_string();
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} {} ", checkbox, name)),
+                    Span::styled(format!("({} files)", row.group.files.len()), Style::default().fg(DIM)),
+                    Span::styled(label, Style::default().fg(color)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(self.pane_block("Groups", Focus::Groups))
+            .highlight_style(Style::default().bg(HIGHLIGHT_BG).add_modifier(Modifier::BOLD));
+        frame.render_stateful_widget(list, area, &mut self.group_list_state);
+    }
+
+    fn draw_files(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let (title, items): (String, Vec<ListItem>) = match self.selected_row() {
+            Some(i) => {
+                let group = &self.rows[i].group;
+                let items = group.files.iter()
+                    .enumerate()
+                    .map(|(n, file)| {
+                        ListItem::new(format!(
+                            "{:>3}. {}",
+                            n + 1,
+                            file.file_name().unwrap_or_default().to_string_lossy()
+                        ))
+                    })
+                    .collect();
+                (format!("Files → {}", group.output.display()), items)
+            }
+            None => ("Files".to_string(), Vec::new()),
+        };
+
+        let list = List::new(items)
+            .block(self.pane_block(&title, Focus::Files))
+            .highlight_style(Style::default().bg(HIGHLIGHT_BG).add_modifier(Modifier::BOLD));
+        frame.render_stateful_widget(list, area, &mut self.file_list_state);
+    }
+
+    fn draw_progress(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let total = self.batch.len();
+        let finished = self.batch.iter()
+            .filter(|&&i| matches!(self.rows[i].status, GroupStatus::Done(_)))
+            .count();
+        let ratio = if total == 0 { 0.0 } else { finished as f64 / total as f64 };
+
+        let gauge = Gauge::default()
+            .block(self.pane_block("Progress", Focus::Groups).border_style(Style::default().fg(DIM)))
+            .gauge_style(Style::default().fg(SUCCESS).bg(HIGHLIGHT_BG))
+            .label(format!("{}/{} groups", finished, total))
+            .ratio(ratio);
+        frame.render_widget(gauge, area);
+    }
+
+    fn draw_log(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let visible = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.log.iter()
+            .skip(self.log.len().saturating_sub(visible))
+            .map(|line| Line::from(line.as_str()))
+            .collect();
+        let block = self.pane_block("Log", Focus::Groups).border_style(Style::default().fg(DIM));
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn app_with_files(names: &[&str]) -> App {
+        let group = PlannedGroup {
+            source_dir: PathBuf::from("src/book"),
+            output: PathBuf::from("out/book.mp3"),
+            files: names.iter().map(|n| PathBuf::from("src/book").join(n)).collect(),
+            trims: Default::default(),
+            meta: None,
+        };
+        App::new(vec![group], PathBuf::from("src"), Args::parse_from(["app"]))
+    }
+
+    fn file_names(app: &App) -> Vec<String> {
+        app.rows[0].group.files.iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_reorder_and_drop_files() {
+        let mut app = app_with_files(&["1.mp3", "2.mp3", "3.mp3"]);
+        app.focus = Focus::Files;
+
+        app.move_selected_file(1);
+        assert_eq!(file_names(&app), vec!["2.mp3", "1.mp3", "3.mp3"]);
+        assert_eq!(app.file_list_state.selected(), Some(1));
+
+        // Moving past the end is a no-op
+        app.select_offset(1);
+        app.move_selected_file(1);
+        assert_eq!(file_names(&app), vec!["2.mp3", "1.mp3", "3.mp3"]);
+
+        app.drop_selected_file();
+        assert_eq!(file_names(&app), vec!["2.mp3", "1.mp3"]);
+        assert_eq!(app.file_list_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn test_dropping_last_file_unchecks_group() {
+        let mut app = app_with_files(&["1.mp3"]);
+        app.focus = Focus::Files;
+        app.drop_selected_file();
+        assert!(!app.rows[0].checked);
+        assert_eq!(app.file_list_state.selected(), None);
+    }
+
+    #[test]
+    fn test_draw_does_not_panic() {
+        let mut app = app_with_files(&["1.mp3", "2.mp3"]);
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("[x] book.mp3"));
+    }
+
+    #[test]
+    fn test_toggle_groups() {
+        let mut app = app_with_files(&["1.mp3"]);
+        assert!(app.rows[0].checked);
+        app.toggle_selected_group();
+        assert!(!app.rows[0].checked);
+        app.toggle_all_groups();
+        assert!(app.rows[0].checked);
+    }
+}
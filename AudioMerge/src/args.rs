@@ -1,12 +1,26 @@
 use clap::Parser;
-use std::path::PathBuf;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
-#[derive(Parser, Debug)]
+use crate::book_meta::{FolderPattern, OutputTemplate};
+use crate::cleanup::AfterMerge;
+use crate::merger::CopyOptions;
+use crate::scanner::ScanOptions;
+use crate::tags;
+use crate::trim::Trim;
+use std::time::Duration;
+
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Source directory to scan for audio files
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Source directory to scan for audio files; repeat to process several roots in one run,
+    /// each merged into its own subfolder of the output directory
     #[arg(short, long, default_value = ".")]
-    pub source: PathBuf,
+    pub source: Vec<PathBuf>,
 
     /// Output directory for merged files
     #[arg(short, long, default_value = "output")]
@@ -16,9 +30,154 @@ pub struct Args {
     #[arg(short, long)]
     pub dry_run: bool,
 
+    /// With --dry-run, only report folders that are new or whose inputs changed since the
+    /// run that wrote this manifest
+    #[arg(long, value_name = "MANIFEST", requires = "dry_run", conflicts_with = "watch")]
+    pub since: Option<PathBuf>,
+
+    /// Only print warnings and errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print more detail, including per-file timings (-vv for even more)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Also append a timestamped log (at least debug level) to this file
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Maximum levels of subdirectories to scan below the source (0 = only the source itself)
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Follow symbolic links while scanning; files reachable through several links are merged once
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// How input files are grouped into merged outputs
+    #[arg(long, value_enum, default_value_t = GroupBy::Folder)]
+    pub group_by: GroupBy,
+
+    /// Read author, title and year from book folder names, e.g. "{author} - {title} ({year})"
+    #[arg(long, value_name = "PATTERN")]
+    pub folder_pattern: Option<FolderPattern>,
+
+    /// Name outputs from the parsed folder fields, relative to the output directory,
+    /// e.g. "{author}/{title} ({year})"
+    #[arg(long, value_name = "TEMPLATE", requires = "folder_pattern")]
+    pub output_name: Option<OutputTemplate>,
+
+    /// What to do when several groups would be written to the same output file
+    #[arg(long, value_enum, default_value_t = OnCollision::Suffix)]
+    pub on_collision: OnCollision,
+
+    /// Format of the dry-run report
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+
+    /// Open an interactive terminal UI to choose groups, reorder or drop files, and watch progress
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Keep running and merge folders as new files arrive in the source directory
+    #[arg(long)]
+    pub watch: bool,
+
+    /// In watch mode, seconds a folder must be unchanged before it is merged
+    #[arg(long, default_value_t = 30)]
+    pub settle_secs: u64,
+
     /// Error handling strategy
     #[arg(long, value_enum, default_value_t = OnError::Skip)]
     pub on_error: OnError,
+
+    /// Maximum allowed difference, in seconds, between the merged file's duration and the sum of its inputs
+    #[arg(long, default_value_t = 1.0, value_parser = parse_tolerance)]
+    pub duration_tolerance: f64,
+
+    /// What to do when files in a folder differ in sample rate, channels or bitrate
+    #[arg(long, value_enum, default_value_t = OnMismatch::Warn)]
+    pub on_mismatch: OnMismatch,
+
+    /// What to do when LAME encoder delay/padding would leave gaps or clicks between files
+    #[arg(long, value_enum, default_value_t = OnGap::Warn)]
+    pub on_gap: OnGap,
+
+    /// Inputs smaller than this (bytes, or with a K/M/G suffix) are treated as corrupt; 0 disables the check
+    #[arg(long, default_value = "1K", value_parser = parse_size)]
+    pub min_file_size: usize,
+
+    /// Write a JSON manifest of every merged output and its inputs to this path
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+
+    /// Write `<output>.chapters.txt` with each source file's start time and title
+    #[arg(long, value_enum)]
+    pub chapters: Option<ChapterFormat>,
+
+    /// Cut this much from the start of every input, e.g. 3s or 500ms (rounded to whole MP3 frames)
+    #[arg(long, default_value = "0s", value_parser = parse_duration)]
+    pub trim_start: Duration,
+
+    /// Cut this much from the end of every input, e.g. 5s
+    #[arg(long, default_value = "0s", value_parser = parse_duration)]
+    pub trim_end: Duration,
+
+    /// Write an extended M3U playlist of each group's original files instead of merging them
+    #[arg(long, value_enum)]
+    pub playlist: Option<PlaylistFormat>,
+
+    /// Do not embed cover.jpg/folder.png from the source folder as the merged file's cover art
+    #[arg(long)]
+    pub no_cover_art: bool,
+
+    /// Set each merged file's modification time to its newest or oldest input's and copy the
+    /// first input's permissions
+    #[arg(long, value_name = "WHICH")]
+    pub preserve_times: Option<PreserveTimes>,
+
+    /// Write a sha256sum-compatible `<output>.sha256` sidecar next to each merged file
+    #[arg(long)]
+    pub write_checksums: bool,
+
+    /// Read buffer size for merging, in bytes or with a K/M/G suffix (e.g. 1M)
+    #[arg(long, default_value = "64K", value_parser = parse_buffer_size)]
+    pub buffer_size: usize,
+
+    /// Limit how fast inputs are read while merging, e.g. 50MB/s (K/M/G suffixes; "/s" optional)
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    pub io_limit: Option<u64>,
+
+    /// Flush each merged file to disk before it is verified and its sources are cleaned up
+    #[arg(long)]
+    pub fsync: bool,
+
+    /// Do not reserve the merged file's full size before writing it
+    #[arg(long)]
+    pub no_preallocate: bool,
+
+    /// What to do with source files after a verified merge: keep, trash or move:<dir>
+    #[arg(long, default_value = "keep")]
+    pub after_merge: AfterMerge,
+}
+
+#[derive(clap::Subcommand, Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Scan the source and write an editable plan file instead of merging
+    Plan {
+        /// Where to write the plan
+        #[arg(default_value = "audiomerge-plan.json")]
+        file: PathBuf,
+    },
+    /// Analyse the planned merges (order, formats, collisions, corrupt files) and report
+    /// every problem without writing anything
+    Check,
+    /// Execute a plan file written by `plan`, exactly as it stands
+    Apply {
+        /// The plan to execute
+        file: PathBuf,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
@@ -28,16 +187,247 @@ pub enum OnError {
     Skip,
 }
 
+impl Args {
+    pub fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            max_depth: self.max_depth,
+            follow_symlinks: self.follow_symlinks,
+        }
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Warn,
+            (false, 0) => LevelFilter::Info,
+            (false, 1) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        }
+    }
+
+    /// The first (usually only) source root.
+    pub fn source_root(&self) -> &Path {
+        self.source.first().map(PathBuf::as_path).unwrap_or(Path::new("."))
+    }
+
+    /// Splits a multi-root run into one single-root run per `--source`. With more than one
+    /// root, each writes to (and moves its sources under) a subfolder named after the root.
+    pub fn per_root(&self) -> Vec<Args> {
+        if self.source.len() <= 1 {
+            return vec![self.clone()];
+        }
+        self.source.iter()
+            .zip(root_labels(&self.source))
+            .map(|(root, label)| Args {
+                source: vec![root.clone()],
+                output: self.output.join(&label),
+                after_merge: match &self.after_merge {
+                    AfterMerge::Move(dir) => AfterMerge::Move(dir.join(&label)),
+                    other => other.clone(),
+                },
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    pub fn trim(&self) -> Trim {
+        Trim { start: self.trim_start, end: self.trim_end }
+    }
+
+    pub fn copy_options(&self) -> CopyOptions {
+        CopyOptions {
+            buffer_size: self.buffer_size,
+            fsync: self.fsync,
+            preallocate: !self.no_preallocate,
+            io_limit: self.io_limit,
+        }
+    }
+}
+
+/// Output subfolder names for each source root: the root's directory name, with " (2)",
+/// " (3)", ... added when two roots share a name (e.g. `/mnt/a/Books` and `/mnt/b/Books`).
+fn root_labels(roots: &[PathBuf]) -> Vec<String> {
+    let mut used = HashSet::new();
+    roots.iter()
+        .map(|root| {
+            let resolved = root.canonicalize().unwrap_or_else(|_| root.clone());
+            let name = resolved.file_name()
+                .map(|name| tags::sanitize_component(&name.to_string_lossy()))
+                .unwrap_or_else(|| "root".to_string());
+            let mut label = name.clone();
+            let mut n = 2;
+            while !used.insert(label.clone()) {
+                label = format!("{} ({})", name, n);
+                n += 1;
+            }
+            label
+        })
+        .collect()
+}
+
+/// Parses a byte count with an optional binary K/M/G suffix: `65536`, `64K`, `1MiB`.
+fn parse_size(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: usize = number.parse().map_err(|_| format!("invalid size '{}'", value))?;
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => return Err(format!("unknown size unit in '{}'", value)),
+    };
+    number.checked_mul(multiplier).ok_or_else(|| format!("size '{}' is too large", value))
+}
+
+/// Parses a duration in seconds, with an optional `ms`, `s` or `m` unit: `3`, `1.5s`, `500ms`, `2m`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid duration '{}'", value))?;
+    let seconds = match unit.trim() {
+        "" | "s" => number,
+        "ms" => number / 1000.0,
+        "m" | "min" => number * 60.0,
+        _ => return Err(format!("unknown duration unit in '{}'", value)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("invalid duration '{}': {}", value, e))
+}
+
+/// Parses a transfer rate such as `50MB/s` or `512K`, in bytes per second.
+fn parse_rate(value: &str) -> Result<u64, String> {
+    let size = value.trim().strip_suffix("/s").unwrap_or(value);
+    match parse_size(size)? {
+        0 => Err("rate must be greater than zero".to_string()),
+        rate => Ok(rate as u64),
+    }
+}
+
+/// Parses a number of seconds that must fit a `Duration`: not negative, NaN or infinite.
+fn parse_tolerance(value: &str) -> Result<f64, String> {
+    let seconds: f64 = value.trim().parse().map_err(|_| format!("invalid number of seconds '{}'", value))?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("invalid tolerance '{}': {}", value, e))?;
+    Ok(seconds)
+}
+
+fn parse_buffer_size(value: &str) -> Result<usize, String> {
+    match parse_size(value)? {
+        0 => Err("buffer size must be greater than zero".to_string()),
+        size => Ok(size),
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum GroupBy {
+    /// One output per directory
+    Folder,
+    /// One output per book directory, with disc subfolders (CD1, Disc 2, Part 3) merged in disc order
+    Book,
+    /// One output per album tag
+    Album,
+    /// One output per album tag, nested under the album artist
+    AlbumArtist,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum OnCollision {
+    /// Append " (2)", " (3)", ... to later outputs
+    Suffix,
+    /// Merge only the first group for each output
+    Skip,
+    /// Refuse to run
+    Error,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum ChapterFormat {
+    /// ffmpeg metadata file (`ffmpeg -i merged.mp3 -i chapters.txt -map_metadata 1`)
+    Ffmetadata,
+    /// Audacity label track (tab-separated start, end, title)
+    Audacity,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum PlaylistFormat {
+    /// `<output>.m3u`
+    M3u,
+    /// `<output>.m3u8`
+    M3u8,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum PreserveTimes {
+    /// The most recently modified input's time
+    Newest,
+    /// The least recently modified input's time
+    Oldest,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum OnMismatch {
+    /// Report the mismatch and merge anyway
+    Warn,
+    /// Report the mismatch and leave the folder unmerged
+    Skip,
+    /// Re-encode the folder through ffmpeg to the first file's format
+    Reencode,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum OnGap {
+    /// Report the joins and merge anyway
+    Warn,
+    /// Merge without checking
+    Ignore,
+    /// Re-encode the folder through ffmpeg, which honours the gapless data
+    Reencode,
+}
+
 #[cfg(test)]
+#[allow(clippy::needless_borrows_for_generic_args, clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_args_parsing_defaults() {
         let args = Args::parse_from(&["app"]);
-        assert_eq!(args.source, PathBuf::from("."));
+        assert_eq!(args.command, None);
+        assert_eq!(args.source, vec![PathBuf::from(".")]);
         assert_eq!(args.output, PathBuf::from("output"));
         assert_eq!(args.dry_run, false);
+        assert_eq!(args.since, None);
+        assert_eq!(args.log_level(), LevelFilter::Info);
+        assert_eq!(args.log_file, None);
+        assert_eq!(args.format, ReportFormat::Text);
+        assert_eq!(args.max_depth, None);
+        assert!(!args.follow_symlinks);
+        assert_eq!(args.group_by, GroupBy::Folder);
+        assert_eq!(args.folder_pattern, None);
+        assert_eq!(args.output_name, None);
+        assert!(!args.tui);
+        assert!(!args.watch);
+        assert_eq!(args.settle_secs, 30);
+        assert_eq!(args.duration_tolerance, 1.0);
+        assert_eq!(args.on_mismatch, OnMismatch::Warn);
+        assert_eq!(args.on_gap, OnGap::Warn);
+        assert_eq!(args.on_collision, OnCollision::Suffix);
+        assert_eq!(args.min_file_size, 1024);
+        assert_eq!(args.manifest, None);
+        assert_eq!(args.chapters, None);
+        assert_eq!(args.copy_options(), CopyOptions::default());
+        assert_eq!(args.playlist, None);
+        assert!(!args.no_cover_art);
+        assert!(args.trim().is_zero());
+        assert!(!args.write_checksums);
+        assert_eq!(args.preserve_times, None);
+        assert_eq!(args.after_merge, AfterMerge::Keep);
         assert_eq!(args.on_error, OnError::Skip);
     }
 
@@ -48,11 +438,146 @@ mod tests {
             "--source", "src_dir",
             "--output", "out_dir",
             "--dry-run",
+            "-vv",
+            "--log-file", "merge.log",
+            "--format", "json",
+            "--max-depth", "2",
+            "--follow-symlinks",
+            "--group-by", "album-artist",
+            "--folder-pattern", "{author} - {title}",
+            "--output-name", "{author}/{title}",
+            "--on-collision", "error",
+            "--tui",
+            "--watch",
+            "--settle-secs", "5",
             "--on-error", "halt",
+            "--duration-tolerance", "2.5",
+            "--on-mismatch", "reencode",
+            "--on-gap", "ignore",
+            "--min-file-size", "0",
+            "--manifest", "run.json",
+            "--chapters", "audacity",
+            "--playlist", "m3u8",
+            "--no-cover-art",
+            "--trim-start", "3s",
+            "--trim-end", "500ms",
+            "--write-checksums",
+            "--preserve-times", "oldest",
+            "--buffer-size", "4M",
+            "--io-limit", "50MB/s",
+            "--fsync",
+            "--no-preallocate",
+            "--after-merge", "move:done",
         ]);
-        assert_eq!(args.source, PathBuf::from("src_dir"));
+        assert_eq!(args.source, vec![PathBuf::from("src_dir")]);
         assert_eq!(args.output, PathBuf::from("out_dir"));
         assert_eq!(args.dry_run, true);
+        assert_eq!(args.log_level(), LevelFilter::Trace);
+        assert_eq!(args.log_file, Some(PathBuf::from("merge.log")));
+        assert_eq!(args.format, ReportFormat::Json);
+        assert_eq!(args.max_depth, Some(2));
+        assert!(args.follow_symlinks);
+        assert_eq!(args.group_by, GroupBy::AlbumArtist);
+        assert_eq!(args.folder_pattern, Some("{author} - {title}".parse().unwrap()));
+        assert_eq!(args.output_name, Some("{author}/{title}".parse().unwrap()));
+        assert_eq!(args.on_collision, OnCollision::Error);
+        assert!(args.tui);
+        assert!(args.watch);
+        assert_eq!(args.settle_secs, 5);
+        assert_eq!(args.on_error, OnError::Halt);
+        assert_eq!(args.duration_tolerance, 2.5);
+        assert_eq!(args.on_mismatch, OnMismatch::Reencode);
+        assert_eq!(args.on_gap, OnGap::Ignore);
+        assert_eq!(args.min_file_size, 0);
+        assert_eq!(args.manifest, Some(PathBuf::from("run.json")));
+        assert_eq!(args.chapters, Some(ChapterFormat::Audacity));
+        assert_eq!(args.playlist, Some(PlaylistFormat::M3u8));
+        assert!(args.no_cover_art);
+        assert_eq!(args.trim(), Trim { start: Duration::from_secs(3), end: Duration::from_millis(500) });
+        assert!(args.write_checksums);
+        assert_eq!(args.preserve_times, Some(PreserveTimes::Oldest));
+        assert_eq!(
+            args.copy_options(),
+            CopyOptions { buffer_size: 4 << 20, fsync: true, preallocate: false, io_limit: Some(50 << 20) }
+        );
+        assert_eq!(args.after_merge, AfterMerge::Move(PathBuf::from("done")));
+    }
+
+    #[test]
+    fn test_args_parsing_subcommands() {
+        let args = Args::parse_from(["app", "--source", "books", "plan"]);
+        assert_eq!(args.source, vec![PathBuf::from("books")]);
+        assert_eq!(args.command, Some(Command::Plan { file: PathBuf::from("audiomerge-plan.json") }));
+
+        assert!(Args::try_parse_from(["app", "--output-name", "{title}"]).is_err());
+        assert!(Args::try_parse_from(["app", "--since", "run.json"]).is_err());
+        let args = Args::parse_from(["app", "--dry-run", "--since", "run.json"]);
+        assert_eq!(args.since, Some(PathBuf::from("run.json")));
+
+        let args = Args::parse_from(["app", "-s", "a", "-s", "b", "check"]);
+        assert_eq!(args.command, Some(Command::Check));
+
+        let args = Args::parse_from(["app", "--on-error", "halt", "apply", "edited.json"]);
         assert_eq!(args.on_error, OnError::Halt);
+        assert_eq!(args.command, Some(Command::Apply { file: PathBuf::from("edited.json") }));
+    }
+
+    #[test]
+    fn test_per_root() {
+        let args = Args::parse_from(["app", "-s", "books"]);
+        assert_eq!(args.per_root()[0].output, PathBuf::from("output"));
+
+        let args = Args::parse_from([
+            "app", "-s", "/mnt/a/Books", "--source", "/mnt/b/Books", "-s", "/mnt/b/Podcasts",
+            "-o", "merged", "--after-merge", "move:done",
+        ]);
+        let runs = args.per_root();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[1].source, vec![PathBuf::from("/mnt/b/Books")]);
+        assert_eq!(runs[0].output, PathBuf::from("merged/Books"));
+        assert_eq!(runs[1].output, PathBuf::from("merged/Books (2)"));
+        assert_eq!(runs[2].output, PathBuf::from("merged/Podcasts"));
+        assert_eq!(runs[2].after_merge, AfterMerge::Move(PathBuf::from("done/Podcasts")));
+    }
+
+    #[test]
+    fn test_log_levels() {
+        assert_eq!(Args::parse_from(["app", "-q"]).log_level(), LevelFilter::Warn);
+        assert_eq!(Args::parse_from(["app", "--verbose"]).log_level(), LevelFilter::Debug);
+        assert!(Args::try_parse_from(["app", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("3"), Ok(Duration::from_secs(3)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("3h").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn test_duration_tolerance() {
+        assert_eq!(Args::parse_from(["app", "--duration-tolerance", "0"]).duration_tolerance, 0.0);
+        assert!(Args::try_parse_from(["app", "--duration-tolerance=-1"]).is_err());
+        assert!(Args::try_parse_from(["app", "--duration-tolerance", "NaN"]).is_err());
+        assert!(Args::try_parse_from(["app", "--duration-tolerance", "inf"]).is_err());
+        assert!(Args::try_parse_from(["app", "--duration-tolerance", "1e300"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("65536"), Ok(65536));
+        assert_eq!(parse_size("64K"), Ok(64 * 1024));
+        assert_eq!(parse_size("1MiB"), Ok(1 << 20));
+        assert_eq!(parse_size("2 gb"), Ok(2 << 30));
+        assert_eq!(parse_size("0"), Ok(0));
+        assert!(parse_buffer_size("0").is_err());
+        assert_eq!(parse_rate("50MB/s"), Ok(50 << 20));
+        assert_eq!(parse_rate("512K"), Ok(512 << 10));
+        assert!(parse_rate("0/s").is_err());
+        assert!(parse_size("12X").is_err());
+        assert!(parse_size("M").is_err());
     }
 }
@@ -0,0 +1,63 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::args::PreserveTimes;
+
+/// Gives the merged file the newest or oldest input's modification time and the first
+/// input's permissions, so it sorts alongside its sources rather than as "new today".
+pub fn preserve_attributes(output: &Path, inputs: &[PathBuf], times: &PreserveTimes) -> io::Result<()> {
+    let Some(first) = inputs.first() else {
+        return Ok(());
+    };
+    let modified = inputs.iter()
+        .map(|path| fs::metadata(path)?.modified())
+        .collect::<io::Result<Vec<SystemTime>>>()?;
+    let mtime = match times {
+        PreserveTimes::Newest => modified.into_iter().max(),
+        PreserveTimes::Oldest => modified.into_iter().min(),
+    };
+
+    if let Some(mtime) = mtime {
+        // Opened for writing because setting times needs write access on some platforms,
+        // so this has to happen before a read-only mode is copied over
+        File::options().write(true).open(output)?.set_modified(mtime)?;
+    }
+    fs::set_permissions(output, fs::metadata(first)?.permissions())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_preserve_attributes() {
+        let temp_dir = TempDir::new().unwrap();
+        let inputs: Vec<PathBuf> = (1..=3).map(|i| temp_dir.path().join(format!("{}.mp3", i))).collect();
+        let output = temp_dir.path().join("out.mp3");
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        for (i, path) in inputs.iter().enumerate() {
+            fs::write(path, b"abc").unwrap();
+            File::options().write(true).open(path).unwrap().set_modified(base + Duration::from_secs(i as u64 * 60)).unwrap();
+        }
+        fs::write(&output, b"abcabcabc").unwrap();
+        let mut readonly = fs::metadata(&inputs[0]).unwrap().permissions();
+        readonly.set_readonly(true);
+        fs::set_permissions(&inputs[0], readonly).unwrap();
+
+        preserve_attributes(&output, &inputs, &PreserveTimes::Newest).unwrap();
+        let metadata = fs::metadata(&output).unwrap();
+        assert_eq!(metadata.modified().unwrap(), base + Duration::from_secs(120));
+        assert!(metadata.permissions().readonly());
+
+        let mut writable = metadata.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        writable.set_readonly(false);
+        fs::set_permissions(&output, writable).unwrap();
+        preserve_attributes(&output, &inputs[1..], &PreserveTimes::Oldest).unwrap();
+        assert_eq!(fs::metadata(&output).unwrap().modified().unwrap(), base + Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,294 @@
+use id3::{Tag, TagLike, Version};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::planner::PlannedGroup;
+use crate::tags;
+
+/// Audiobook details read from a folder name such as `Author - Title (Year)`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BookMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Author,
+    Title,
+    Year,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "author" => Ok(Field::Author),
+            "title" => Ok(Field::Title),
+            "year" => Ok(Field::Year),
+            other => Err(format!("unknown field '{{{}}}' (expected {{author}}, {{title}} or {{year}})", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+/// Splits `{author} - {title}` into literals and fields.
+fn parse_parts(pattern: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        if open > 0 {
+            parts.push(Part::Literal(rest[..open].to_string()));
+        }
+        let close = rest[open..].find('}').ok_or_else(|| format!("unclosed '{{' in '{}'", pattern))? + open;
+        parts.push(Part::Field(rest[open + 1..close].parse()?));
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+/// How book folders are named, e.g. `{author} - {title} ({year})`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FolderPattern {
+    parts: Vec<Part>,
+}
+
+impl FromStr for FolderPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = parse_parts(s)?;
+        if parts.windows(2).any(|pair| matches!(pair, [Part::Field(_), Part::Field(_)])) {
+            return Err(format!("fields in '{}' must be separated by text", s));
+        }
+        if !parts.iter().any(|part| matches!(part, Part::Field(_))) {
+            return Err(format!("'{}' has no {{author}}, {{title}} or {{year}} field", s));
+        }
+        Ok(FolderPattern { parts })
+    }
+}
+
+impl FolderPattern {
+    /// Matches the whole folder name against the pattern. Each field takes the shortest text
+    /// that lets the rest match, so `{author} - {title}` splits at the first " - ".
+    pub fn parse_name(&self, name: &str) -> Option<BookMeta> {
+        let mut meta = BookMeta::default();
+        match_parts(&self.parts, name, &mut meta).then_some(meta)
+    }
+}
+
+fn match_parts(parts: &[Part], text: &str, meta: &mut BookMeta) -> bool {
+    match parts {
+        [] => text.is_empty(),
+        [Part::Literal(literal), rest @ ..] => {
+            text.strip_prefix(literal.as_str()).is_some_and(|text| match_parts(rest, text, meta))
+        }
+        [Part::Field(field), rest @ ..] => {
+            // Candidate ends: where the next literal occurs, or the end of the name
+            let ends: Vec<usize> = match rest.first() {
+                Some(Part::Literal(literal)) => text.match_indices(literal.as_str()).map(|(i, _)| i).collect(),
+                _ => vec![text.len()],
+            };
+            ends.into_iter().any(|end| {
+                let value = text[..end].trim();
+                set_field(meta, *field, value) && match_parts(rest, &text[end..], meta)
+            })
+        }
+    }
+}
+
+fn set_field(meta: &mut BookMeta, field: Field, value: &str) -> bool {
+    if value.is_empty() {
+        return false;
+    }
+    match field {
+        Field::Author => meta.author = Some(value.to_string()),
+        Field::Title => meta.title = Some(value.to_string()),
+        Field::Year => match value.parse() {
+            Ok(year) if value.len() == 4 && value.chars().all(|c| c.is_ascii_digit()) => meta.year = Some(year),
+            _ => return false,
+        },
+    }
+    true
+}
+
+/// Output path template relative to the output directory, e.g. `{author}/{title} ({year})`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    parts: Vec<Part>,
+}
+
+impl FromStr for OutputTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(OutputTemplate { parts: parse_parts(s)? })
+    }
+}
+
+impl OutputTemplate {
+    /// Renders the template, or `None` if it uses a field the folder name did not provide.
+    /// Each `/`-separated component is sanitized on its own, so fields cannot add directories.
+    pub fn render(&self, meta: &BookMeta) -> Option<PathBuf> {
+        let mut components = vec![String::new()];
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => {
+                    let mut pieces = literal.split('/');
+                    components.last_mut()?.push_str(pieces.next().unwrap_or_default());
+                    components.extend(pieces.map(String::from));
+                }
+                Part::Field(field) => {
+                    let value = match field {
+                        Field::Author => meta.author.clone()?,
+                        Field::Title => meta.title.clone()?,
+                        Field::Year => meta.year?.to_string(),
+                    };
+                    components.last_mut()?.push_str(&value.replace('/', "_"));
+                }
+            }
+        }
+        let mut path: PathBuf = components.iter()
+            .filter(|component| !component.trim().is_empty())
+            .map(|component| tags::sanitize_component(component))
+            .collect();
+        path.as_mut_os_string().push(".mp3");
+        Some(path)
+    }
+}
+
+/// Attaches the metadata parsed from each group's folder name and, with a template, renames
+/// its output. Groups whose folder does not match the pattern are left as planned.
+pub fn apply(plan: &mut [PlannedGroup], pattern: &FolderPattern, template: Option<&OutputTemplate>, output_dir: &Path) {
+    for group in plan {
+        let name = group.source_dir.file_name().unwrap_or_default().to_string_lossy();
+        let Some(meta) = pattern.parse_name(&name) else {
+            log::debug!("'{}' does not match the folder pattern", group.source_dir.display());
+            continue;
+        };
+        if let Some(path) = template.and_then(|template| template.render(&meta)) {
+            group.output = output_dir.join(path);
+        }
+        group.meta = Some(meta);
+    }
+}
+
+/// Writes the known fields into the merged file's ID3 tag, keeping everything else.
+pub fn write_tags(output: &Path, meta: &BookMeta) -> io::Result<()> {
+    let mut tag = match Tag::read_from_path(output) {
+        Ok(tag) => tag,
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Tag::new(),
+        Err(e) => return Err(io::Error::other(e)),
+    };
+    let version = if tag.frames().next().is_some() { tag.version() } else { Version::Id3v24 };
+    if let Some(author) = &meta.author {
+        tag.set_artist(author);
+        tag.set_album_artist(author);
+    }
+    if let Some(title) = &meta.title {
+        tag.set_album(title);
+        tag.set_title(title);
+    }
+    if let Some(year) = meta.year {
+        tag.set_year(year);
+    }
+    tag.write_to_path(output, version).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp3;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn meta(author: &str, title: &str, year: Option<i32>) -> BookMeta {
+        BookMeta { author: Some(author.to_string()), title: Some(title.to_string()), year }
+    }
+
+    #[test]
+    fn test_folder_pattern() {
+        let pattern: FolderPattern = "{author} - {title} ({year})".parse().unwrap();
+        assert_eq!(
+            pattern.parse_name("Frank Herbert - Dune (1965)"),
+            Some(meta("Frank Herbert", "Dune", Some(1965)))
+        );
+        assert_eq!(
+            pattern.parse_name("Le Guin - The Dispossessed - An Ambiguous Utopia (1974)"),
+            Some(meta("Le Guin", "The Dispossessed - An Ambiguous Utopia", Some(1974)))
+        );
+        assert_eq!(pattern.parse_name("Frank Herbert - Dune"), None);
+        assert_eq!(pattern.parse_name("Frank Herbert - Dune (new)"), None);
+
+        let pattern: FolderPattern = "{title}".parse().unwrap();
+        assert_eq!(pattern.parse_name("Dune").and_then(|m| m.title), Some("Dune".to_string()));
+
+        assert!("{author}{title}".parse::<FolderPattern>().is_err());
+        assert!("{narrator} - {title}".parse::<FolderPattern>().is_err());
+        assert!("Books".parse::<FolderPattern>().is_err());
+        assert!("{author".parse::<FolderPattern>().is_err());
+    }
+
+    #[test]
+    fn test_output_template() {
+        let template: OutputTemplate = "{author}/{title} ({year})".parse().unwrap();
+        assert_eq!(
+            template.render(&meta("Frank Herbert", "Dune", Some(1965))),
+            Some(PathBuf::from("Frank Herbert/Dune (1965).mp3"))
+        );
+        assert_eq!(
+            template.render(&meta("AC/DC", "Live: 1991", Some(1992))),
+            Some(PathBuf::from("AC_DC/Live_ 1991 (1992).mp3"))
+        );
+        assert_eq!(template.render(&meta("Frank Herbert", "Dune", None)), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let group = |dir: &str| PlannedGroup {
+            source_dir: PathBuf::from(dir),
+            output: PathBuf::from("out/x.mp3"),
+            files: vec![],
+            trims: Default::default(),
+            meta: None,
+        };
+        let mut plan = vec![group("src/Frank Herbert - Dune (1965)"), group("src/misc")];
+        let pattern: FolderPattern = "{author} - {title} ({year})".parse().unwrap();
+        let template: OutputTemplate = "{author}/{title}".parse().unwrap();
+
+        apply(&mut plan, &pattern, Some(&template), Path::new("out"));
+        assert_eq!(plan[0].output, PathBuf::from("out/Frank Herbert/Dune.mp3"));
+        assert_eq!(plan[0].meta, Some(meta("Frank Herbert", "Dune", Some(1965))));
+        assert_eq!(plan[1], group("src/misc"));
+    }
+
+    #[test]
+    fn test_write_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.mp3");
+        fs::write(&path, mp3::synth_frames(2)).unwrap();
+
+        write_tags(&path, &meta("Frank Herbert", "Dune", Some(1965))).unwrap();
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.artist(), Some("Frank Herbert"));
+        assert_eq!(tag.album_artist(), Some("Frank Herbert"));
+        assert_eq!(tag.album(), Some("Dune"));
+        assert_eq!(tag.year(), Some(1965));
+        assert_eq!(mp3::scan(&path).unwrap().frames, 2);
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::args::ChapterFormat;
+use crate::mp3::StreamInfo;
+use crate::tags;
+
+/// One source file's span within the merged output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// Lays the inputs end to end. Titles come from the ID3 title tag, falling back to the file name.
+pub fn build_chapters(files: &[PathBuf], infos: &[StreamInfo]) -> Vec<Chapter> {
+    let mut start = Duration::ZERO;
+    files.iter()
+        .zip(infos)
+        .map(|(path, info)| {
+            let chapter = Chapter { title: tags::display_title(path), start, end: start + info.duration };
+            start = chapter.end;
+            chapter
+        })
+        .collect()
+}
+
+/// `<output stem>.chapters.txt` next to the merged file.
+pub fn chapters_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!("{}.chapters.txt", stem))
+}
+
+pub fn format_chapters(chapters: &[Chapter], format: &ChapterFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ChapterFormat::Ffmetadata => {
+            out.push_str(";FFMETADATA1\n");
+            for chapter in chapters {
+                out.push_str("[CHAPTER]\nTIMEBASE=1/1000\n");
+                let _ = writeln!(out, "START={}", chapter.start.as_millis());
+                let _ = writeln!(out, "END={}", chapter.end.as_millis());
+                let _ = writeln!(out, "title={}", escape_ffmetadata(&chapter.title));
+            }
+        }
+        ChapterFormat::Audacity => {
+            for chapter in chapters {
+                let _ = writeln!(
+                    out,
+                    "{:.6}\t{:.6}\t{}",
+                    chapter.start.as_secs_f64(),
+                    chapter.end.as_secs_f64(),
+                    chapter.title.replace(['\t', '\n'], " ")
+                );
+            }
+        }
+    }
+    out
+}
+
+/// ffmpeg's metadata format treats `=`, `;`, `#`, `\` and newlines as special.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> Vec<Chapter> {
+        vec![
+            Chapter { title: "Intro".to_string(), start: Duration::ZERO, end: Duration::from_millis(1500) },
+            Chapter {
+                title: "Part 1; a=b".to_string(),
+                start: Duration::from_millis(1500),
+                end: Duration::from_millis(4000),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_build_chapters() {
+        let files = vec![PathBuf::from("missing/01 Intro.mp3"), PathBuf::from("missing/02.mp3")];
+        let infos = vec![
+            StreamInfo { duration: Duration::from_secs(3), ..Default::default() },
+            StreamInfo { duration: Duration::from_secs(5), ..Default::default() },
+        ];
+
+        let chapters = build_chapters(&files, &infos);
+        assert_eq!(chapters[0].title, "01 Intro");
+        assert_eq!(chapters[0].end, Duration::from_secs(3));
+        assert_eq!(chapters[1].title, "02");
+        assert_eq!(chapters[1].start, Duration::from_secs(3));
+        assert_eq!(chapters[1].end, Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_format_ffmetadata() {
+        let text = format_chapters(&chapters(), &ChapterFormat::Ffmetadata);
+        assert!(text.starts_with(";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=Intro\n"));
+        assert!(text.contains("START=1500\nEND=4000\ntitle=Part 1\\; a\\=b\n"));
+    }
+
+    #[test]
+    fn test_format_audacity() {
+        let text = format_chapters(&chapters(), &ChapterFormat::Audacity);
+        assert_eq!(text, "0.000000\t1.500000\tIntro\n1.500000\t4.000000\tPart 1; a=b\n");
+    }
+
+    #[test]
+    fn test_chapters_path() {
+        assert_eq!(chapters_path(Path::new("out/book.mp3")), PathBuf::from("out/book.chapters.txt"));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::args::{Args, OnCollision, OnGap, OnMismatch};
+use crate::checksum;
+use crate::gapless;
+use crate::mismatch;
+use crate::mp3::{self, StreamInfo};
+use crate::planner::{self, PlannedGroup};
+use crate::tags;
+use crate::validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// One thing a merge of the group would trip over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    fn error(message: String) -> Self {
+        Finding { severity: Severity::Error, message }
+    }
+
+    fn warning(message: String) -> Self {
+        Finding { severity: Severity::Warning, message }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupCheck {
+    pub group: PlannedGroup,
+    pub findings: Vec<Finding>,
+}
+
+impl GroupCheck {
+    pub fn worst(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).reduce(|a, b| if b > a { b } else { a })
+    }
+}
+
+/// Runs every pre-merge analysis over the plan without writing anything: collisions,
+/// unreadable or corrupt inputs, checksum sidecars, format mismatches, encoder gaps and
+/// file order. Unlike a real run, nothing stops at the first problem.
+pub fn check_plan(plan: Vec<PlannedGroup>, args: &Args) -> Vec<GroupCheck> {
+    let (_, collisions) = planner::resolve_collisions(plan.clone(), &args.on_collision);
+    let mut collision_findings: HashMap<PathBuf, Vec<Finding>> = HashMap::new();
+    for collision in collisions {
+        let message = format!(
+            "output '{}' is also claimed by '{}'",
+            collision.output.display(),
+            collision.claimed_by.display()
+        );
+        let finding = match (&collision.renamed_to, &args.on_collision) {
+            (Some(renamed), _) => Finding::warning(format!("{}; would be written to '{}'", message, renamed.display())),
+            (None, OnCollision::Skip) => Finding::warning(format!("{}; would be skipped", message)),
+            (None, _) => Finding::error(format!("{}; the run would refuse to start", message)),
+        };
+        collision_findings.entry(collision.source_dir).or_default().push(finding);
+    }
+
+    plan.into_iter()
+        .map(|group| {
+            let mut findings = collision_findings.remove(&group.source_dir).unwrap_or_default();
+            findings.extend(check_group(&group, args));
+            GroupCheck { group, findings }
+        })
+        .collect()
+}
+
+fn check_group(group: &PlannedGroup, args: &Args) -> Vec<Finding> {
+    let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut findings = Vec::new();
+
+    let mut files = Vec::new();
+    let mut infos: Vec<StreamInfo> = Vec::new();
+    for file in &group.files {
+        match mp3::scan(file) {
+            Ok(info) => {
+                files.push(file.clone());
+                infos.push(info);
+            }
+            Err(e) => findings.push(Finding::error(format!("{}: cannot be read: {}", name(file), e))),
+        }
+    }
+
+    for (file, info) in files.iter().zip(&infos) {
+        match validate::check_input(file, info, args.min_file_size as u64) {
+            Ok(Some(problem)) => findings.push(Finding::error(format!("{}: {}", name(file), problem))),
+            Ok(None) => {}
+            Err(e) => findings.push(Finding::error(format!("{}: cannot be read: {}", name(file), e))),
+        }
+        match checksum::read_sidecar(file) {
+            Ok(Some(expected)) => match checksum::sha256_file(file) {
+                Ok(digest) if digest != expected => {
+                    findings.push(Finding::error(format!("{}: does not match its .sha256 sidecar", name(file))));
+                }
+                Ok(_) => {}
+                Err(e) => findings.push(Finding::error(format!("{}: cannot be hashed: {}", name(file), e))),
+            },
+            Ok(None) => {}
+            Err(e) => findings.push(Finding::warning(format!("{}: unreadable checksum sidecar: {}", name(file), e))),
+        }
+    }
+
+    let (reference, mismatches) = mismatch::find_mismatches(&files, &infos);
+    if let Some(reference) = reference {
+        let consequence = match args.on_mismatch {
+            OnMismatch::Warn => "the folder would be merged anyway",
+            OnMismatch::Skip => "the folder would be skipped",
+            OnMismatch::Reencode => "the folder would be re-encoded",
+        };
+        for m in &mismatches {
+            findings.push(Finding::warning(format!(
+                "{}: format {} differs from the first file's {}; {}",
+                name(&m.path),
+                m.format,
+                reference,
+                consequence
+            )));
+        }
+    }
+
+    if args.on_gap == OnGap::Warn && mismatches.is_empty() {
+        for gap in gapless::find_gaps(&files, &infos) {
+            findings.push(Finding::warning(format!(
+                "{} -> {}: encoder delay/padding leaves {} ms of silence at the join",
+                name(&gap.after),
+                name(&gap.before),
+                gap.duration.as_millis()
+            )));
+        }
+    }
+
+    findings.extend(check_order(&files).into_iter().map(Finding::warning));
+
+    if group.output.exists() {
+        findings.push(Finding::warning(format!("output '{}' already exists and would be replaced", group.output.display())));
+    }
+
+    findings
+}
+
+/// Flags files whose ID3 disc/track numbers disagree with the planned order, and
+/// duplicate track numbers. Files without a track tag are not compared.
+fn check_order(files: &[PathBuf]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen: HashMap<(u32, u32), &PathBuf> = HashMap::new();
+    let mut previous: Option<(u32, u32, &PathBuf)> = None;
+    for file in files {
+        let tags = tags::read_tags(file);
+        let Some(track) = tags.track else {
+            continue;
+        };
+        let key = (tags.disc.unwrap_or(1), track);
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if let Some(first) = seen.insert(key, file) {
+            problems.push(format!(
+                "{}: has the same disc/track number ({}/{}) as {}",
+                name,
+                key.0,
+                key.1,
+                first.file_name().unwrap_or_default().to_string_lossy()
+            ));
+        } else if let Some((disc, prev_track, prev)) = previous && (disc, prev_track) > key {
+            problems.push(format!(
+                "{}: track {}/{} is ordered after {} (track {}/{}); check the file names",
+                name,
+                key.0,
+                key.1,
+                prev.file_name().unwrap_or_default().to_string_lossy(),
+                disc,
+                prev_track
+            ));
+        }
+        previous = Some((key.0, key.1, file));
+    }
+    problems
+}
+
+pub fn format_check(checks: &[GroupCheck]) -> String {
+    let mut output = String::new();
+    output.push_str("Check Results:\n");
+    output.push_str("--------------\n");
+
+    for check in checks {
+        output.push_str(&format!("Source: {}\n", check.group.source_dir.display()));
+        output.push_str(&format!("Output: {}\n", check.group.output.display()));
+        output.push_str(&format!("Files:  {}\n", check.group.files.len()));
+        if check.findings.is_empty() {
+            output.push_str("  ok\n");
+        }
+        for finding in &check.findings {
+            output.push_str(&format!("  {}: {}\n", finding.severity, finding.message));
+        }
+        output.push('\n');
+    }
+
+    let count = |severity| checks.iter().filter(|c| c.worst() == severity).count();
+    output.push_str(&format!(
+        "Checked {} folders: {} ok, {} with warnings, {} with errors.\n",
+        checks.len(),
+        count(None),
+        count(Some(Severity::Warning)),
+        count(Some(Severity::Error))
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use id3::{Tag, TagLike, Version};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn group(dir: &Path, output: &str, files: &[&str]) -> PlannedGroup {
+        PlannedGroup {
+            source_dir: dir.to_path_buf(),
+            output: PathBuf::from(output),
+            files: files.iter().map(|f| dir.join(f)).collect(),
+            trims: Default::default(),
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn test_check_plan() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = temp_dir.path().join("good");
+        let bad = temp_dir.path().join("bad");
+        fs::create_dir(&good).unwrap();
+        fs::create_dir(&bad).unwrap();
+        fs::write(good.join("1.mp3"), mp3::synth_frames(10)).unwrap();
+        fs::write(good.join("2.mp3"), mp3::synth_frames(10)).unwrap();
+        fs::write(bad.join("1.mp3"), b"<!DOCTYPE html><html>not found</html>").unwrap();
+        fs::write(bad.join("2.mp3"), mp3::synth_frames(10)).unwrap();
+        checksum::write_sidecar(&bad.join("2.mp3"), "0000").unwrap();
+
+        let args = Args::parse_from(["app", "--min-file-size", "0", "--on-collision", "error"]);
+        let plan = vec![
+            group(&good, "out/book.mp3", &["1.mp3", "2.mp3"]),
+            group(&bad, "out/book.mp3", &["1.mp3", "2.mp3", "3.mp3"]),
+        ];
+        let checks = check_plan(plan, &args);
+
+        assert!(checks[0].findings.is_empty());
+        assert_eq!(checks[1].worst(), Some(Severity::Error));
+        let messages: Vec<_> = checks[1].findings.iter().map(|f| f.message.as_str()).collect();
+        assert!(messages[0].contains("also claimed by"));
+        assert!(messages.iter().any(|m| m.starts_with("3.mp3: cannot be read")));
+        assert!(messages.iter().any(|m| m.starts_with("1.mp3: looks like an HTML page")));
+        assert!(messages.iter().any(|m| m.starts_with("2.mp3: does not match its .sha256 sidecar")));
+
+        let report = format_check(&checks);
+        assert!(report.contains("Files:  2\n  ok\n"));
+        assert!(report.contains("  error: 3.mp3: cannot be read"));
+        assert!(report.ends_with("Checked 2 folders: 1 ok, 0 with warnings, 1 with errors.\n"));
+    }
+
+    #[test]
+    fn test_check_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<PathBuf> = ["a.mp3", "b.mp3", "c.mp3", "d.mp3"].iter().map(|f| temp_dir.path().join(f)).collect();
+        for (file, track) in files.iter().zip([1, 3, 2, 2]) {
+            fs::write(file, mp3::synth_frames(2)).unwrap();
+            let mut tag = Tag::new();
+            tag.set_track(track);
+            tag.write_to_path(file, Version::Id3v24).unwrap();
+        }
+
+        let problems = check_order(&files);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("c.mp3: track 1/2 is ordered after b.mp3 (track 1/3)"));
+        assert!(problems[1].starts_with("d.mp3: has the same disc/track number (1/2) as c.mp3"));
+        assert!(check_order(&files[..2]).is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Returns the lowercase hex SHA-256 digest of the file's contents.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path)?;
+    io::copy(&mut file, &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Location of a file's checksum sidecar: `<file>.sha256`.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Returns the digest recorded in the file's sidecar, or `None` if there is no sidecar.
+pub fn read_sidecar(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(sidecar_path(path)) {
+        Ok(content) => Ok(content.split_whitespace().next().map(str::to_lowercase)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes the digest in the format produced by `sha256sum`, so `sha256sum -c` can verify it.
+pub fn write_sidecar(path: &Path, digest: &str) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    fs::write(sidecar_path(path), format!("{}  {}\n", digest, name))
+}
+
+/// Compares each file's digest against its sidecar (where one exists) and returns the
+/// files that no longer match, e.g. because of bit-rot or an incomplete copy.
+pub fn find_sidecar_mismatches(files: &[PathBuf], digests: &[String]) -> io::Result<Vec<PathBuf>> {
+    let mut mismatched = Vec::new();
+    for (path, digest) in files.iter().zip(digests) {
+        if let Some(expected) = read_sidecar(path)? && &expected != digest {
+            mismatched.push(path.clone());
+        }
+    }
+    Ok(mismatched)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sha256_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_sidecars() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = temp_dir.path().join("good.mp3");
+        let rotten = temp_dir.path().join("rotten.mp3");
+        let unchecked = temp_dir.path().join("unchecked.mp3");
+        for path in [&good, &rotten, &unchecked] {
+            fs::write(path, b"abc").unwrap();
+        }
+        let digest = sha256_file(&good).unwrap();
+        write_sidecar(&good, &digest).unwrap();
+        write_sidecar(&rotten, &digest).unwrap();
+        fs::write(&rotten, b"abd").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("good.mp3.sha256")).unwrap(),
+            format!("{}  good.mp3\n", digest)
+        );
+        assert_eq!(read_sidecar(&unchecked).unwrap(), None);
+
+        let files = vec![good.clone(), rotten.clone(), unchecked.clone()];
+        let digests: Vec<_> = files.iter().map(|f| sha256_file(f).unwrap()).collect();
+        assert_eq!(find_sidecar_mismatches(&files, &digests).unwrap(), vec![rotten]);
+    }
+}
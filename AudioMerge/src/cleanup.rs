@@ -0,0 +1,90 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// What happens to the source chapter files once their merge has been verified.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AfterMerge {
+    Keep,
+    Trash,
+    /// Relocate sources under this directory, preserving their path relative to the source root.
+    Move(PathBuf),
+}
+
+impl FromStr for AfterMerge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(AfterMerge::Keep),
+            "trash" => Ok(AfterMerge::Trash),
+            _ => match s.strip_prefix("move:") {
+                Some(dir) if !dir.is_empty() => Ok(AfterMerge::Move(PathBuf::from(dir))),
+                _ => Err(format!("expected keep, trash or move:<dir>, got '{}'", s)),
+            },
+        }
+    }
+}
+
+pub fn handle_sources(files: &[PathBuf], source_root: &Path, action: &AfterMerge) -> io::Result<()> {
+    match action {
+        AfterMerge::Keep => Ok(()),
+        AfterMerge::Trash => trash::delete_all(files).map_err(io::Error::other),
+        AfterMerge::Move(dest_root) => {
+            for file in files {
+                let relative = file.strip_prefix(source_root)
+                    .unwrap_or_else(|_| Path::new(file.file_name().unwrap_or_default()));
+                move_file(file, &dest_root.join(relative))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Renames the file, falling back to copy-and-delete when the destination is on another filesystem.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_after_merge() {
+        assert_eq!("keep".parse(), Ok(AfterMerge::Keep));
+        assert_eq!("trash".parse(), Ok(AfterMerge::Trash));
+        assert_eq!("move:/archive".parse(), Ok(AfterMerge::Move(PathBuf::from("/archive"))));
+        assert!("move:".parse::<AfterMerge>().is_err());
+        assert!("delete".parse::<AfterMerge>().is_err());
+    }
+
+    #[test]
+    fn test_move_sources_preserves_structure() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("src");
+        let archive = temp_dir.path().join("archive");
+        let book = source.join("book");
+        fs::create_dir_all(&book).unwrap();
+        let files = vec![book.join("1.mp3"), book.join("2.mp3")];
+        for file in &files {
+            fs::write(file, b"data").unwrap();
+        }
+
+        handle_sources(&files, &source, &AfterMerge::Move(archive.clone())).unwrap();
+
+        assert!(!files[0].exists());
+        assert!(!files[1].exists());
+        assert!(archive.join("book/1.mp3").exists());
+        assert!(archive.join("book/2.mp3").exists());
+    }
+}
@@ -0,0 +1,101 @@
+use id3::frame::{Picture, PictureType};
+use id3::{Tag, TagLike, Version};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File names (without extension) recognised as cover art, in order of preference.
+const COVER_NAMES: &[&str] = &["cover", "folder", "front", "albumart"];
+const COVER_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+
+/// Looks for cover art in each directory in turn (e.g. the book folder, then its disc
+/// folders). Names are matched case-insensitively, so `Cover.JPG` is found too.
+pub fn find_cover(dirs: &[&Path]) -> Option<PathBuf> {
+    dirs.iter().find_map(|dir| {
+        let images: Vec<(String, PathBuf)> = fs::read_dir(dir).ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                let ext = path.extension()?.to_string_lossy().to_lowercase();
+                let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+                COVER_EXTENSIONS.contains(&ext.as_str()).then_some((stem, path))
+            })
+            .collect();
+        COVER_NAMES.iter().find_map(|name| {
+            let mut matches: Vec<&PathBuf> = images.iter().filter(|(stem, _)| stem == name).map(|(_, p)| p).collect();
+            // Stable choice when both cover.jpg and cover.png exist
+            matches.sort();
+            matches.first().map(|p| (*p).clone())
+        })
+    })
+}
+
+/// Embeds the image as the front cover (APIC) of the merged file's ID3 tag, replacing any
+/// existing front cover and keeping the rest of the tag.
+pub fn embed_cover(output: &Path, image: &Path) -> io::Result<()> {
+    let data = fs::read(image)?;
+    let mime_type = match image.extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        Some("png") => "image/png",
+        _ => "image/jpeg",
+    };
+
+    let mut tag = match Tag::read_from_path(output) {
+        Ok(tag) => tag,
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Tag::new(),
+        Err(e) => return Err(io::Error::other(e)),
+    };
+    let version = if tag.frames().next().is_some() { tag.version() } else { Version::Id3v24 };
+    tag.remove_picture_by_type(PictureType::CoverFront);
+    tag.add_frame(Picture {
+        mime_type: mime_type.to_string(),
+        picture_type: PictureType::CoverFront,
+        description: String::new(),
+        data,
+    });
+    tag.write_to_path(output, version).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp3;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_cover() {
+        let temp_dir = TempDir::new().unwrap();
+        let book = temp_dir.path().join("Book");
+        let disc = book.join("CD1");
+        fs::create_dir_all(&disc).unwrap();
+        fs::write(disc.join("Folder.PNG"), b"png").unwrap();
+
+        assert_eq!(find_cover(&[&book]), None);
+        assert_eq!(find_cover(&[&book, &disc]), Some(disc.join("Folder.PNG")));
+
+        fs::write(book.join("notes.jpg"), b"jpg").unwrap();
+        fs::write(book.join("folder.jpg"), b"jpg").unwrap();
+        fs::write(book.join("cover.jpeg"), b"jpg").unwrap();
+        assert_eq!(find_cover(&[&book, &disc]), Some(book.join("cover.jpeg")));
+    }
+
+    #[test]
+    fn test_embed_cover() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("out.mp3");
+        let image = temp_dir.path().join("cover.png");
+        fs::write(&output, mp3::synth_frames(10)).unwrap();
+        fs::write(&image, b"\x89PNG fake").unwrap();
+
+        embed_cover(&output, &image).unwrap();
+        // Embedding again replaces rather than duplicates the cover
+        embed_cover(&output, &image).unwrap();
+
+        let tag = Tag::read_from_path(&output).unwrap();
+        let pictures: Vec<_> = tag.pictures().collect();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].mime_type, "image/png");
+        assert_eq!(pictures[0].data, b"\x89PNG fake");
+        assert_eq!(mp3::scan(&output).unwrap().frames, 10);
+    }
+}
@@ -1,43 +1,96 @@
-use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
 
-pub fn format_dry_run(groups: &HashMap<PathBuf, Vec<PathBuf>>, source_root: &Path, output_dir: &Path) -> String {
+use crate::manifest::{GroupChange, Manifest};
+use crate::planner::PlannedGroup;
+
+/// Pairs each group with its change since the `--since` manifest, leaving out unchanged
+/// groups. Without a manifest every group is kept, with no change recorded.
+fn changed_groups<'a>(plan: &'a [PlannedGroup], since: Option<&Manifest>) -> Vec<(&'a PlannedGroup, Option<GroupChange>)> {
+    plan.iter()
+        .filter_map(|group| match since {
+            Some(manifest) => manifest.change_since(group).map(|change| (group, Some(change))),
+            None => Some((group, None)),
+        })
+        .collect()
+}
+
+pub fn format_dry_run(plan: &[PlannedGroup], since: Option<&Manifest>) -> String {
     let mut output = String::new();
     output.push_str("Dry Run Results:\n");
     output.push_str("----------------\n");
 
-    // Sort keys for consistent output
-    let mut dirs: Vec<_> = groups.keys().collect();
-    dirs.sort();
-
-    for dir in dirs {
-        let files = &groups[dir];
-        let relative_path = dir.strip_prefix(source_root).unwrap_or(dir);
-        
-        let output_file = if relative_path.as_os_str().is_empty() {
-            let dir_name = dir.file_name()
-                .map(|n| n.to_string_lossy())
-                .unwrap_or_else(|| std::borrow::Cow::from("merged"));
-            output_dir.join(format!("{}.mp3", dir_name))
-        } else {
-            output_dir.join(relative_path).with_extension("mp3")
-        };
-        
-        output.push_str(&format!("Source: {}\n", dir.display()));
-        output.push_str(&format!("Output: {}\n", output_file.display()));
+    let groups = changed_groups(plan, since);
+    for (group, change) in &groups {
+        output.push_str(&format!("Source: {}\n", group.source_dir.display()));
+        if let Some(change) = change {
+            output.push_str(&format!("Status: {}\n", change));
+        }
+        output.push_str(&format!("Output: {}\n", group.output.display()));
         output.push_str("Files to merge:\n");
-        for file in files {
+        for file in &group.files {
             output.push_str(&format!("  - {}\n", file.file_name().unwrap_or_default().to_string_lossy()));
         }
-        output.push_str("\n");
+        output.push('\n');
+    }
+    if since.is_some() {
+        output.push_str(&format!("{} unchanged folders not shown.\n", plan.len() - groups.len()));
     }
-    
+
     output
 }
 
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    groups: Vec<JsonGroup<'a>>,
+    total_estimated_bytes: u64,
+    /// Groups left out because they match the `--since` manifest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unchanged: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct JsonGroup<'a> {
+    source_dir: &'a PathBuf,
+    output: &'a PathBuf,
+    files: &'a [PathBuf],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    change: Option<GroupChange>,
+    /// Sum of the input sizes; a plain concatenation produces exactly this many bytes.
+    estimated_bytes: u64,
+}
+
+/// Renders the plan as JSON for wrapper scripts and other tools to consume.
+pub fn format_dry_run_json(plan: &[PlannedGroup], since: Option<&Manifest>) -> String {
+    let groups: Vec<_> = changed_groups(plan, since).into_iter()
+        .map(|(group, change)| JsonGroup {
+            source_dir: &group.source_dir,
+            output: &group.output,
+            files: &group.files,
+            change,
+            estimated_bytes: group.files.iter()
+                .filter_map(|f| fs::metadata(f).ok())
+                .map(|m| m.len())
+                .sum(),
+        })
+        .collect();
+    let report = JsonReport {
+        total_estimated_bytes: groups.iter().map(|g| g.estimated_bytes).sum(),
+        unchanged: since.map(|_| plan.len() - groups.len()),
+        groups,
+    };
+
+    serde_json::to_string_pretty(&report).expect("dry-run report is always serializable")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::planner::plan_groups;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use tempfile::TempDir;
 
     #[test]
     fn test_format_dry_run() {
@@ -47,12 +100,42 @@ mod tests {
             source_root.join("book1/chap1.mp3"),
             source_root.join("book1/chap2.mp3"),
         ]);
-        
-        let output = format_dry_run(&groups, &source_root, Path::new("out_dir"));
-        
+
+        let plan = plan_groups(&groups, &source_root, Path::new("out_dir"));
+        let output = format_dry_run(&plan, None);
+
         assert!(output.contains("Source: src/book1"));
         assert!(output.contains("Output: out_dir/book1.mp3"));
         assert!(output.contains("  - chap1.mp3"));
         assert!(output.contains("  - chap2.mp3"));
+        assert!(!output.contains("Status:"));
+
+        let since = Manifest::new(&[source_root], Path::new("out_dir"));
+        let output = format_dry_run(&plan, Some(&since));
+        assert!(output.contains("Source: src/book1\nStatus: new\n"));
+        assert!(output.contains("0 unchanged folders not shown."));
+    }
+
+    #[test]
+    fn test_format_dry_run_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let book = temp_dir.path().join("book1");
+        fs::create_dir(&book).unwrap();
+        fs::write(book.join("chap1.mp3"), vec![0u8; 100]).unwrap();
+        fs::write(book.join("chap2.mp3"), vec![0u8; 50]).unwrap();
+
+        let mut groups = HashMap::new();
+        groups.insert(book.clone(), vec![book.join("chap1.mp3"), book.join("chap2.mp3")]);
+        let plan = plan_groups(&groups, temp_dir.path(), Path::new("out_dir"));
+
+        let json: serde_json::Value = serde_json::from_str(&format_dry_run_json(&plan, None)).unwrap();
+        assert_eq!(json["total_estimated_bytes"], 150);
+        let group = &json["groups"][0];
+        assert_eq!(group["output"], "out_dir/book1.mp3");
+        assert_eq!(group["estimated_bytes"], 150);
+        assert_eq!(group["files"].as_array().unwrap().len(), 2);
+        assert!(group["files"][0].as_str().unwrap().ends_with("chap1.mp3"));
+        assert!(group.get("change").is_none());
+        assert!(json.get("unchanged").is_none());
     }
 }
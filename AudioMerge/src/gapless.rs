@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::mp3::StreamInfo;
+
+/// A join between two inputs where byte-level concatenation leaves silence in the output:
+/// the end padding of the first file, then the Info frame and encoder delay of the second.
+#[derive(Debug, PartialEq)]
+pub struct Gap {
+    pub after: PathBuf,
+    pub before: PathBuf,
+    pub duration: Duration,
+}
+
+/// Finds every join with encoder silence on either side. Decoders only skip delay and padding
+/// at the very start and end of a stream, so inside a merged file they play as gaps or clicks.
+pub fn find_gaps(files: &[PathBuf], infos: &[StreamInfo]) -> Vec<Gap> {
+    let seconds = |samples: u32, info: &StreamInfo| {
+        info.format.map_or(0.0, |format| samples as f64 / format.sample_rate as f64)
+    };
+
+    files.windows(2)
+        .zip(infos.windows(2))
+        .filter_map(|(pair, info_pair)| {
+            let end = info_pair[0].encoder_gap.map_or(0.0, |gap| seconds(gap.padding, &info_pair[0]));
+            let start = info_pair[1].encoder_gap
+                .map_or(0.0, |gap| seconds(gap.info_frame + gap.delay, &info_pair[1]));
+            (end + start > 0.0).then(|| Gap {
+                after: pair[0].clone(),
+                before: pair[1].clone(),
+                duration: Duration::from_secs_f64(end + start),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp3::{AudioFormat, EncoderGap};
+
+    fn info(gap: Option<EncoderGap>) -> StreamInfo {
+        StreamInfo {
+            format: Some(AudioFormat { sample_rate: 44100, channels: 2, bitrate_kbps: Some(128) }),
+            encoder_gap: gap,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_gaps() {
+        let files: Vec<PathBuf> = ["1.mp3", "2.mp3", "3.mp3", "4.mp3"].iter().map(PathBuf::from).collect();
+        let lame = EncoderGap { delay: 576, padding: 1323, info_frame: 1152 };
+        let infos = vec![info(None), info(None), info(Some(lame)), info(Some(lame))];
+
+        let gaps = find_gaps(&files, &infos);
+        assert_eq!(gaps.len(), 2);
+        // Nothing before 3.mp3 but its own Info frame and delay
+        assert_eq!(gaps[0].after, files[1]);
+        assert_eq!(gaps[0].duration.as_millis(), 39);
+        // 3.mp3's padding, then 4.mp3's Info frame and delay
+        assert_eq!(gaps[1].before, files[3]);
+        assert_eq!(gaps[1].duration.as_millis(), 69);
+
+        assert!(find_gaps(&files[..2], &infos[..2]).is_empty());
+    }
+}
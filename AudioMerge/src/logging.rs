@@ -0,0 +1,55 @@
+use log::{Level, LevelFilter};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Sets up the global logger. Info messages go to stdout as plain text, everything else to
+/// stderr (warnings prefixed with "Warning: "). The log file, if any, gets timestamped lines
+/// at debug level or finer, whatever the console level. Only this crate's own messages are
+/// logged, not those of its dependencies.
+pub fn init(console_level: LevelFilter, log_file: Option<&Path>) -> Result<(), fern::InitError> {
+    let file_level = console_level.max(LevelFilter::Debug);
+    let max_level = if log_file.is_some() { file_level } else { console_level };
+
+    let console = fern::Dispatch::new()
+        .level(console_level)
+        .chain(
+            fern::Dispatch::new()
+                .filter(|metadata| metadata.level() == Level::Info)
+                .format(|out, message, _| out.finish(format_args!("{}", message)))
+                .chain(io::stdout()),
+        )
+        .chain(
+            fern::Dispatch::new()
+                .filter(|metadata| metadata.level() != Level::Info)
+                .format(|out, message, record| match record.level() {
+                    Level::Warn => out.finish(format_args!("Warning: {}", message)),
+                    _ => out.finish(format_args!("{}", message)),
+                })
+                .chain(io::stderr()),
+        );
+
+    let mut dispatch = fern::Dispatch::new()
+        .level(max_level)
+        .filter(|metadata| metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+        .chain(console);
+
+    if let Some(path) = log_file {
+        dispatch = dispatch.chain(
+            fern::Dispatch::new()
+                .level(file_level)
+                .format(|out, message, record| {
+                    out.finish(format_args!(
+                        "{} {:<5} {}",
+                        humantime::format_rfc3339_seconds(SystemTime::now()),
+                        record.level(),
+                        message
+                    ))
+                })
+                .chain(fern::log_file(path)?),
+        );
+    }
+
+    dispatch.apply()?;
+    Ok(())
+}
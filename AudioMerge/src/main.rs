@@ -1,73 +1,341 @@
+mod app;
 mod args;
+mod attributes;
+mod book_meta;
+mod chapters;
+mod check;
+mod checksum;
+mod cleanup;
+mod cover;
 mod scanner;
+mod summary;
 mod dry_run;
+mod gapless;
+mod logging;
+mod manifest;
 mod merger;
+mod mismatch;
+mod mp3;
+mod pipeline;
+mod plan_file;
+mod planner;
+mod playlist;
+mod resume;
+mod tags;
+mod throttle;
+mod trim;
+mod tui;
+mod validate;
+mod verify;
+mod watch;
 
-use clap::Parser;
-use args::Args;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use args::{Args, Command, GroupBy, OnCollision, ReportFormat};
+use manifest::Manifest;
+use pipeline::ConsoleReporter;
+use plan_file::PlanFile;
+use planner::PlannedGroup;
+use scanner::ScanOptions;
+use summary::RunSummary;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 fn main() {
     let args = Args::parse();
+    let is_plan = matches!(args.command, Some(Command::Plan { .. }));
+    if args.source.len() > 1 && (args.watch || args.tui || is_plan) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "--watch, --tui and `plan` take a single --source")
+            .exit();
+    }
+
+    // The TUI owns the terminal, so it only logs to the file
+    let console_level = if args.tui { log::LevelFilter::Off } else { args.log_level() };
+    if let Err(e) = logging::init(console_level, args.log_file.as_deref()) {
+        eprintln!("Error: could not set up logging: {}", e);
+        std::process::exit(summary::EXIT_FATAL);
+    }
+
+    if let Some(Command::Apply { file }) = &args.command {
+        run_apply(file, &args);
+        return;
+    }
     
-    // Check if source exists
-    if !args.source.exists() {
-        eprintln!("Error: Source directory '{}' does not exist.", args.source.display());
-        std::process::exit(1);
+    // Check if every source exists
+    for source in &args.source {
+        if !source.exists() {
+            log::error!("Error: Source directory '{}' does not exist.", source.display());
+            std::process::exit(summary::EXIT_FATAL);
+        }
+    }
+
+    if args.watch {
+        run_watch(&args);
+        return;
     }
 
-    let groups = scanner::scan_audio_files(&args.source);
+    let planned: Vec<(Args, Vec<PlannedGroup>)> = args.per_root()
+        .into_iter()
+        .map(|run_args| {
+            let source = run_args.source_root();
+            let groups = scanner::scan_audio_files(source, &run_args.scan_options());
+            let plan = plan_groups(&groups, source, &run_args);
+            (run_args, plan)
+        })
+        .collect();
 
-    if groups.is_empty() {
-        println!("No MP3 files found in '{}'.", args.source.display());
+    if let Some(Command::Check) = &args.command {
+        run_check(planned, &args);
+        return;
+    }
+
+    let runs: Vec<(Args, Vec<PlannedGroup>)> = planned.into_iter()
+        .map(|(run_args, plan)| {
+            let Some(plan) = resolve_collisions(plan, &run_args.on_collision) else {
+                std::process::exit(summary::EXIT_FATAL);
+            };
+            (run_args, plan)
+        })
+        .collect();
+
+    if let Some(Command::Plan { file }) = &args.command {
+        let plan = &runs[0].1;
+        let plan_file = PlanFile::new(args.source_root(), &args.output, plan);
+        if let Err(e) = plan_file.save(file) {
+            log::error!("Error: could not write plan '{}': {}", file.display(), e);
+            std::process::exit(summary::EXIT_FATAL);
+        }
+        log::info!("Wrote plan for {} groups to '{}'.", plan.len(), file.display());
+        log::info!("Edit it if needed, then run: AudioMerge apply {}", file.display());
+        return;
+    }
+
+    execute(runs, &args.output, &args);
+}
+
+/// Runs finished plans, one per source root, according to the mode flags: report, TUI,
+/// playlists or merge.
+fn execute(runs: Vec<(Args, Vec<PlannedGroup>)>, output_dir: &Path, args: &Args) {
+    let all_groups: Vec<PlannedGroup> = runs.iter().flat_map(|(_, plan)| plan.iter().cloned()).collect();
+
+    let since = args.since.as_ref().map(|path| match Manifest::load(path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::error!("Error: could not read manifest '{}': {}", path.display(), e);
+            std::process::exit(summary::EXIT_FATAL);
+        }
+    });
+
+    if args.dry_run && args.format == ReportFormat::Json {
+        println!("{}", dry_run::format_dry_run_json(&all_groups, since.as_ref()));
+        return;
+    }
+
+    if all_groups.is_empty() {
+        let sources: Vec<String> = runs.iter()
+            .map(|(run_args, _)| format!("'{}'", run_args.source_root().display()))
+            .collect();
+        log::info!("No MP3 files found in {}.", sources.join(", "));
+        return;
+    }
+
+    if args.tui {
+        let Some((run_args, plan)) = runs.into_iter().next() else {
+            return;
+        };
+        run_tui(plan, &run_args);
         return;
     }
 
     if args.dry_run {
-        let output = dry_run::format_dry_run(&groups, &args.source, &args.output);
+        let output = dry_run::format_dry_run(&all_groups, since.as_ref());
         println!("{}", output);
+    } else if args.playlist.is_some() {
+        write_playlists(&all_groups, args);
     } else {
-        println!("Found {} folders with audio files.", groups.len());
-        println!("Starting merge process...");
-
-        // Sort keys for consistent processing order
-        let mut dirs: Vec<_> = groups.keys().collect();
-        dirs.sort();
-
-        for dir in dirs {
-            let files = &groups[dir];
-            
-            // Calculate path relative to source to preserve structure and avoid collisions
-            let relative_path = dir.strip_prefix(&args.source).unwrap_or(dir);
-            let output_file = if relative_path.as_os_str().is_empty() {
-                let dir_name = dir.file_name()
-                    .map(|n| n.to_string_lossy())
-                    .unwrap_or_else(|| std::borrow::Cow::from("merged"));
-                args.output.join(format!("{}.mp3", dir_name))
-            } else {
-                args.output.join(relative_path).with_extension("mp3")
-            };
-            
-            println!("Merging {} files from '{}' to '{}'", files.len(), dir.display(), output_file.display());
-            
-            if let Err(e) = merger::merge_files(files, &output_file) {
-                eprintln!("Error merging files for {}: {}", dir.display(), e);
-                match args.on_error {
-                    args::OnError::Halt => {
-                        eprintln!("Halting due to error.");
-                        std::process::exit(1);
-                    },
-                    args::OnError::Skip => {
-                        eprintln!("Skipping...");
-                        continue;
-                    },
-                    args::OnError::Prompt => {
-                        eprintln!("Prompt strategy not yet implemented. Defaulting to Skip behavior.");
-                        continue;
-                    }
-                }
+        log::info!("Found {} folders with audio files.", all_groups.len());
+        log::info!("Starting merge process...");
+
+        let sources: Vec<PathBuf> = runs.iter().map(|(run_args, _)| run_args.source_root().to_path_buf()).collect();
+        let mut run_manifest = args.manifest.as_ref().map(|_| Manifest::new(&sources, output_dir));
+        let mut reporter = ConsoleReporter { on_error: args.on_error.clone() };
+        let mut summary = RunSummary::default();
+        for (run_args, plan) in &runs {
+            if runs.len() > 1 {
+                log::info!("Merging '{}' into '{}'", run_args.source_root().display(), run_args.output.display());
             }
+            let root_summary =
+                pipeline::merge_plan(plan, run_args.source_root(), run_args, &mut run_manifest, &mut reporter);
+            summary.add(&root_summary);
+        }
+        log::info!("{}", summary);
+        if summary.exit_code() != summary::EXIT_OK {
+            std::process::exit(summary.exit_code());
+        }
+    }
+}
+
+/// `check`: reports everything a merge of the plan would run into, without writing anything.
+fn run_check(planned: Vec<(Args, Vec<PlannedGroup>)>, args: &Args) {
+    let checks: Vec<_> = planned.into_iter()
+        .flat_map(|(run_args, plan)| check::check_plan(plan, &run_args))
+        .collect();
+    if checks.is_empty() {
+        let sources: Vec<String> = args.source.iter().map(|s| format!("'{}'", s.display())).collect();
+        log::info!("No MP3 files found in {}.", sources.join(", "));
+        return;
+    }
+    println!("{}", check::format_check(&checks));
+    if checks.iter().any(|c| c.worst() == Some(check::Severity::Error)) {
+        std::process::exit(summary::EXIT_PARTIAL);
+    }
+}
+
+/// `apply`: executes a plan file exactly as written; scanning and grouping options are ignored.
+fn run_apply(file: &Path, args: &Args) {
+    let plan_file = match PlanFile::load(file) {
+        Ok(plan_file) => plan_file,
+        Err(e) => {
+            log::error!("Error: could not read plan '{}': {}", file.display(), e);
+            std::process::exit(summary::EXIT_FATAL);
+        }
+    };
+
+    let problems = plan_file.problems();
+    if !problems.is_empty() {
+        let mut message = format!("Error: plan '{}' cannot be applied:", file.display());
+        for problem in &problems {
+            message.push_str(&format!("\n  - {}", problem));
         }
-        println!("Done.");
+        log::error!("{}", message);
+        std::process::exit(summary::EXIT_FATAL);
+    }
+
+    let Some(plan) = resolve_collisions(plan_file.groups, &args.on_collision) else {
+        std::process::exit(summary::EXIT_FATAL);
+    };
+    let run_args = Args {
+        source: vec![plan_file.source],
+        output: plan_file.output_dir.clone(),
+        ..args.clone()
+    };
+    execute(vec![(run_args, plan)], &plan_file.output_dir, args);
+}
+
+/// Plans the scanned groups and names them from their folders if `--folder-pattern` is set.
+fn plan_groups(groups: &HashMap<PathBuf, Vec<PathBuf>>, source_root: &Path, args: &Args) -> Vec<PlannedGroup> {
+    let mut plan = planner::plan(groups, source_root, &args.output, &args.group_by);
+    if let Some(pattern) = &args.folder_pattern {
+        book_meta::apply(&mut plan, pattern, args.output_name.as_ref(), &args.output);
+    }
+    plan
+}
+
+/// Applies `--on-collision` and reports every collision. Returns `None` if the plan must not run.
+fn resolve_collisions(plan: Vec<PlannedGroup>, on_collision: &OnCollision) -> Option<Vec<PlannedGroup>> {
+    let (plan, collisions) = planner::resolve_collisions(plan, on_collision);
+    for collision in &collisions {
+        let message = format!(
+            "'{}' and '{}' would both be written to '{}'",
+            collision.claimed_by.display(),
+            collision.source_dir.display(),
+            collision.output.display()
+        );
+        match (&collision.renamed_to, on_collision) {
+            (Some(renamed), _) => log::warn!("{}; writing the second to '{}'.", message, renamed.display()),
+            (None, OnCollision::Skip) => log::warn!("{}; skipping the second.", message),
+            (None, _) => log::error!("Error: {}.", message),
+        }
+    }
+    if *on_collision == OnCollision::Error && !collisions.is_empty() {
+        log::error!("Refusing to merge because of output collisions (see --on-collision).");
+        return None;
+    }
+    Some(plan)
+}
+
+/// `--playlist`: records each group's order without merging anything.
+fn write_playlists(plan: &[PlannedGroup], args: &Args) {
+    let Some(format) = &args.playlist else {
+        return;
+    };
+    for group in plan {
+        match playlist::write_playlist(group, format) {
+            Ok(path) => log::info!("Wrote playlist '{}' ({} files)", path.display(), group.files.len()),
+            Err(e) => log::error!("Error writing playlist for {}: {}", group.source_dir.display(), e),
+        }
+    }
+}
+
+fn run_tui(plan: Vec<PlannedGroup>, args: &Args) {
+    tui::install_panic_hook();
+    let result = tui::init().and_then(|mut terminal| {
+        let mut app = app::App::new(plan, args.source_root().to_path_buf(), args.clone());
+        let result = app.run(&mut terminal);
+        tui::restore()?;
+        result
+    });
+    if let Err(e) = result {
+        let _ = tui::restore();
+        log::error!("Error: terminal UI failed: {}", e);
+        std::process::exit(summary::EXIT_FATAL);
+    }
+}
+
+/// Merges (or, with --dry-run, reports) each folder in the source once its files settle.
+fn run_watch(args: &Args) {
+    let source_root = match args.source_root().canonicalize() {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("Error: cannot resolve source directory '{}': {}", args.source_root().display(), e);
+            std::process::exit(summary::EXIT_FATAL);
+        }
+    };
+    let output_root = std::path::absolute(&args.output).unwrap_or_else(|_| args.output.clone());
+    let mut run_manifest = args.manifest.as_ref().map(|_| Manifest::new(std::slice::from_ref(&source_root), &args.output));
+    let mut reporter = ConsoleReporter { on_error: args.on_error.clone() };
+
+    log::info!(
+        "Watching '{}' for new audio (merging after {}s without changes). Press Ctrl+C to stop.",
+        source_root.display(),
+        args.settle_secs
+    );
+
+    let result = watch::watch(&source_root, &output_root, Duration::from_secs(args.settle_secs), |dir| {
+        // Only this folder's own files; subfolders settle and are merged on their own.
+        // In book mode a settled disc folder re-merges its whole book instead.
+        let is_disc = dir.file_name().and_then(|name| planner::disc_number(&name.to_string_lossy())).is_some();
+        let (scan_root, max_depth) = if args.group_by != GroupBy::Book {
+            (dir, 0)
+        } else if is_disc && dir != source_root {
+            (dir.parent().unwrap_or(dir), 1)
+        } else {
+            (dir, 1)
+        };
+        let options = ScanOptions { max_depth: Some(max_depth), ..args.scan_options() };
+        let groups = scanner::scan_audio_files(scan_root, &options);
+        let mut plan = plan_groups(&groups, &source_root, args);
+        if args.group_by == GroupBy::Book {
+            // Non-disc subfolders picked up by the deeper scan are books of their own
+            plan.retain(|group| group.source_dir == scan_root);
+        }
+        let Some(plan) = resolve_collisions(plan, &args.on_collision) else {
+            return;
+        };
+        if args.dry_run {
+            print!("{}", dry_run::format_dry_run(&plan, None));
+        } else if args.playlist.is_some() {
+            write_playlists(&plan, args);
+        } else {
+            log::info!("{}", pipeline::merge_plan(&plan, &source_root, args, &mut run_manifest, &mut reporter));
+        }
+    });
+
+    if let Err(e) = result {
+        log::error!("Error: watching '{}' failed: {}", source_root.display(), e);
+        std::process::exit(summary::EXIT_FATAL);
     }
 }
 
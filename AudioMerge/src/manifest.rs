@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::merger::MergeDigests;
+use crate::mp3::StreamInfo;
+use crate::planner::PlannedGroup;
+
+pub const MANIFEST_VERSION: u32 = 2;
+
+/// Record of everything written during a run, so merged outputs can later be traced
+/// back to (and verified against) the chapter files that went into them.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Manifest {
+    pub version: u32,
+    /// Seconds since the Unix epoch when the run started.
+    pub created_at: u64,
+    /// Every source root of the run, in `--source` order. Missing from version 1 manifests.
+    #[serde(default)]
+    pub sources: Vec<PathBuf>,
+    pub output_dir: PathBuf,
+    pub outputs: Vec<OutputEntry>,
+}
+
+/// How a planned group differs from what an earlier run's manifest recorded.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupChange {
+    /// No output was recorded for this folder
+    New,
+    /// The folder's inputs were added, removed, reordered or resized since
+    Changed,
+}
+
+impl std::fmt::Display for GroupChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupChange::New => write!(f, "new"),
+            GroupChange::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct OutputEntry {
+    pub path: PathBuf,
+    pub source_dir: PathBuf,
+    pub bytes: u64,
+    pub duration_secs: f64,
+    pub sha256: String,
+    pub inputs: Vec<FileEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub duration_secs: f64,
+    pub sha256: String,
+}
+
+impl FileEntry {
+    pub fn from_file(path: &Path, info: &StreamInfo, sha256: &str) -> io::Result<Self> {
+        Ok(FileEntry {
+            path: path.to_path_buf(),
+            bytes: fs::metadata(path)?.len(),
+            duration_secs: info.duration.as_secs_f64(),
+            sha256: sha256.to_string(),
+        })
+    }
+}
+
+impl OutputEntry {
+    pub fn from_merge(
+        output: &Path,
+        output_info: &StreamInfo,
+        source_dir: &Path,
+        files: &[PathBuf],
+        infos: &[StreamInfo],
+        digests: &MergeDigests,
+    ) -> io::Result<Self> {
+        let inputs = files.iter()
+            .zip(infos)
+            .zip(&digests.inputs)
+            .map(|((path, info), digest)| FileEntry::from_file(path, info, digest))
+            .collect::<io::Result<Vec<_>>>()?;
+        let output = FileEntry::from_file(output, output_info, &digests.output)?;
+        Ok(OutputEntry {
+            path: output.path,
+            source_dir: source_dir.to_path_buf(),
+            bytes: output.bytes,
+            duration_secs: output.duration_secs,
+            sha256: output.sha256,
+            inputs,
+        })
+    }
+}
+
+impl Manifest {
+    pub fn new(sources: &[PathBuf], output_dir: &Path) -> Self {
+        Manifest {
+            version: MANIFEST_VERSION,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            sources: sources.to_vec(),
+            output_dir: output_dir.to_path_buf(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Compares the group against the latest output recorded for its folder, or `None` if
+    /// the same files (by path, order and size) were merged then. Contents are not re-hashed,
+    /// so this stays quick on a large library.
+    pub fn change_since(&self, group: &PlannedGroup) -> Option<GroupChange> {
+        let source_dir = absolute(&group.source_dir);
+        let Some(entry) = self.outputs.iter().rev().find(|entry| absolute(&entry.source_dir) == source_dir) else {
+            return Some(GroupChange::New);
+        };
+        let unchanged = entry.inputs.len() == group.files.len()
+            && entry.inputs.iter().zip(&group.files).all(|(input, file)| {
+                absolute(&input.path) == absolute(file) && fs::metadata(file).is_ok_and(|m| m.len() == input.bytes)
+            });
+        (!unchanged).then_some(GroupChange::Changed)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, data)
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::{merge_files, CopyOptions};
+    use crate::mp3;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input1 = temp_dir.path().join("1.mp3");
+        let input2 = temp_dir.path().join("2.mp3");
+        let output = temp_dir.path().join("out.mp3");
+        fs::write(&input1, mp3::synth_frames(10)).unwrap();
+        fs::write(&input2, mp3::synth_frames(5)).unwrap();
+
+        let files = vec![input1, input2];
+        let digests = merge_files(&files, &output, &CopyOptions::default()).unwrap();
+        let infos: Vec<_> = files.iter().map(|p| mp3::scan(p).unwrap()).collect();
+        let output_info = mp3::scan(&output).unwrap();
+
+        let entry = OutputEntry::from_merge(&output, &output_info, temp_dir.path(), &files, &infos, &digests).unwrap();
+        assert_eq!(entry.bytes, 417 * 15);
+        assert_eq!(entry.sha256, digests.output);
+        assert_eq!(entry.inputs[0].sha256, digests.inputs[0]);
+        assert_eq!(entry.inputs.len(), 2);
+        assert_eq!(entry.inputs[0].bytes, 417 * 10);
+        assert_eq!(entry.inputs[1].path, files[1]);
+
+        let mut manifest = Manifest::new(&[PathBuf::from("src")], Path::new("out"));
+        manifest.outputs.push(entry);
+
+        let manifest_path = temp_dir.path().join("manifest.json");
+        manifest.save(&manifest_path).unwrap();
+        assert_eq!(Manifest::load(&manifest_path).unwrap(), manifest);
+    }
+
+    #[test]
+    fn test_change_since() {
+        let temp_dir = TempDir::new().unwrap();
+        let book = temp_dir.path().join("book");
+        fs::create_dir(&book).unwrap();
+        let files = vec![book.join("1.mp3"), book.join("2.mp3")];
+        for file in &files {
+            fs::write(file, mp3::synth_frames(3)).unwrap();
+        }
+        let inputs = files.iter()
+            .map(|file| FileEntry::from_file(file, &mp3::scan(file).unwrap(), "").unwrap())
+            .collect();
+        let mut manifest = Manifest::new(&[temp_dir.path().to_path_buf()], Path::new("out"));
+        manifest.outputs.push(OutputEntry {
+            path: PathBuf::from("out/book.mp3"),
+            source_dir: book.clone(),
+            bytes: 0,
+            duration_secs: 0.0,
+            sha256: String::new(),
+            inputs,
+        });
+
+        let mut group = PlannedGroup {
+            source_dir: book.clone(),
+            output: PathBuf::from("out/book.mp3"),
+            files: files.clone(),
+            trims: Default::default(),
+            meta: None,
+        };
+        assert_eq!(manifest.change_since(&group), None);
+
+        fs::write(&files[1], mp3::synth_frames(4)).unwrap();
+        assert_eq!(manifest.change_since(&group), Some(GroupChange::Changed));
+
+        group.files.reverse();
+        assert_eq!(manifest.change_since(&group), Some(GroupChange::Changed));
+
+        group.source_dir = temp_dir.path().join("other");
+        assert_eq!(manifest.change_since(&group), Some(GroupChange::New));
+    }
+}
@@ -1,26 +1,303 @@
+use sha2::{Digest, Sha256};
 use std::fs::{self, File};
-use std::io;
+use std::io::{self, Read, Write};
+use std::ops::Range;
 use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
 
-pub fn merge_files(files: &[std::path::PathBuf], output_path: &Path) -> io::Result<()> {
+use crate::checksum;
+use crate::resume::{self, Progress};
+use crate::throttle::TokenBucket;
+use crate::mp3::AudioFormat;
+use crate::trim::TrimmedInput;
+
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A byte range covering any whole file.
+pub const WHOLE_FILE: Range<u64> = 0..u64::MAX;
+
+/// How the output file is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyOptions {
+    /// Bytes read from an input per read call
+    pub buffer_size: usize,
+    /// Flush the output (and its directory entry) to disk before reporting success
+    pub fsync: bool,
+    /// Reserve the output's full size up front, to avoid fragmentation and fail early when
+    /// the disk is too small
+    pub preallocate: bool,
+    /// Average read rate in bytes per second, or `None` for no limit
+    pub io_limit: Option<u64>,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions { buffer_size: DEFAULT_BUFFER_SIZE, fsync: false, preallocate: true, io_limit: None }
+    }
+}
+
+/// SHA-256 digests (lowercase hex) of each input, in order, and of the written output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeDigests {
+    pub inputs: Vec<String>,
+    pub output: String,
+}
+
+/// Concatenates the inputs into the output, hashing every byte as it streams through so
+/// the digests describe exactly what was read and written.
+pub fn merge_files(files: &[std::path::PathBuf], output_path: &Path, options: &CopyOptions) -> io::Result<MergeDigests> {
+    let whole = vec![vec![WHOLE_FILE]; files.len()];
+    merge_ranges(files, &whole, output_path, options)
+}
+
+/// Like `merge_files`, but only the given byte ranges (sorted, non-overlapping) of each
+/// input are written. Input digests still cover the whole input file.
+///
+/// The output is written to `<output>.partial` and renamed once complete. Progress is
+/// recorded as it goes, so if a run is interrupted the next merge of the same unchanged
+/// inputs continues where it stopped instead of starting over.
+pub fn merge_ranges(
+    files: &[std::path::PathBuf],
+    ranges: &[Vec<Range<u64>>],
+    output_path: &Path,
+    options: &CopyOptions,
+) -> io::Result<MergeDigests> {
     if let Some(parent) = output_path.parent() {
         fs::create_dir_all(parent)?;
     }
 
-    let mut output_file = File::create(output_path)?;
+    let partial_path = resume::partial_path(output_path);
+    let fresh = Progress::start(files, ranges)?;
+    let mut progress = match Progress::load(output_path, &fresh) {
+        Some(progress) => {
+            log::info!(
+                "Resuming '{}' at file {} of {} ({} bytes already written)",
+                output_path.display(),
+                (progress.input + 1).min(files.len()),
+                files.len(),
+                progress.written
+            );
+            progress
+        }
+        None => fresh,
+    };
 
-    for path in files {
+    let mut output_hasher = Sha256::new();
+    let mut output_file = if progress.written > 0 {
+        let mut file = File::options().read(true).write(true).open(&partial_path)?;
+        file.set_len(progress.written)?;
+        // Re-hashing what is already there is far cheaper than writing it again
+        io::copy(&mut file, &mut output_hasher)?;
+        file
+    } else {
+        let file = File::create(&partial_path)?;
+        if options.preallocate {
+            let mut total = 0;
+            for (path, ranges) in files.iter().zip(ranges) {
+                let len = fs::metadata(path)?.len();
+                total += ranges.iter().map(|r| r.end.min(len).saturating_sub(r.start.min(len))).sum::<u64>();
+            }
+            // Best effort: not every filesystem (notably some network mounts) supports it
+            let _ = fs4::FileExt::allocate(&file, total);
+        }
+        file
+    };
+    let mut input_digests = Vec::with_capacity(files.len());
+    let mut written = progress.written;
+    let mut checkpoint = written;
+    let mut buffer = vec![0u8; options.buffer_size.max(1)];
+    let mut throttle = options.io_limit.map(|rate| TokenBucket::new(rate, buffer.len()));
+
+    for (index, (path, ranges)) in files.iter().zip(ranges).enumerate() {
+        if index < progress.input {
+            input_digests.push(checksum::sha256_file(path)?);
+            continue;
+        }
+        let started = Instant::now();
+        let written_before = written;
         let mut input_file = File::open(path)?;
-        io::copy(&mut input_file, &mut output_file)?;
+        let mut input_hasher = Sha256::new();
+        let mut position: u64 = 0;
+        if index == progress.input && progress.position > 0 {
+            position = io::copy(&mut (&mut input_file).take(progress.position), &mut input_hasher)?;
+        }
+        loop {
+            let read = match input_file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if let Some(throttle) = &mut throttle {
+                throttle.take(read);
+            }
+            let chunk = &buffer[..read];
+            input_hasher.update(chunk);
+            let chunk_end = position + read as u64;
+            for range in ranges {
+                let (start, end) = (range.start.max(position), range.end.min(chunk_end));
+                if start < end {
+                    let kept = &chunk[(start - position) as usize..(end - position) as usize];
+                    output_hasher.update(kept);
+                    output_file.write_all(kept)?;
+                    written += kept.len() as u64;
+                }
+            }
+            position = chunk_end;
+            if written - checkpoint >= resume::CHECKPOINT_BYTES {
+                progress = Progress { input: index, position, written, ..progress };
+                save_progress(&progress, &output_file, output_path, options)?;
+                checkpoint = written;
+            }
+        }
+        input_digests.push(checksum::to_hex(&input_hasher.finalize()));
+        progress = Progress { input: index + 1, position: 0, written, ..progress };
+        save_progress(&progress, &output_file, output_path, options)?;
+        checkpoint = written;
+        log::debug!(
+            "Copied '{}' ({} of {} bytes) in {:.2?}",
+            path.display(),
+            written - written_before,
+            position,
+            started.elapsed()
+        );
+    }
+
+    // Drops any preallocated tail if an input shrank while we were reading it
+    output_file.set_len(written)?;
+    fs::rename(&partial_path, output_path)?;
+    Progress::clear(output_path)?;
+    if options.fsync {
+        sync_to_disk(&output_file, output_path)?;
     }
 
+    Ok(MergeDigests {
+        inputs: input_digests,
+        output: checksum::to_hex(&output_hasher.finalize()),
+    })
+}
+
+/// Records progress once the bytes it vouches for are out of our hands (and, with
+/// `--fsync`, on disk).
+fn save_progress(progress: &Progress, output_file: &File, output_path: &Path, options: &CopyOptions) -> io::Result<()> {
+    if options.fsync {
+        output_file.sync_data()?;
+    }
+    progress.save(output_path)
+}
+
+/// Creates an empty ffmpeg concat list in the temp directory under a name no other merge,
+/// in this process or another, is using.
+fn create_concat_list() -> io::Result<(std::path::PathBuf, File)> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("audiomerge-{}-{}.ffconcat", std::process::id(), n));
+        match File::options().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decodes and re-encodes every input through ffmpeg into a single stream with the
+/// given format. Used when the inputs disagree on format and a plain byte concatenation
+/// would not play back correctly.
+///
+/// Like the other merges, ffmpeg writes to `<output>.partial`, which is renamed only once
+/// ffmpeg has succeeded; an interrupted re-encode starts over on the next run.
+pub fn reencode_files(
+    files: &[std::path::PathBuf],
+    trims: &[TrimmedInput],
+    output_path: &Path,
+    format: &AudioFormat,
+    options: &CopyOptions,
+) -> io::Result<MergeDigests> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Progress left by an interrupted byte-copy merge doesn't describe what ffmpeg writes
+    Progress::clear(output_path)?;
+    let partial_path = resume::partial_path(output_path);
+
+    let (list_path, mut list) = create_concat_list()?;
+    let written = (|| -> io::Result<()> {
+        writeln!(list, "ffconcat version 1.0")?;
+        for (path, trim) in files.iter().zip(trims) {
+            let absolute = fs::canonicalize(path)?;
+            writeln!(list, "file '{}'", absolute.to_string_lossy().replace('\'', "'\\''"))?;
+            if !trim.start.is_zero() {
+                writeln!(list, "inpoint {:.6}", trim.start.as_secs_f64())?;
+            }
+            if trim.end < trim.full {
+                writeln!(list, "outpoint {:.6}", trim.end.as_secs_f64())?;
+            }
+        }
+        Ok(())
+    })();
+    drop(list);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&list_path);
+        return Err(e);
+    }
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-f", "concat", "-safe", "0", "-i"])
+        .arg(&list_path)
+        .args(["-vn", "-codec:a", "libmp3lame"])
+        .args(["-ar", &format.sample_rate.to_string()])
+        .args(["-ac", &format.channels.to_string()]);
+    match format.bitrate_kbps {
+        Some(kbps) => command.args(["-b:a", &format!("{}k", kbps)]),
+        None => command.args(["-q:a", "2"]),
+    };
+    let started = Instant::now();
+    // The `.partial` name hides the format from ffmpeg, so it is given explicitly
+    let status = command.args(["-f", "mp3"]).arg(&partial_path).status();
+    log::debug!("ffmpeg re-encoded {} files in {:.2?}", files.len(), started.elapsed());
+    let _ = fs::remove_file(&list_path);
+
+    let status = status.map_err(|e| io::Error::new(e.kind(), format!("failed to run ffmpeg: {}", e)))?;
+    if !status.success() {
+        let _ = fs::remove_file(&partial_path);
+        return Err(io::Error::other(format!("ffmpeg exited with {}", status)));
+    }
+    if options.fsync {
+        File::open(&partial_path)?.sync_all()?;
+    }
+    fs::rename(&partial_path, output_path)?;
+    if options.fsync {
+        sync_to_disk(&File::open(output_path)?, output_path)?;
+    }
+
+    Ok(MergeDigests {
+        inputs: files.iter().map(|f| checksum::sha256_file(f)).collect::<io::Result<_>>()?,
+        output: checksum::sha256_file(output_path)?,
+    })
+}
+
+/// Flushes the file's data, then its directory so the new entry itself survives a crash.
+fn sync_to_disk(file: &File, path: &Path) -> io::Result<()> {
+    file.sync_all()?;
+    // Directories cannot be opened for syncing on every platform
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::{Read, Write};
     use tempfile::TempDir;
 
     #[test]
@@ -37,12 +314,94 @@ mod tests {
         file2.write_all(b"World!").unwrap();
 
         let files = vec![file1_path, file2_path];
-        merge_files(&files, &output_path).unwrap();
+        let digests = merge_files(&files, &output_path, &CopyOptions::default()).unwrap();
 
         let mut output_file = File::open(&output_path).unwrap();
         let mut content = String::new();
         output_file.read_to_string(&mut content).unwrap();
 
         assert_eq!(content, "Hello, World!");
+
+        assert_eq!(digests.inputs.len(), 2);
+        assert_eq!(digests.inputs[0], checksum::sha256_file(&files[0]).unwrap());
+        assert_eq!(digests.inputs[1], checksum::sha256_file(&files[1]).unwrap());
+        assert_eq!(digests.output, checksum::sha256_file(&output_path).unwrap());
+    }
+
+    #[test]
+    fn test_merge_files_with_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<_> = (0..3).map(|i| temp_dir.path().join(format!("{}.bin", i))).collect();
+        for (i, path) in files.iter().enumerate() {
+            fs::write(path, vec![i as u8; 1000 + i]).unwrap();
+        }
+        let output_path = temp_dir.path().join("out/merged.bin");
+
+        let options = CopyOptions { buffer_size: 7, fsync: true, preallocate: true, io_limit: Some(1 << 20) };
+        let digests = merge_files(&files, &output_path, &options).unwrap();
+
+        let content = fs::read(&output_path).unwrap();
+        assert_eq!(content.len(), 3003);
+        assert_eq!(&content[1000..1002], &[1, 1]);
+        assert_eq!(digests.output, checksum::sha256_file(&output_path).unwrap());
+        assert_eq!(digests, merge_files(&files, &output_path, &CopyOptions::default()).unwrap());
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        let temp_dir = TempDir::new().unwrap();
+        let files = vec![temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt")];
+        fs::write(&files[0], b"[tag]jingle Chapter one. outro").unwrap();
+        fs::write(&files[1], b"Chapter two.").unwrap();
+        let output_path = temp_dir.path().join("out.txt");
+
+        let ranges = vec![vec![0..5, 12..24], vec![WHOLE_FILE]];
+        let options = CopyOptions { buffer_size: 4, ..CopyOptions::default() };
+        let digests = merge_ranges(&files, &ranges, &output_path, &options).unwrap();
+
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "[tag]Chapter one.Chapter two.");
+        assert_eq!(digests.inputs[0], checksum::sha256_file(&files[0]).unwrap());
+        assert_eq!(digests.output, checksum::sha256_file(&output_path).unwrap());
+        assert!(!resume::partial_path(&output_path).exists());
+        assert!(!resume::progress_path(&output_path).exists());
+    }
+
+    #[test]
+    fn test_merge_ranges_resumes() {
+        let temp_dir = TempDir::new().unwrap();
+        let files = vec![temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt")];
+        fs::write(&files[0], b"[tag]jingle Chapter one. outro").unwrap();
+        fs::write(&files[1], b"Chapter two.").unwrap();
+        let output_path = temp_dir.path().join("out.txt");
+        let ranges = vec![vec![0..5, 12..24], vec![WHOLE_FILE]];
+        let expected = merge_ranges(&files, &ranges, &output_path, &CopyOptions::default()).unwrap();
+        fs::remove_file(&output_path).unwrap();
+
+        // Interrupted four bytes into b.txt, with unrecorded bytes after the checkpoint
+        let progress = Progress { input: 1, position: 4, written: 21, ..Progress::start(&files, &ranges).unwrap() };
+        progress.save(&output_path).unwrap();
+        fs::write(resume::partial_path(&output_path), b"[tag]Chapter one.Chapxxxx").unwrap();
+
+        let digests = merge_ranges(&files, &ranges, &output_path, &CopyOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "[tag]Chapter one.Chapter two.");
+        assert_eq!(digests, expected);
+        assert!(!resume::progress_path(&output_path).exists());
+
+        // Progress recorded for different inputs is ignored
+        let stale = Progress { inputs: Vec::new(), ..progress };
+        stale.save(&output_path).unwrap();
+        fs::write(resume::partial_path(&output_path), b"garbage").unwrap();
+        assert_eq!(merge_ranges(&files, &ranges, &output_path, &CopyOptions::default()).unwrap(), expected);
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "[tag]Chapter one.Chapter two.");
+    }
+
+    #[test]
+    fn test_concat_lists_are_unique() {
+        let (first, _) = create_concat_list().unwrap();
+        let (second, _) = create_concat_list().unwrap();
+        assert_ne!(first, second);
+        assert!(first.exists() && second.exists());
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
     }
 }
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use crate::mp3::{AudioFormat, StreamInfo};
+
+/// An input whose stream format differs from the first file in its group.
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub path: PathBuf,
+    pub format: AudioFormat,
+}
+
+/// Compares each input against the first file with a recognisable format. Sample rate and
+/// channel differences always count; bitrate only counts when both files are CBR, since
+/// mixing VBR and CBR frames concatenates cleanly.
+pub fn find_mismatches(files: &[PathBuf], infos: &[StreamInfo]) -> (Option<AudioFormat>, Vec<Mismatch>) {
+    let reference = infos.iter().find_map(|info| info.format);
+    let Some(reference) = reference else {
+        return (None, Vec::new());
+    };
+
+    let mismatches = files.iter()
+        .zip(infos)
+        .filter_map(|(path, info)| {
+            let format = info.format?;
+            let bitrate_differs = matches!(
+                (reference.bitrate_kbps, format.bitrate_kbps),
+                (Some(a), Some(b)) if a != b
+            );
+            if format.sample_rate != reference.sample_rate
+                || format.channels != reference.channels
+                || bitrate_differs
+            {
+                Some(Mismatch { path: path.clone(), format })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    (Some(reference), mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(sample_rate: u32, channels: u8, bitrate_kbps: Option<u32>) -> StreamInfo {
+        StreamInfo {
+            format: Some(AudioFormat { sample_rate, channels, bitrate_kbps }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_mismatches() {
+        let files = vec![
+            PathBuf::from("1.mp3"),
+            PathBuf::from("2.mp3"),
+            PathBuf::from("3.mp3"),
+            PathBuf::from("4.mp3"),
+            PathBuf::from("5.mp3"),
+        ];
+        let infos = vec![
+            info(44100, 2, Some(128)),
+            info(44100, 2, None),        // VBR alongside CBR is fine
+            info(48000, 2, Some(128)),   // sample rate differs
+            info(44100, 1, Some(128)),   // mono vs stereo
+            info(44100, 2, Some(64)),    // CBR bitrate differs
+        ];
+
+        let (reference, mismatches) = find_mismatches(&files, &infos);
+        assert_eq!(reference, infos[0].format);
+        let paths: Vec<_> = mismatches.iter().map(|m| m.path.clone()).collect();
+        assert_eq!(paths, vec![files[2].clone(), files[3].clone(), files[4].clone()]);
+    }
+
+    #[test]
+    fn test_find_mismatches_all_same() {
+        let files = vec![PathBuf::from("1.mp3"), PathBuf::from("2.mp3")];
+        let infos = vec![info(44100, 2, Some(128)), info(44100, 2, Some(128))];
+
+        let (_, mismatches) = find_mismatches(&files, &infos);
+        assert!(mismatches.is_empty());
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+const ID3V2_HEADER_LEN: usize = 10;
+const ID3V1_TAG_LEN: i64 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MpegVersion {
+    V1,
+    V2,
+    V2_5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layer {
+    L1,
+    L2,
+    L3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameHeader {
+    pub version: MpegVersion,
+    pub layer: Layer,
+    pub bitrate_kbps: u32,
+    pub sample_rate: u32,
+    pub padding: bool,
+    pub mono: bool,
+}
+
+impl FrameHeader {
+    /// Parses a 4-byte MPEG audio frame header. Returns `None` for anything that is
+    /// not a valid header, including free-format and reserved values.
+    pub fn parse(bytes: [u8; 4]) -> Option<Self> {
+        if bytes[0] != 0xFF || bytes[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = match (bytes[1] >> 3) & 0b11 {
+            0b00 => MpegVersion::V2_5,
+            0b10 => MpegVersion::V2,
+            0b11 => MpegVersion::V1,
+            _ => return None,
+        };
+        let layer = match (bytes[1] >> 1) & 0b11 {
+            0b01 => Layer::L3,
+            0b10 => Layer::L2,
+            0b11 => Layer::L1,
+            _ => return None,
+        };
+
+        let bitrate_index = (bytes[2] >> 4) as usize;
+        if bitrate_index == 0 || bitrate_index == 15 {
+            return None;
+        }
+        let bitrate_kbps = match (version, layer) {
+            (MpegVersion::V1, Layer::L1) => [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+            (MpegVersion::V1, Layer::L2) => [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+            (MpegVersion::V1, Layer::L3) => [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+            (_, Layer::L1) => [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+            (_, _) => [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        }[bitrate_index];
+
+        let sample_rate_index = ((bytes[2] >> 2) & 0b11) as usize;
+        if sample_rate_index == 3 {
+            return None;
+        }
+        let sample_rate = match version {
+            MpegVersion::V1 => [44100, 48000, 32000],
+            MpegVersion::V2 => [22050, 24000, 16000],
+            MpegVersion::V2_5 => [11025, 12000, 8000],
+        }[sample_rate_index];
+
+        Some(FrameHeader {
+            version,
+            layer,
+            bitrate_kbps,
+            sample_rate,
+            padding: (bytes[2] >> 1) & 1 == 1,
+            mono: bytes[3] >> 6 == 0b11,
+        })
+    }
+
+    pub fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.version) {
+            (Layer::L1, _) => 384,
+            (Layer::L2, _) => 1152,
+            (Layer::L3, MpegVersion::V1) => 1152,
+            (Layer::L3, _) => 576,
+        }
+    }
+
+    /// Total frame length in bytes, including the 4-byte header.
+    pub fn frame_len(&self) -> usize {
+        let bitrate = self.bitrate_kbps as usize * 1000;
+        let sample_rate = self.sample_rate as usize;
+        let padding = self.padding as usize;
+        match (self.layer, self.version) {
+            (Layer::L1, _) => (12 * bitrate / sample_rate + padding) * 4,
+            (Layer::L3, MpegVersion::V2 | MpegVersion::V2_5) => 72 * bitrate / sample_rate + padding,
+            _ => 144 * bitrate / sample_rate + padding,
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.samples_per_frame() as f64 / self.sample_rate as f64
+    }
+
+    /// Length of the Layer III side information that follows the header.
+    fn side_info_len(&self) -> usize {
+        match (self.version, self.mono) {
+            (MpegVersion::V1, true) => 17,
+            (MpegVersion::V1, false) => 32,
+            (_, true) => 9,
+            (_, false) => 17,
+        }
+    }
+}
+
+/// Gapless playback data from a Xing/Info frame and its LAME extension, in samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EncoderGap {
+    /// Silence the encoder added at the start
+    pub delay: u32,
+    /// Silence added at the end to fill the last frame
+    pub padding: u32,
+    /// Samples in the Xing/Info frame itself, which holds no audio
+    pub info_frame: u32,
+}
+
+/// Offset of the LAME extension (encoder string, then delay/padding at +21) from the
+/// start of the Xing tag, as written by LAME and ffmpeg.
+const LAME_TAG_OFFSET: usize = 120;
+
+/// Reads the Xing/Info tag in the body of a stream's first frame, if there is one.
+pub fn parse_encoder_gap(frame: &FrameHeader, body: &[u8]) -> Option<EncoderGap> {
+    if frame.layer != Layer::L3 {
+        return None;
+    }
+    let tag = body.get(frame.side_info_len()..)?;
+    if !tag.starts_with(b"Xing") && !tag.starts_with(b"Info") {
+        return None;
+    }
+    let mut gap = EncoderGap { info_frame: frame.samples_per_frame(), ..Default::default() };
+    let lame = tag.get(LAME_TAG_OFFSET..)?;
+    if lame.len() >= 24 && lame[..4].iter().all(u8::is_ascii_alphanumeric) {
+        let bytes = &lame[21..24];
+        gap.delay = ((bytes[0] as u32) << 4) | (bytes[1] as u32 >> 4);
+        gap.padding = ((bytes[1] as u32 & 0x0F) << 8) | bytes[2] as u32;
+    }
+    Some(gap)
+}
+
+/// The stream parameters that must agree across inputs for a byte-level concatenation
+/// to play back correctly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u8,
+    /// `None` when the bitrate varies between frames (VBR).
+    pub bitrate_kbps: Option<u32>,
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = if self.channels == 1 { "mono" } else { "stereo" };
+        match self.bitrate_kbps {
+            Some(kbps) => write!(f, "{} Hz, {}, {} kbps", self.sample_rate, channels, kbps),
+            None => write!(f, "{} Hz, {}, VBR", self.sample_rate, channels),
+        }
+    }
+}
+
+/// Summary of the MPEG audio frames found in a file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamInfo {
+    pub frames: u64,
+    pub duration: Duration,
+    /// Format of the first frame, or `None` if no frames were found.
+    pub format: Option<AudioFormat>,
+    /// Bytes that were neither part of a frame nor of a tag.
+    pub unrecognised_bytes: u64,
+    /// Encoder delay and padding, if the first frame is a Xing/Info frame.
+    pub encoder_gap: Option<EncoderGap>,
+}
+
+/// Walks every frame in the file to estimate its playing time. Tags (ID3v2/ID3v1) are
+/// skipped, and unrecognised bytes are stepped over one at a time until the next frame
+/// header, so concatenated files with embedded tags are measured correctly.
+pub fn scan(path: &Path) -> io::Result<StreamInfo> {
+    scan_frames(path, |_, _| {})
+}
+
+/// Like `scan`, also calling `on_frame` with the byte offset and header of every frame.
+pub fn scan_frames(path: &Path, mut on_frame: impl FnMut(u64, &FrameHeader)) -> io::Result<StreamInfo> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut info = StreamInfo::default();
+    let mut seconds = 0.0;
+    let mut offset: u64 = 0;
+
+    loop {
+        let mut header = [0u8; 4];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+
+        if &header[..3] == b"ID3" {
+            let mut rest = [0u8; ID3V2_HEADER_LEN - 4];
+            reader.read_exact(&mut rest)?;
+            let size = rest[2..6].iter().fold(0i64, |acc, b| (acc << 7) | (*b & 0x7F) as i64);
+            let footer = if rest[1] & 0x10 != 0 { 10 } else { 0 };
+            reader.seek_relative(size + footer)?;
+            offset += (ID3V2_HEADER_LEN as i64 + size + footer) as u64;
+        } else if &header[..3] == b"TAG" {
+            reader.seek_relative(ID3V1_TAG_LEN - 4)?;
+            offset += ID3V1_TAG_LEN as u64;
+        } else if let Some(frame) = FrameHeader::parse(header) {
+            match &mut info.format {
+                None => {
+                    info.format = Some(AudioFormat {
+                        sample_rate: frame.sample_rate,
+                        channels: if frame.mono { 1 } else { 2 },
+                        bitrate_kbps: Some(frame.bitrate_kbps),
+                    });
+                }
+                Some(format) if format.bitrate_kbps != Some(frame.bitrate_kbps) => format.bitrate_kbps = None,
+                Some(_) => {}
+            }
+            on_frame(offset, &frame);
+            if info.frames == 0 {
+                let mut body = Vec::new();
+                reader.by_ref().take(frame.frame_len() as u64 - 4).read_to_end(&mut body)?;
+                info.encoder_gap = parse_encoder_gap(&frame, &body);
+            } else {
+                reader.seek_relative(frame.frame_len() as i64 - 4)?;
+            }
+            info.frames += 1;
+            seconds += frame.duration();
+            offset += frame.frame_len() as u64;
+        } else {
+            info.unrecognised_bytes += 1;
+            reader.seek_relative(-3)?;
+            offset += 1;
+        }
+    }
+
+    info.duration = Duration::from_secs_f64(seconds);
+    Ok(info)
+}
+
+/// Builds `count` silent MPEG-1 Layer III frames (128kbps, 44.1kHz, stereo).
+#[cfg(test)]
+pub fn synth_frames(count: usize) -> Vec<u8> {
+    synth_frames_with_header([0xFF, 0xFB, 0x90, 0x00], count)
+}
+
+#[cfg(test)]
+pub fn synth_frames_with_header(header: [u8; 4], count: usize) -> Vec<u8> {
+    let frame_len = FrameHeader::parse(header).unwrap().frame_len();
+    let mut data = Vec::with_capacity(frame_len * count);
+    for _ in 0..count {
+        data.extend_from_slice(&header);
+        data.resize(data.len() + frame_len - 4, 0);
+    }
+    data
+}
+
+/// A LAME-style Info frame recording the given encoder delay and padding.
+#[cfg(test)]
+pub fn synth_info_frame(delay: u32, padding: u32) -> Vec<u8> {
+    let mut frame = synth_frames(1);
+    let tag = 4 + 32;
+    frame[tag..tag + 4].copy_from_slice(b"Info");
+    let lame = tag + LAME_TAG_OFFSET;
+    frame[lame..lame + 4].copy_from_slice(b"LAME");
+    frame[lame + 21] = (delay >> 4) as u8;
+    frame[lame + 22] = ((delay & 0x0F) << 4) as u8 | (padding >> 8) as u8;
+    frame[lame + 23] = padding as u8;
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_header() {
+        let header = FrameHeader::parse([0xFF, 0xFB, 0x90, 0x00]).unwrap();
+        assert_eq!(header.version, MpegVersion::V1);
+        assert_eq!(header.layer, Layer::L3);
+        assert_eq!(header.bitrate_kbps, 128);
+        assert_eq!(header.sample_rate, 44100);
+        assert_eq!(header.frame_len(), 417);
+        assert_eq!(header.samples_per_frame(), 1152);
+        assert!(!header.mono);
+
+        assert!(FrameHeader::parse([0x00, 0x00, 0x00, 0x00]).is_none());
+        // Bitrate index 15 is invalid
+        assert!(FrameHeader::parse([0xFF, 0xFB, 0xF0, 0x00]).is_none());
+    }
+
+    #[test]
+    fn test_scan_skips_tags_and_junk() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.mp3");
+
+        let mut data = vec![b'I', b'D', b'3', 4, 0, 0, 0, 0, 0, 5, 1, 2, 3, 4, 5];
+        data.extend(synth_frames(10));
+        data.extend_from_slice(b"junk");
+        data.extend(synth_frames(5));
+        let mut tag = b"TAG".to_vec();
+        tag.resize(128, 0);
+        data.extend(tag);
+        fs::write(&path, data).unwrap();
+
+        let info = scan(&path).unwrap();
+        assert_eq!(info.frames, 15);
+        assert_eq!(info.unrecognised_bytes, 4);
+        let expected = 15.0 * 1152.0 / 44100.0;
+        assert!((info.duration.as_secs_f64() - expected).abs() < 0.001);
+        assert_eq!(
+            info.format,
+            Some(AudioFormat { sample_rate: 44100, channels: 2, bitrate_kbps: Some(128) })
+        );
+    }
+
+    #[test]
+    fn test_scan_reads_encoder_gap() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("lame.mp3");
+
+        let mut data = synth_info_frame(576, 1000);
+        data.extend(synth_frames(3));
+        fs::write(&path, data).unwrap();
+        let info = scan(&path).unwrap();
+        assert_eq!(info.frames, 4);
+        assert_eq!(info.encoder_gap, Some(EncoderGap { delay: 576, padding: 1000, info_frame: 1152 }));
+
+        fs::write(&path, synth_frames(3)).unwrap();
+        assert_eq!(scan(&path).unwrap().encoder_gap, None);
+    }
+
+    #[test]
+    fn test_scan_detects_vbr() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("vbr.mp3");
+
+        let mut data = synth_frames(3);
+        // Same stream at 192kbps
+        data.extend(synth_frames_with_header([0xFF, 0xFB, 0xB0, 0x00], 3));
+        fs::write(&path, data).unwrap();
+
+        let format = scan(&path).unwrap().format.unwrap();
+        assert_eq!(format.bitrate_kbps, None);
+        assert_eq!(format.to_string(), "44100 Hz, stereo, VBR");
+    }
+}
@@ -0,0 +1,454 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::args::{Args, OnError, OnGap, OnMismatch};
+use crate::attributes;
+use crate::book_meta;
+use crate::chapters;
+use crate::checksum;
+use crate::cleanup;
+use crate::cover;
+use crate::gapless;
+use crate::manifest::{self, Manifest};
+use crate::merger;
+use crate::mismatch;
+use crate::mp3::{self, StreamInfo};
+use crate::planner::PlannedGroup;
+use crate::summary::RunSummary;
+use crate::trim::{self, TrimmedInput};
+use crate::validate;
+use crate::verify;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupOutcome {
+    Merged,
+    Skipped,
+    Failed,
+}
+
+/// Receives progress from the merge pipeline, so the same pipeline can drive the
+/// console output and the TUI.
+pub trait Reporter {
+    fn group_started(&mut self, _index: usize, _group: &PlannedGroup) {}
+    fn group_finished(&mut self, _index: usize, _outcome: GroupOutcome) {}
+    fn info(&mut self, message: &str);
+    fn warn(&mut self, message: &str);
+    /// Reports an error. Returns `false` if processing should stop.
+    fn error(&mut self, message: &str) -> bool;
+}
+
+/// Prints progress to stdout/stderr and applies the `--on-error` strategy.
+pub struct ConsoleReporter {
+    pub on_error: OnError,
+}
+
+impl Reporter for ConsoleReporter {
+    fn group_started(&mut self, _index: usize, group: &PlannedGroup) {
+        log::info!(
+            "Merging {} files from '{}' to '{}'",
+            group.files.len(),
+            group.source_dir.display(),
+            group.output.display()
+        );
+    }
+
+    fn info(&mut self, message: &str) {
+        log::info!("{}", message);
+    }
+
+    fn warn(&mut self, message: &str) {
+        log::warn!("{}", message);
+    }
+
+    fn error(&mut self, message: &str) -> bool {
+        log::error!("{}", message);
+        match self.on_error {
+            OnError::Halt => {
+                log::error!("Halting due to error.");
+                return false;
+            },
+            OnError::Skip => {
+                log::error!("Skipping...");
+            },
+            OnError::Prompt => {
+                log::error!("Prompt strategy not yet implemented. Defaulting to Skip behavior.");
+            }
+        }
+        true
+    }
+}
+
+/// Runs every planned merge: format checks, merge, verification, manifest and source cleanup.
+pub fn merge_plan(
+    plan: &[PlannedGroup],
+    source_root: &Path,
+    args: &Args,
+    run_manifest: &mut Option<Manifest>,
+    reporter: &mut dyn Reporter,
+) -> RunSummary {
+    let mut summary = RunSummary::default();
+    for (index, group) in plan.iter().enumerate() {
+        reporter.group_started(index, group);
+        let started = Instant::now();
+        let result = merge_group(group, source_root, args, run_manifest, reporter, &mut summary);
+        log::debug!("Finished '{}' in {:.2?}", group.output.display(), started.elapsed());
+        match result {
+            Some(outcome) => {
+                summary.record(outcome);
+                reporter.group_finished(index, outcome);
+            }
+            None => {
+                summary.record(GroupOutcome::Failed);
+                summary.not_attempted = plan.len() - index - 1;
+                reporter.group_finished(index, GroupOutcome::Failed);
+                break;
+            }
+        }
+    }
+    summary
+}
+
+/// Merges one group. Returns `None` if the reporter asked to stop.
+fn merge_group(
+    group: &PlannedGroup,
+    source_root: &Path,
+    args: &Args,
+    run_manifest: &mut Option<Manifest>,
+    reporter: &mut dyn Reporter,
+    summary: &mut RunSummary,
+) -> Option<GroupOutcome> {
+    let tolerance = Duration::from_secs_f64(args.duration_tolerance);
+    let dir = &group.source_dir;
+    let files = &group.files;
+    let output_file = &group.output;
+    // Reports an error that aborts this group
+    let fail = |reporter: &mut dyn Reporter, message: String| {
+        reporter.error(&message).then_some(GroupOutcome::Failed)
+    };
+
+    let infos = match files.iter().map(|f| mp3::scan(f)).collect::<std::io::Result<Vec<_>>>() {
+        Ok(infos) => infos,
+        Err(e) => return fail(reporter, format!("Error reading files for {}: {}", dir.display(), e)),
+    };
+
+    match validate::find_invalid_inputs(files, &infos, args.min_file_size as u64) {
+        Ok(invalid) if !invalid.is_empty() => {
+            let mut message = format!("Corrupt or incomplete input in {}; nothing was merged:", dir.display());
+            for input in &invalid {
+                message.push_str(&format!("\n  - {}: {}", input.path.display(), input.problem));
+            }
+            return fail(reporter, message);
+        }
+        Ok(_) => {}
+        Err(e) => return fail(reporter, format!("Error reading files for {}: {}", dir.display(), e)),
+    }
+
+    let (reference, mismatches) = mismatch::find_mismatches(files, &infos);
+    let mut reencode_format = None;
+    if let Some(reference) = reference && !mismatches.is_empty() {
+        let mut message = format!("Format mismatch in {} (first file is {}):", dir.display(), reference);
+        for m in &mismatches {
+            message.push_str(&format!("\n  - {}: {}", m.path.file_name().unwrap_or_default().to_string_lossy(), m.format));
+        }
+        reporter.warn(&message);
+        match args.on_mismatch {
+            OnMismatch::Warn => {}
+            OnMismatch::Skip => {
+                reporter.info("Skipping folder due to format mismatch.");
+                return Some(GroupOutcome::Skipped);
+            }
+            OnMismatch::Reencode => {
+                reporter.info(&format!("Re-encoding to {}", reference));
+                reencode_format = Some(reference);
+            }
+        }
+    }
+
+    if reencode_format.is_none() && args.on_gap != OnGap::Ignore {
+        let gaps = gapless::find_gaps(files, &infos);
+        if let Some(longest) = gaps.iter().map(|gap| gap.duration).max() {
+            let mut message = format!(
+                "{} of {} joins in {} have encoder delay/padding and may play with gaps or clicks (up to {} ms):",
+                gaps.len(),
+                files.len() - 1,
+                dir.display(),
+                longest.as_millis()
+            );
+            for gap in &gaps {
+                let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                message.push_str(&format!("\n  - {} -> {}", name(&gap.after), name(&gap.before)));
+            }
+            if args.on_gap == OnGap::Reencode {
+                reporter.info(&message);
+                reencode_format = reference;
+                if let Some(format) = reference {
+                    reporter.info(&format!("Re-encoding to {} for gapless joins", format));
+                }
+            } else {
+                message.push_str("\nUse --on-gap reencode to merge this folder gaplessly.");
+                reporter.warn(&message);
+            }
+        }
+    }
+
+    let default_trim = args.trim();
+    let trims = files.iter()
+        .zip(&infos)
+        .map(|(file, info)| match group.trim_for(file, default_trim) {
+            trim if trim.is_zero() => Ok(TrimmedInput::whole(info)),
+            trim => trim::trim_input(file, trim)
+                .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", file.display(), e))),
+        })
+        .collect::<std::io::Result<Vec<_>>>();
+    let trims = match trims {
+        Ok(trims) => trims,
+        Err(e) => return fail(reporter, format!("Error trimming files for {}: {}", dir.display(), e)),
+    };
+    // What actually goes into the output, for the duration check and chapter marks
+    let kept: Vec<StreamInfo> = infos.iter()
+        .zip(&trims)
+        .map(|(info, trim)| StreamInfo { duration: trim.duration(), ..info.clone() })
+        .collect();
+
+    let copy_options = args.copy_options();
+    let result = match &reencode_format {
+        Some(format) => merger::reencode_files(files, &trims, output_file, format, &copy_options),
+        None if trims.iter().all(|t| t.ranges == [merger::WHOLE_FILE]) => {
+            merger::merge_files(files, output_file, &copy_options)
+        }
+        None => {
+            let ranges: Vec<_> = trims.iter().map(|t| t.ranges.clone()).collect();
+            merger::merge_ranges(files, &ranges, output_file, &copy_options)
+        }
+    };
+    let mut digests = match result {
+        Ok(digests) => digests,
+        Err(e) => return fail(reporter, format!("Error merging files for {}: {}", dir.display(), e)),
+    };
+
+    if !args.no_cover_art && let Some(image) = find_group_cover(group) {
+        // The tag rewrite changes the output, so its digest is taken again afterwards
+        match cover::embed_cover(output_file, &image).and_then(|_| checksum::sha256_file(output_file)) {
+            Ok(digest) => digests.output = digest,
+            Err(e) => reporter.warn(&format!("could not embed cover art {}: {}", image.display(), e)),
+        }
+    }
+
+    if let Some(meta) = &group.meta {
+        match book_meta::write_tags(output_file, meta).and_then(|_| checksum::sha256_file(output_file)) {
+            Ok(digest) => digests.output = digest,
+            Err(e) => reporter.warn(&format!("could not write tags to {}: {}", output_file.display(), e)),
+        }
+    }
+
+    if let Some(times) = &args.preserve_times
+        && let Err(e) = attributes::preserve_attributes(output_file, files, times)
+    {
+        reporter.warn(&format!("could not copy timestamps to {}: {}", output_file.display(), e));
+    }
+
+    match checksum::find_sidecar_mismatches(files, &digests.inputs) {
+        Ok(corrupted) if !corrupted.is_empty() => {
+            let mut message = format!("Checksum mismatch in {}; the merged output has been removed:", dir.display());
+            for path in &corrupted {
+                message.push_str(&format!("\n  - {}", path.display()));
+            }
+            let _ = std::fs::remove_file(output_file);
+            return fail(reporter, message);
+        }
+        Ok(_) => {}
+        Err(e) => reporter.warn(&format!("could not read checksum sidecars for {}: {}", dir.display(), e)),
+    }
+
+    if args.write_checksums && let Err(e) = checksum::write_sidecar(output_file, &digests.output) {
+        reporter.warn(&format!("could not write checksum for {}: {}", output_file.display(), e));
+    }
+
+    let output_info = match read_back(output_file, &kept, tolerance) {
+        Ok(info) => info,
+        Err(message) => return fail(reporter, message),
+    };
+
+    if let Some(format) = &args.chapters {
+        let text = chapters::format_chapters(&chapters::build_chapters(files, &kept), format);
+        let path = chapters::chapters_path(output_file);
+        if let Err(e) = std::fs::write(&path, text) {
+            reporter.warn(&format!("could not write chapters {}: {}", path.display(), e));
+        }
+    }
+
+    if let (Some(manifest), Some(manifest_path)) = (run_manifest.as_mut(), &args.manifest) {
+        match manifest::OutputEntry::from_merge(output_file, &output_info, dir, files, &infos, &digests) {
+            Ok(entry) => manifest.outputs.push(entry),
+            Err(e) => reporter.warn(&format!("could not record {} in manifest: {}", output_file.display(), e)),
+        }
+        // Saved after every output so a halted run still leaves a valid manifest
+        if let Err(e) = manifest.save(manifest_path) {
+            reporter.warn(&format!("could not write manifest {}: {}", manifest_path.display(), e));
+        }
+    }
+
+    // Sources are only touched once the output is known to be complete
+    let mut outcome = GroupOutcome::Merged;
+    if let Err(e) = cleanup::handle_sources(files, source_root, &args.after_merge) {
+        outcome = GroupOutcome::Failed;
+        if !reporter.error(&format!("Error handling source files for {}: {}", dir.display(), e)) {
+            return None;
+        }
+    }
+
+    if outcome == GroupOutcome::Merged {
+        summary.bytes += std::fs::metadata(output_file).map(|m| m.len()).unwrap_or(0);
+        summary.duration += output_info.duration;
+    }
+    Some(outcome)
+}
+
+/// Scan a finished output and check its length against what went in. An output that
+/// can't be read or came out the wrong length is not kept, so it is neither recorded nor
+/// mistaken for a finished output on the next run.
+fn read_back(output_file: &Path, kept: &[StreamInfo], tolerance: Duration) -> Result<StreamInfo, String> {
+    let remove_output = || {
+        let _ = std::fs::remove_file(output_file);
+        let _ = std::fs::remove_file(checksum::sidecar_path(output_file));
+    };
+    let output_info = match mp3::scan(output_file) {
+        Ok(info) => info,
+        Err(e) => {
+            remove_output();
+            return Err(format!(
+                "Could not read back {}: {}; the merged output has been removed",
+                output_file.display(),
+                e
+            ));
+        }
+    };
+
+    let check = verify::check_duration(kept, &output_info);
+    if !check.is_within(tolerance) {
+        remove_output();
+        return Err(format!(
+            "Duration mismatch for {}: inputs total {:.2}s, output is {:.2}s; the merged output has been removed",
+            output_file.display(),
+            check.expected.as_secs_f64(),
+            check.actual.as_secs_f64()
+        ));
+    }
+    Ok(output_info)
+}
+
+/// Cover art from the group's folder, or failing that from the folders its files are in.
+fn find_group_cover(group: &PlannedGroup) -> Option<std::path::PathBuf> {
+    let mut dirs: Vec<&Path> = vec![&group.source_dir];
+    for parent in group.files.iter().filter_map(|f| f.parent()) {
+        if !dirs.contains(&parent) {
+            dirs.push(parent);
+        }
+    }
+    cover::find_cover(&dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Records outcomes and stops at the first error, like `--on-error halt` without exiting.
+    #[derive(Default)]
+    struct RecordingReporter {
+        outcomes: Vec<(usize, GroupOutcome)>,
+        errors: Vec<String>,
+    }
+
+    impl Reporter for RecordingReporter {
+        fn group_finished(&mut self, index: usize, outcome: GroupOutcome) {
+            self.outcomes.push((index, outcome));
+        }
+        fn info(&mut self, _message: &str) {}
+        fn warn(&mut self, _message: &str) {}
+        fn error(&mut self, message: &str) -> bool {
+            self.errors.push(message.to_string());
+            false
+        }
+    }
+
+    #[test]
+    fn test_read_back_removes_an_unreadable_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("book.mp3");
+        // An ID3 tag cut off in its header
+        fs::write(&output, b"ID3\x03").unwrap();
+        fs::write(checksum::sidecar_path(&output), "digest").unwrap();
+
+        let message = read_back(&output, &[], Duration::ZERO).unwrap_err();
+        assert!(message.starts_with("Could not read back"), "{}", message);
+        assert!(message.ends_with("the merged output has been removed"), "{}", message);
+        assert!(!output.exists());
+        assert!(!checksum::sidecar_path(&output).exists());
+    }
+
+    #[test]
+    fn test_read_back_removes_an_output_of_the_wrong_length() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("1.mp3");
+        let output = temp_dir.path().join("book.mp3");
+        fs::write(&input, mp3::synth_frames(50)).unwrap();
+        fs::write(&output, mp3::synth_frames(5)).unwrap();
+        let kept = vec![mp3::scan(&input).unwrap()];
+
+        let message = read_back(&output, &kept, Duration::from_millis(10)).unwrap_err();
+        assert!(message.starts_with("Duration mismatch"), "{}", message);
+        assert!(!output.exists());
+
+        fs::write(&output, mp3::synth_frames(50)).unwrap();
+        let info = read_back(&output, &kept, Duration::ZERO).unwrap();
+        assert_eq!(info.duration, kept[0].duration);
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn test_merge_plan_stops_when_reporter_halts() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = temp_dir.path().join("good");
+        fs::create_dir(&good).unwrap();
+        fs::write(good.join("1.mp3"), mp3::synth_frames(5)).unwrap();
+
+        let out = temp_dir.path().join("out");
+        let plan = vec![
+            PlannedGroup {
+                source_dir: good.clone(),
+                output: out.join("good.mp3"),
+                files: vec![good.join("1.mp3")],
+                trims: Default::default(),
+                meta: None,
+            },
+            PlannedGroup {
+                source_dir: temp_dir.path().join("missing"),
+                output: out.join("missing.mp3"),
+                files: vec![temp_dir.path().join("missing/1.mp3")],
+                trims: Default::default(),
+                meta: None,
+            },
+            PlannedGroup {
+                source_dir: good.clone(),
+                output: out.join("never.mp3"),
+                files: vec![good.join("1.mp3")],
+                trims: Default::default(),
+                meta: None,
+            },
+        ];
+
+        let args = Args::parse_from(["app"]);
+        let mut reporter = RecordingReporter::default();
+        let summary = merge_plan(&plan, temp_dir.path(), &args, &mut None, &mut reporter);
+
+        assert_eq!(reporter.outcomes, vec![(0, GroupOutcome::Merged), (1, GroupOutcome::Failed)]);
+        assert_eq!((summary.merged, summary.failed, summary.not_attempted), (1, 1, 1));
+        assert_eq!(summary.bytes, 417 * 5);
+        assert_eq!(reporter.errors.len(), 1);
+        assert!(out.join("good.mp3").exists());
+        assert!(!out.join("never.mp3").exists());
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::planner::PlannedGroup;
+
+pub const PLAN_VERSION: u32 = 1;
+
+/// An editable merge plan written by `plan` and executed verbatim by `apply`. Groups can be
+/// reordered or removed, files reordered or dropped, and outputs renamed in between.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PlanFile {
+    pub version: u32,
+    pub source: PathBuf,
+    pub output_dir: PathBuf,
+    pub groups: Vec<PlannedGroup>,
+}
+
+impl PlanFile {
+    /// Paths are stored absolute so the plan can be applied from any working directory.
+    pub fn new(source: &Path, output_dir: &Path, groups: &[PlannedGroup]) -> Self {
+        PlanFile {
+            version: PLAN_VERSION,
+            source: absolute(source),
+            output_dir: absolute(output_dir),
+            groups: groups.iter()
+                .map(|group| PlannedGroup {
+                    source_dir: absolute(&group.source_dir),
+                    output: absolute(&group.output),
+                    files: group.files.iter().map(|f| absolute(f)).collect(),
+                    trims: group.trims.iter().map(|(f, t)| (absolute(f), t.clone())).collect(),
+                    meta: group.meta.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, data + "\n")
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let plan: PlanFile = serde_json::from_str(&data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if plan.version != PLAN_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported plan version {} (expected {})", plan.version, PLAN_VERSION),
+            ));
+        }
+        Ok(plan)
+    }
+
+    /// Problems that would make the plan fail half-way: empty groups, missing inputs and
+    /// unusable trims.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for group in &self.groups {
+            if group.files.is_empty() {
+                problems.push(format!("group for '{}' has no files", group.output.display()));
+            }
+            for file in group.files.iter().filter(|f| !f.is_file()) {
+                problems.push(format!("'{}' does not exist", file.display()));
+            }
+            for (file, trim) in &group.trims {
+                if let Some(problem) = trim.problem() {
+                    problems.push(format!("trim for '{}': {}", file.display(), problem));
+                }
+            }
+        }
+        problems
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trim::TrimOverride;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plan_file_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let book = temp_dir.path().join("src/Book");
+        fs::create_dir_all(&book).unwrap();
+        fs::write(book.join("1.mp3"), b"").unwrap();
+
+        let groups = vec![PlannedGroup {
+            source_dir: book.clone(),
+            output: temp_dir.path().join("out/Book.mp3"),
+            files: vec![book.join("1.mp3"), book.join("2.mp3")],
+            trims: Default::default(),
+            meta: None,
+        }];
+        let plan = PlanFile::new(&temp_dir.path().join("src"), &temp_dir.path().join("out"), &groups);
+        let path = temp_dir.path().join("plan.json");
+        plan.save(&path).unwrap();
+
+        let loaded = PlanFile::load(&path).unwrap();
+        assert_eq!(loaded, plan);
+        assert_eq!(loaded.problems(), vec![format!("'{}' does not exist", book.join("2.mp3").display())]);
+
+        let mut trimmed = loaded;
+        trimmed.groups[0].trims.insert(book.join("1.mp3"), TrimOverride { start_secs: Some(1e300), end_secs: None });
+        assert_eq!(trimmed.problems().len(), 2);
+        assert!(trimmed.problems()[1].starts_with(&format!("trim for '{}': start_secs", book.join("1.mp3").display())));
+
+        fs::write(&path, fs::read_to_string(&path).unwrap().replace("\"version\": 1", "\"version\": 9")).unwrap();
+        assert_eq!(PlanFile::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,426 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::args::{GroupBy, OnCollision};
+use crate::book_meta::BookMeta;
+use crate::scanner::compare_natural;
+use crate::tags::{self, TrackTags};
+use crate::trim::{Trim, TrimOverride};
+
+/// One folder's worth of work: the ordered inputs and where they will be written.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlannedGroup {
+    pub source_dir: PathBuf,
+    pub output: PathBuf,
+    pub files: Vec<PathBuf>,
+    /// Per-file trims, keyed by path, added by hand to a plan file
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trims: BTreeMap<PathBuf, TrimOverride>,
+    /// Author/title/year parsed from the folder name, written to the merged file's tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<BookMeta>,
+}
+
+impl PlannedGroup {
+    /// The file's trim: its override where there is one, otherwise the default.
+    pub fn trim_for(&self, file: &Path, default: Trim) -> Trim {
+        self.trims.get(file).map(|o| o.apply(default)).unwrap_or(default)
+    }
+}
+
+/// Turns scanned groups into a list of merges, sorted by source directory so that
+/// dry runs and real runs process folders in the same order.
+pub fn plan_groups(groups: &HashMap<PathBuf, Vec<PathBuf>>, source_root: &Path, output_dir: &Path) -> Vec<PlannedGroup> {
+    let mut dirs: Vec<_> = groups.keys().collect();
+    dirs.sort();
+
+    dirs.into_iter()
+        .map(|dir| PlannedGroup {
+            source_dir: dir.clone(),
+            output: output_path(dir, source_root, output_dir),
+            files: groups[dir].clone(),
+            trims: BTreeMap::new(),
+            meta: None,
+        })
+        .collect()
+}
+
+/// Like `plan_groups`, but disc folders (`CD1`, `Disc 2`, ...) are folded into their parent,
+/// which is merged as one book: the parent's own files first, then each disc in number order.
+pub fn plan_books(groups: &HashMap<PathBuf, Vec<PathBuf>>, source_root: &Path, output_dir: &Path) -> Vec<PlannedGroup> {
+    let mut books: HashMap<PathBuf, Vec<(Option<u32>, &PathBuf)>> = HashMap::new();
+    for dir in groups.keys() {
+        let disc = dir.file_name().and_then(|name| disc_number(&name.to_string_lossy()));
+        match (disc, dir.parent()) {
+            // Never climb above the source root
+            (Some(disc), Some(parent)) if dir != source_root => {
+                books.entry(parent.to_path_buf()).or_default().push((Some(disc), dir));
+            }
+            _ => books.entry(dir.clone()).or_default().push((None, dir)),
+        }
+    }
+
+    let mut book_dirs: Vec<_> = books.keys().cloned().collect();
+    book_dirs.sort();
+
+    book_dirs.into_iter()
+        .map(|book| {
+            let mut discs = books.remove(&book).unwrap_or_default();
+            discs.sort_by(|(a_disc, a_dir), (b_disc, b_dir)| {
+                a_disc.cmp(b_disc).then_with(|| compare_natural(
+                    &a_dir.file_name().unwrap_or_default().to_string_lossy(),
+                    &b_dir.file_name().unwrap_or_default().to_string_lossy(),
+                ))
+            });
+            PlannedGroup {
+                output: output_path(&book, source_root, output_dir),
+                files: discs.iter().flat_map(|(_, dir)| groups[*dir].iter().cloned()).collect(),
+                source_dir: book,
+                trims: BTreeMap::new(),
+                meta: None,
+            }
+        })
+        .collect()
+}
+
+/// Recognizes disc folder names such as `CD1`, `cd 02`, `Disc 3 - Title`, `Disk_4` or
+/// `Part 5` and returns the number.
+pub fn disc_number(name: &str) -> Option<u32> {
+    let lower = name.to_lowercase();
+    let rest = ["disc", "disk", "cd", "part", "pt"].iter().find_map(|prefix| lower.strip_prefix(prefix))?;
+    let rest = rest.trim_start_matches([' ', '_', '-', '.']);
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    // "CD1a" or "Disc 2nd" are not plain disc numbers
+    if rest[digits..].starts_with(char::is_alphanumeric) {
+        return None;
+    }
+    rest[..digits].parse().ok()
+}
+
+/// Optional artist directory and album name.
+type AlbumKey = (Option<String>, String);
+
+/// Plans merges according to the grouping mode. Tag-based modes gather files that share
+/// an album across folders; files without an album tag fall back to folder grouping.
+pub fn plan(
+    groups: &HashMap<PathBuf, Vec<PathBuf>>,
+    source_root: &Path,
+    output_dir: &Path,
+    group_by: &GroupBy,
+) -> Vec<PlannedGroup> {
+    match group_by {
+        GroupBy::Folder => return plan_groups(groups, source_root, output_dir),
+        GroupBy::Book => return plan_books(groups, source_root, output_dir),
+        GroupBy::Album | GroupBy::AlbumArtist => {}
+    }
+
+    // BTreeMap so albums are planned in a stable order
+    let mut albums: BTreeMap<AlbumKey, Vec<(PathBuf, TrackTags)>> = BTreeMap::new();
+    let mut untagged: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+
+    for (dir, files) in groups {
+        for file in files {
+            let tags = tags::read_tags(file);
+            let Some(album) = tags.album.clone() else {
+                untagged.entry(dir.clone()).or_default().push(file.clone());
+                continue;
+            };
+            let artist = match group_by {
+                GroupBy::AlbumArtist => Some(
+                    tags.album_artist.clone()
+                        .or_else(|| tags.artist.clone())
+                        .unwrap_or_else(|| "Unknown Artist".to_string()),
+                ),
+                _ => None,
+            };
+            albums.entry((artist, album)).or_default().push((file.clone(), tags));
+        }
+    }
+
+    let mut plan = plan_groups(&untagged, source_root, output_dir);
+    for ((artist, album), mut tracks) in albums {
+        tracks.sort_by(|(a_path, a), (b_path, b)| {
+            (a.disc.unwrap_or(0), a.track.unwrap_or(0))
+                .cmp(&(b.disc.unwrap_or(0), b.track.unwrap_or(0)))
+                .then_with(|| compare_natural(
+                    &a_path.file_name().unwrap_or_default().to_string_lossy(),
+                    &b_path.file_name().unwrap_or_default().to_string_lossy(),
+                ))
+        });
+        let files: Vec<PathBuf> = tracks.into_iter().map(|(path, _)| path).collect();
+
+        let mut output = output_dir.to_path_buf();
+        if let Some(artist) = artist {
+            output.push(tags::sanitize_component(&artist));
+        }
+        output.push(format!("{}.mp3", tags::sanitize_component(&album)));
+
+        plan.push(PlannedGroup {
+            source_dir: common_ancestor(&files),
+            output,
+            files,
+            trims: BTreeMap::new(),
+            meta: None,
+        });
+    }
+
+    plan
+}
+
+/// A group whose output path was already claimed by an earlier group in the plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    pub output: PathBuf,
+    pub source_dir: PathBuf,
+    pub claimed_by: PathBuf,
+    /// Where the group is written instead, with `--on-collision suffix`
+    pub renamed_to: Option<PathBuf>,
+}
+
+/// Finds groups that would overwrite an earlier group's output and applies the strategy:
+/// `suffix` renames them, `skip` drops them and `error` leaves the plan untouched so the
+/// caller can refuse to run.
+pub fn resolve_collisions(plan: Vec<PlannedGroup>, on_collision: &OnCollision) -> (Vec<PlannedGroup>, Vec<Collision>) {
+    // Every original output is reserved so a suffixed name never takes a later group's path
+    let mut taken: HashSet<PathBuf> = plan.iter().map(|g| g.output.clone()).collect();
+    let mut claimed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut resolved = Vec::with_capacity(plan.len());
+    let mut collisions = Vec::new();
+
+    for mut group in plan {
+        let Some(claimed_by) = claimed.get(&group.output) else {
+            claimed.insert(group.output.clone(), group.source_dir.clone());
+            resolved.push(group);
+            continue;
+        };
+        let mut collision = Collision {
+            output: group.output.clone(),
+            source_dir: group.source_dir.clone(),
+            claimed_by: claimed_by.clone(),
+            renamed_to: None,
+        };
+        match on_collision {
+            OnCollision::Suffix => {
+                let renamed = (2..)
+                    .map(|n| suffixed(&group.output, n))
+                    .find(|candidate| !taken.contains(candidate))
+                    .expect("unbounded suffix search");
+                taken.insert(renamed.clone());
+                claimed.insert(renamed.clone(), group.source_dir.clone());
+                collision.renamed_to = Some(renamed.clone());
+                group.output = renamed;
+                resolved.push(group);
+            }
+            OnCollision::Skip => {}
+            OnCollision::Error => resolved.push(group),
+        }
+        collisions.push(collision);
+    }
+
+    (resolved, collisions)
+}
+
+/// `book.mp3` -> `book (2).mp3`
+fn suffixed(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{} ({}).{}", stem, n, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{} ({})", stem, n)),
+    }
+}
+
+/// Deepest directory containing every file.
+fn common_ancestor(files: &[PathBuf]) -> PathBuf {
+    let mut ancestor = files.first()
+        .and_then(|f| f.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    for file in files {
+        while !file.starts_with(&ancestor) {
+            if !ancestor.pop() {
+                break;
+            }
+        }
+    }
+    ancestor
+}
+
+/// Calculates the output path relative to the source to preserve structure and avoid collisions.
+pub fn output_path(dir: &Path, source_root: &Path, output_dir: &Path) -> PathBuf {
+    let relative_path = dir.strip_prefix(source_root).unwrap_or(dir);
+    if relative_path.as_os_str().is_empty() {
+        let dir_name = dir.file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_else(|| std::borrow::Cow::from("merged"));
+        output_dir.join(format!("{}.mp3", dir_name))
+    } else {
+        output_dir.join(relative_path).with_extension("mp3")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path() {
+        let root = Path::new("/library/books");
+        let out = Path::new("out");
+        assert_eq!(output_path(Path::new("/library/books/a/b"), root, out), PathBuf::from("out/a/b.mp3"));
+        assert_eq!(output_path(root, root, out), PathBuf::from("out/books.mp3"));
+    }
+
+    #[test]
+    fn test_disc_number() {
+        assert_eq!(disc_number("CD1"), Some(1));
+        assert_eq!(disc_number("cd 02"), Some(2));
+        assert_eq!(disc_number("Disc 3 - The Return"), Some(3));
+        assert_eq!(disc_number("Disk_4"), Some(4));
+        assert_eq!(disc_number("Part.5"), Some(5));
+        assert_eq!(disc_number("Discworld 3"), None);
+        assert_eq!(disc_number("CD1a"), None);
+        assert_eq!(disc_number("Partition"), None);
+        assert_eq!(disc_number("Book"), None);
+    }
+
+    #[test]
+    fn test_plan_books() {
+        let root = PathBuf::from("src");
+        let book = root.join("Book");
+        let mut groups = HashMap::new();
+        groups.insert(book.clone(), vec![book.join("intro.mp3")]);
+        groups.insert(book.join("CD10"), vec![book.join("CD10/1.mp3")]);
+        groups.insert(book.join("CD2"), vec![book.join("CD2/1.mp3"), book.join("CD2/2.mp3")]);
+        groups.insert(book.join("Disc 1"), vec![book.join("Disc 1/1.mp3")]);
+        groups.insert(book.join("Extras"), vec![book.join("Extras/1.mp3")]);
+
+        let plan = plan_books(&groups, &root, Path::new("out"));
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].source_dir, book);
+        assert_eq!(plan[0].output, PathBuf::from("out/Book.mp3"));
+        assert_eq!(plan[0].files, vec![
+            book.join("intro.mp3"),
+            book.join("Disc 1/1.mp3"),
+            book.join("CD2/1.mp3"),
+            book.join("CD2/2.mp3"),
+            book.join("CD10/1.mp3"),
+        ]);
+        assert_eq!(plan[1].source_dir, book.join("Extras"));
+
+        // A disc folder used as the source root stays on its own
+        let disc_root = root.join("CD1");
+        let mut groups = HashMap::new();
+        groups.insert(disc_root.clone(), vec![disc_root.join("1.mp3")]);
+        let plan = plan_books(&groups, &disc_root, Path::new("out"));
+        assert_eq!(plan[0].output, PathBuf::from("out/CD1.mp3"));
+    }
+
+    #[test]
+    fn test_resolve_collisions() {
+        let root = PathBuf::from("src");
+        let group = |dir: &str, output: &str| PlannedGroup {
+            source_dir: root.join(dir),
+            output: PathBuf::from(output),
+            files: vec![root.join(dir).join("1.mp3")],
+            trims: BTreeMap::new(),
+            meta: None,
+        };
+        // "Book.1" and "Book.2" both become Book.mp3; "Book (2)" already owns the first suffix
+        let plan = vec![
+            group("Book (2)", "out/Book (2).mp3"),
+            group("Book.1", "out/Book.mp3"),
+            group("Book.2", "out/Book.mp3"),
+            group("Other", "out/Other.mp3"),
+        ];
+
+        let (suffixed, collisions) = resolve_collisions(plan.clone(), &OnCollision::Suffix);
+        let outputs: Vec<_> = suffixed.iter().map(|g| g.output.clone()).collect();
+        assert_eq!(outputs, vec![
+            PathBuf::from("out/Book (2).mp3"),
+            PathBuf::from("out/Book.mp3"),
+            PathBuf::from("out/Book (3).mp3"),
+            PathBuf::from("out/Other.mp3"),
+        ]);
+        assert_eq!(collisions, vec![Collision {
+            output: PathBuf::from("out/Book.mp3"),
+            source_dir: root.join("Book.2"),
+            claimed_by: root.join("Book.1"),
+            renamed_to: Some(PathBuf::from("out/Book (3).mp3")),
+        }]);
+
+        let (skipped, collisions) = resolve_collisions(plan.clone(), &OnCollision::Skip);
+        assert_eq!(skipped.len(), 3);
+        assert!(skipped.iter().all(|g| g.source_dir != root.join("Book.2")));
+        assert_eq!(collisions.len(), 1);
+
+        let (unchanged, collisions) = resolve_collisions(plan.clone(), &OnCollision::Error);
+        assert_eq!(unchanged, plan);
+        assert_eq!(collisions[0].renamed_to, None);
+    }
+
+    #[test]
+    fn test_plan_by_album() {
+        use crate::mp3;
+        use id3::{Tag, TagLike, Version};
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let cd1 = root.join("book/cd1");
+        let cd2 = root.join("book/cd2");
+        let loose = root.join("loose");
+        for dir in [&cd1, &cd2, &loose] {
+            fs::create_dir_all(dir).unwrap();
+        }
+
+        let write = |path: &Path, album: Option<&str>, disc: u32, track: u32| {
+            fs::write(path, mp3::synth_frames(1)).unwrap();
+            if let Some(album) = album {
+                let mut tag = Tag::new();
+                tag.set_album(album);
+                tag.set_album_artist("Some/Author");
+                tag.set_disc(disc);
+                tag.set_track(track);
+                tag.write_to_path(path, Version::Id3v24).unwrap();
+            }
+        };
+        write(&cd2.join("01.mp3"), Some("Book"), 2, 1);
+        write(&cd1.join("02.mp3"), Some("Book"), 1, 2);
+        write(&cd1.join("01.mp3"), Some("Book"), 1, 1);
+        write(&loose.join("x.mp3"), None, 0, 0);
+
+        let mut groups: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        groups.insert(cd1.clone(), vec![cd1.join("01.mp3"), cd1.join("02.mp3")]);
+        groups.insert(cd2.clone(), vec![cd2.join("01.mp3")]);
+        groups.insert(loose.clone(), vec![loose.join("x.mp3")]);
+
+        let out = Path::new("out");
+        let plan_album = plan(&groups, root, out, &GroupBy::Album);
+        assert_eq!(plan_album.len(), 2);
+        assert_eq!(plan_album[0].output, PathBuf::from("out/loose.mp3"));
+        let book = &plan_album[1];
+        assert_eq!(book.output, PathBuf::from("out/Book.mp3"));
+        assert_eq!(book.source_dir, root.join("book"));
+        assert_eq!(book.files, vec![cd1.join("01.mp3"), cd1.join("02.mp3"), cd2.join("01.mp3")]);
+
+        let plan_artist = plan(&groups, root, out, &GroupBy::AlbumArtist);
+        assert_eq!(plan_artist[1].output, PathBuf::from("out/Some_Author/Book.mp3"));
+    }
+
+    #[test]
+    fn test_plan_groups_sorted() {
+        let root = PathBuf::from("src");
+        let mut groups = HashMap::new();
+        groups.insert(root.join("b"), vec![root.join("b/1.mp3")]);
+        groups.insert(root.join("a"), vec![root.join("a/1.mp3"), root.join("a/2.mp3")]);
+
+        let plan = plan_groups(&groups, &root, Path::new("out"));
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].source_dir, root.join("a"));
+        assert_eq!(plan[0].output, PathBuf::from("out/a.mp3"));
+        assert_eq!(plan[0].files.len(), 2);
+        assert_eq!(plan[1].source_dir, root.join("b"));
+    }
+}
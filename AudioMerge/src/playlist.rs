@@ -0,0 +1,84 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::args::PlaylistFormat;
+use crate::mp3;
+use crate::planner::PlannedGroup;
+use crate::tags;
+
+/// The playlist takes the place of the merged file: `out/Book.mp3` becomes `out/Book.m3u`.
+pub fn playlist_path(output: &Path, format: &PlaylistFormat) -> PathBuf {
+    output.with_extension(match format {
+        PlaylistFormat::M3u => "m3u",
+        PlaylistFormat::M3u8 => "m3u8",
+    })
+}
+
+/// Extended M3U listing the group's files, in merge order, relative to the playlist's folder.
+/// Files whose length cannot be read get the conventional `-1` duration.
+pub fn format_playlist(group: &PlannedGroup, playlist: &Path) -> String {
+    let base = std::path::absolute(playlist.parent().unwrap_or(Path::new(".")))
+        .unwrap_or_default();
+    let mut out = String::from("#EXTM3U\n");
+    for file in &group.files {
+        let seconds = mp3::scan(file).map(|info| info.duration.as_secs() as i64).unwrap_or(-1);
+        let absolute = std::path::absolute(file).unwrap_or_else(|_| file.clone());
+        let entry = pathdiff::diff_paths(&absolute, &base).unwrap_or(absolute);
+        let _ = writeln!(out, "#EXTINF:{},{}", seconds, tags::display_title(file));
+        let _ = writeln!(out, "{}", entry.display());
+    }
+    out
+}
+
+/// Writes the group's playlist and returns its path.
+pub fn write_playlist(group: &PlannedGroup, format: &PlaylistFormat) -> io::Result<PathBuf> {
+    let path = playlist_path(&group.output, format);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, format_playlist(group, &path))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_playlist_path() {
+        assert_eq!(playlist_path(Path::new("out/a/Book.mp3"), &PlaylistFormat::M3u), PathBuf::from("out/a/Book.m3u"));
+        assert_eq!(playlist_path(Path::new("out/Book.mp3"), &PlaylistFormat::M3u8), PathBuf::from("out/Book.m3u8"));
+    }
+
+    #[test]
+    fn test_write_playlist() {
+        let temp_dir = TempDir::new().unwrap();
+        let book = temp_dir.path().join("src/Book");
+        fs::create_dir_all(&book).unwrap();
+        // 39 frames of 1152 samples at 44.1 kHz is just over one second
+        fs::write(book.join("01 Intro.mp3"), mp3::synth_frames(39)).unwrap();
+        fs::write(book.join("02 Outro.mp3"), mp3::synth_frames(1)).unwrap();
+
+        let group = PlannedGroup {
+            source_dir: book.clone(),
+            output: temp_dir.path().join("out/Book.mp3"),
+            files: vec![book.join("01 Intro.mp3"), book.join("02 Outro.mp3")],
+            trims: Default::default(),
+            meta: None,
+        };
+        let path = write_playlist(&group, &PlaylistFormat::M3u8).unwrap();
+
+        assert_eq!(path, temp_dir.path().join("out/Book.m3u8"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!(
+                "#EXTM3U\n#EXTINF:1,01 Intro\n{}\n#EXTINF:0,02 Outro\n{}\n",
+                Path::new("../src/Book/01 Intro.mp3").display(),
+                Path::new("../src/Book/02 Outro.mp3").display()
+            )
+        );
+    }
+}
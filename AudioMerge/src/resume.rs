@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// How often, in output bytes, progress is recorded within a single input.
+pub const CHECKPOINT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Identifies an input well enough to notice it changed between runs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InputStamp {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub modified: SystemTime,
+}
+
+impl InputStamp {
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(InputStamp { path: path.to_path_buf(), bytes: metadata.len(), modified: metadata.modified()? })
+    }
+}
+
+/// How far an interrupted concatenation got: everything before byte `position` of input
+/// number `input` is in the first `written` bytes of the `.partial` file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Progress {
+    pub inputs: Vec<InputStamp>,
+    pub ranges: Vec<Vec<Range<u64>>>,
+    pub input: usize,
+    pub position: u64,
+    pub written: u64,
+}
+
+/// The file a merge writes to until it completes: `<output>.partial`.
+pub fn partial_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// Where the progress of the `.partial` file is recorded: `<output>.partial.json`.
+pub fn progress_path(output: &Path) -> PathBuf {
+    let mut name = partial_path(output).into_os_string();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+impl Progress {
+    pub fn start(files: &[PathBuf], ranges: &[Vec<Range<u64>>]) -> io::Result<Self> {
+        Ok(Progress {
+            inputs: files.iter().map(|path| InputStamp::of(path)).collect::<io::Result<_>>()?,
+            ranges: ranges.to_vec(),
+            input: 0,
+            position: 0,
+            written: 0,
+        })
+    }
+
+    /// The recorded progress for this output, if it describes the same inputs (unchanged
+    /// since) and the `.partial` file still holds everything it claims was written.
+    pub fn load(output: &Path, fresh: &Progress) -> Option<Self> {
+        let data = fs::read_to_string(progress_path(output)).ok()?;
+        let progress: Progress = serde_json::from_str(&data).ok()?;
+        let partial_len = fs::metadata(partial_path(output)).ok()?.len();
+        let usable = progress.inputs == fresh.inputs
+            && progress.ranges == fresh.ranges
+            && progress.input <= progress.inputs.len()
+            && partial_len >= progress.written;
+        if !usable {
+            log::debug!("Discarding stale progress for '{}'", output.display());
+        }
+        usable.then_some(progress)
+    }
+
+    pub fn save(&self, output: &Path) -> io::Result<()> {
+        let data = serde_json::to_string(self).map_err(io::Error::other)?;
+        // Written beside and renamed over, so an interruption never leaves half a record
+        let path = progress_path(output);
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        fs::write(&temp, data)?;
+        fs::rename(&temp, &path)
+    }
+
+    pub fn clear(output: &Path) -> io::Result<()> {
+        match fs::remove_file(progress_path(output)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::WHOLE_FILE;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_paths() {
+        let output = Path::new("out/Book.mp3");
+        assert_eq!(partial_path(output), PathBuf::from("out/Book.mp3.partial"));
+        assert_eq!(progress_path(output), PathBuf::from("out/Book.mp3.partial.json"));
+    }
+
+    #[test]
+    fn test_progress_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("1.mp3");
+        let output = temp_dir.path().join("Book.mp3");
+        fs::write(&input, b"abcdef").unwrap();
+        let files = vec![input.clone()];
+        let ranges = vec![vec![WHOLE_FILE]];
+
+        let fresh = Progress::start(&files, &ranges).unwrap();
+        assert_eq!(Progress::load(&output, &fresh), None);
+
+        let progress = Progress { position: 4, written: 4, ..fresh.clone() };
+        progress.save(&output).unwrap();
+        // No .partial file to resume
+        assert_eq!(Progress::load(&output, &fresh), None);
+
+        fs::write(partial_path(&output), b"abcd").unwrap();
+        assert_eq!(Progress::load(&output, &fresh), Some(progress.clone()));
+
+        let other_ranges = Progress::start(&files, &[vec![0..2, 3..u64::MAX]]).unwrap();
+        assert_eq!(Progress::load(&output, &other_ranges), None);
+
+        fs::write(&input, b"abcdefg").unwrap();
+        assert_eq!(Progress::load(&output, &Progress::start(&files, &ranges).unwrap()), None);
+
+        Progress::clear(&output).unwrap();
+        Progress::clear(&output).unwrap();
+        assert!(!progress_path(&output).exists());
+    }
+}
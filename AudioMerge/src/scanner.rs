@@ -1,15 +1,31 @@
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use walkdir::WalkDir;
 
-pub fn scan_audio_files(root: &Path) -> HashMap<PathBuf, Vec<PathBuf>> {
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanOptions {
+    /// Levels of subdirectories to descend below the root; `Some(0)` scans only the root itself.
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+}
+
+pub fn scan_audio_files(root: &Path, options: &ScanOptions) -> HashMap<PathBuf, Vec<PathBuf>> {
     let mut groups: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    // Symlinked mirrors reach the same file by several paths; only the first one counts
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+
+    let mut walker = WalkDir::new(root)
+        .follow_links(options.follow_symlinks)
+        .sort_by_file_name();
+    if let Some(depth) = options.max_depth {
+        walker = walker.max_depth(depth + 1);
+    }
 
-    for result in WalkDir::new(root) {
+    for result in walker {
         let entry = match result {
             Ok(e) => e,
             Err(err) => {
-                eprintln!("Error accessing file: {}", err);
+                log::warn!("Skipping unreadable path: {}", err);
                 // TODO: Integrate 'on_error' strategy logic here (Halt/Skip/Prompt)
                 continue;
             }
@@ -17,14 +33,19 @@ pub fn scan_audio_files(root: &Path) -> HashMap<PathBuf, Vec<PathBuf>> {
 
         if entry.file_type().is_file() {
             // For MVP we assume mp3, but let's check extension just in case
-            if let Some(ext) = entry.path().extension() {
-                if ext.to_string_lossy().to_lowercase() == "mp3" {
-                    if let Some(parent) = entry.path().parent() {
-                        groups.entry(parent.to_path_buf())
-                            .or_default()
-                            .push(entry.path().to_path_buf());
+            if is_mp3(entry.path())
+                && let Some(parent) = entry.path().parent()
+            {
+                if options.follow_symlinks {
+                    let canonical = entry.path().canonicalize().unwrap_or_else(|_| entry.path().to_path_buf());
+                    if !seen.insert(canonical) {
+                        continue;
                     }
                 }
+
+                groups.entry(parent.to_path_buf())
+                    .or_default()
+                    .push(entry.path().to_path_buf());
             }
         }
     }
@@ -41,7 +62,12 @@ pub fn scan_audio_files(root: &Path) -> HashMap<PathBuf, Vec<PathBuf>> {
     groups
 }
 
-fn compare_natural(s1: &str, s2: &str) -> std::cmp::Ordering {
+pub fn is_mp3(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.to_string_lossy().to_lowercase() == "mp3")
+}
+
+pub fn compare_natural(s1: &str, s2: &str) -> std::cmp::Ordering {
     let chunks1 = get_chunks(s1);
     let chunks2 = get_chunks(s2);
 
@@ -114,9 +140,7 @@ fn get_chunks(s: &str) -> Vec<Chunk> {
     let mut current_digits = String::new();
     let mut current_text = String::new();
 
-    let mut chars = s.chars().peekable();
-
-    while let Some(c) = chars.next() {
+    for c in s.chars() {
         if c.is_ascii_digit() {
             if !current_text.is_empty() {
                 chunks.push(Chunk::Text(current_text.clone()));
@@ -178,7 +202,7 @@ mod tests {
         File::create(sub_dir.join("audio3.mp3")).unwrap();
         File::create(root.join("other.txt")).unwrap(); // Should be ignored
 
-        let groups = scan_audio_files(root);
+        let groups = scan_audio_files(root, &ScanOptions::default());
 
         assert_eq!(groups.len(), 2);
         
@@ -194,6 +218,41 @@ mod tests {
         assert!(sub_files.iter().any(|p| p.ends_with("audio3.mp3")));
     }
 
+    #[test]
+    fn test_scan_audio_files_max_depth() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        File::create(root.join("0.mp3")).unwrap();
+        File::create(root.join("a/1.mp3")).unwrap();
+        File::create(root.join("a/b/2.mp3")).unwrap();
+
+        let depth = |max_depth| scan_audio_files(root, &ScanOptions { max_depth, ..Default::default() }).len();
+        assert_eq!(depth(Some(0)), 1);
+        assert_eq!(depth(Some(1)), 2);
+        assert_eq!(depth(None), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_audio_files_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("book")).unwrap();
+        File::create(root.join("book/1.mp3")).unwrap();
+        std::os::unix::fs::symlink(root.join("book"), root.join("mirror")).unwrap();
+
+        // Not followed: the mirror is ignored
+        let groups = scan_audio_files(root, &ScanOptions::default());
+        assert_eq!(groups.len(), 1);
+
+        // Followed: the mirror is entered but the same file is not counted twice
+        let options = ScanOptions { follow_symlinks: true, ..Default::default() };
+        let groups = scan_audio_files(root, &options);
+        let total: usize = groups.values().map(Vec::len).sum();
+        assert_eq!(total, 1);
+    }
+
     #[test]
     fn test_scan_audio_files_sorting() {
         let temp_dir = TempDir::new().unwrap();
@@ -210,7 +269,7 @@ mod tests {
         File::create(root.join("file 3.mp3")).unwrap();
         File::create(root.join("file 04.mp3")).unwrap();
 
-        let groups = scan_audio_files(root);
+        let groups = scan_audio_files(root, &ScanOptions::default());
         let files = groups.get(root).unwrap();
 
         assert_eq!(files.len(), 7);
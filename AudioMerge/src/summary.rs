@@ -0,0 +1,119 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::pipeline::GroupOutcome;
+
+/// Every group was merged or deliberately skipped.
+pub const EXIT_OK: i32 = 0;
+/// Nothing could be done: bad arguments, unreadable source or plan, refused collisions.
+pub const EXIT_FATAL: i32 = 1;
+/// The run finished (or was halted) with at least one group failed.
+pub const EXIT_PARTIAL: i32 = 2;
+
+/// Totals for a run, printed at the end and turned into the exit status.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunSummary {
+    pub merged: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// Groups never started because the run was halted
+    pub not_attempted: usize,
+    /// Size and playing time of the merged outputs
+    pub bytes: u64,
+    pub duration: Duration,
+}
+
+impl RunSummary {
+    pub fn record(&mut self, outcome: GroupOutcome) {
+        match outcome {
+            GroupOutcome::Merged => self.merged += 1,
+            GroupOutcome::Skipped => self.skipped += 1,
+            GroupOutcome::Failed => self.failed += 1,
+        }
+    }
+
+    /// Folds another root's totals into this one.
+    pub fn add(&mut self, other: &RunSummary) {
+        self.merged += other.merged;
+        self.skipped += other.skipped;
+        self.failed += other.failed;
+        self.not_attempted += other.not_attempted;
+        self.bytes += other.bytes;
+        self.duration += other.duration;
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.failed > 0 || self.not_attempted > 0 {
+            EXIT_PARTIAL
+        } else {
+            EXIT_OK
+        }
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Summary: {} merged, {} skipped, {} failed", self.merged, self.skipped, self.failed)?;
+        if self.not_attempted > 0 {
+            write!(f, ", {} not attempted", self.not_attempted)?;
+        }
+        write!(f, "\nOutput: {}, {}", format_bytes(self.bytes), format_duration(self.duration))
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+/// `h:mm:ss`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_summary() {
+        let mut summary = RunSummary::default();
+        summary.record(GroupOutcome::Merged);
+        summary.record(GroupOutcome::Skipped);
+        assert_eq!(summary.exit_code(), EXIT_OK);
+
+        summary.record(GroupOutcome::Failed);
+        summary.bytes = 3 * 1024 * 1024 / 2;
+        summary.duration = Duration::from_secs(3 * 3600 + 5 * 60 + 9);
+        assert_eq!(summary.exit_code(), EXIT_PARTIAL);
+        assert_eq!(summary.to_string(), "Summary: 1 merged, 1 skipped, 1 failed\nOutput: 1.5 MiB, 3:05:09");
+
+        let halted = RunSummary { not_attempted: 2, ..RunSummary::default() };
+        assert_eq!(halted.exit_code(), EXIT_PARTIAL);
+        assert!(halted.to_string().contains("0 failed, 2 not attempted"));
+
+        summary.add(&halted);
+        assert_eq!((summary.merged, summary.failed, summary.not_attempted), (1, 1, 2));
+        assert_eq!(summary.bytes, 3 * 1024 * 1024 / 2);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2.0 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+}
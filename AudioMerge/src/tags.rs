@@ -0,0 +1,96 @@
+use id3::{Tag, TagLike};
+use std::path::Path;
+
+/// The subset of ID3 metadata used for grouping and ordering.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackTags {
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub artist: Option<String>,
+    pub disc: Option<u32>,
+    pub track: Option<u32>,
+}
+
+/// Reads the ID3 tag of a file. Files without a readable tag yield empty metadata.
+pub fn read_tags(path: &Path) -> TrackTags {
+    let Ok(tag) = Tag::read_from_path(path) else {
+        return TrackTags::default();
+    };
+    let text = |value: Option<&str>| value.map(str::trim).filter(|v| !v.is_empty()).map(String::from);
+
+    TrackTags {
+        title: text(tag.title()),
+        album: text(tag.album()),
+        album_artist: text(tag.album_artist()),
+        artist: text(tag.artist()),
+        disc: tag.disc(),
+        track: tag.track(),
+    }
+}
+
+/// The track's ID3 title, falling back to the file name without its extension.
+pub fn display_title(path: &Path) -> String {
+    read_tags(path).title.unwrap_or_else(|| {
+        path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+    })
+}
+
+/// Makes a tag value safe to use as a single path component.
+pub fn sanitize_component(value: &str) -> String {
+    let cleaned: String = value.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = cleaned.trim().trim_matches('.').trim();
+    if trimmed.is_empty() {
+        "Unknown".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp3;
+    use id3::Version;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("1.mp3");
+        fs::write(&path, mp3::synth_frames(2)).unwrap();
+
+        assert_eq!(read_tags(&path), TrackTags::default());
+
+        let mut tag = Tag::new();
+        tag.set_title("Chapter One");
+        tag.set_album("The Book");
+        tag.set_album_artist("  ");
+        tag.set_artist("Author");
+        tag.set_disc(2);
+        tag.set_track(7);
+        tag.write_to_path(&path, Version::Id3v24).unwrap();
+
+        let tags = read_tags(&path);
+        assert_eq!(tags.title.as_deref(), Some("Chapter One"));
+        assert_eq!(tags.album.as_deref(), Some("The Book"));
+        assert_eq!(tags.album_artist, None);
+        assert_eq!(tags.artist.as_deref(), Some("Author"));
+        assert_eq!(tags.disc, Some(2));
+        assert_eq!(tags.track, Some(7));
+    }
+
+    #[test]
+    fn test_sanitize_component() {
+        assert_eq!(sanitize_component("AC/DC: Live?"), "AC_DC_ Live_");
+        assert_eq!(sanitize_component("  ..  "), "Unknown");
+        assert_eq!(sanitize_component("..hidden"), "hidden");
+    }
+}
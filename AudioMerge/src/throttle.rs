@@ -0,0 +1,62 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Limits reads to an average rate, allowing bursts of up to one second's worth (or one
+/// buffer, if that is larger) after a pause.
+#[derive(Debug)]
+pub struct TokenBucket {
+    bytes_per_sec: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(bytes_per_sec: u64, buffer_size: usize) -> Self {
+        let capacity = (bytes_per_sec as f64).max(buffer_size as f64);
+        TokenBucket { bytes_per_sec: bytes_per_sec as f64, capacity, tokens: capacity, last: Instant::now() }
+    }
+
+    /// Takes `bytes` tokens, returning how long to wait before they may be used.
+    fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.capacity) - bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_sec)
+        }
+    }
+
+    /// Blocks until `bytes` more may be read without exceeding the rate.
+    pub fn take(&mut self, bytes: usize) {
+        let wait = self.reserve(bytes, Instant::now());
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(1000, 100);
+        let start = bucket.last;
+
+        // The first second's worth goes through at once
+        assert_eq!(bucket.reserve(1000, start), Duration::ZERO);
+        // Then reads wait for the tokens they need
+        assert_eq!(bucket.reserve(500, start), Duration::from_millis(500));
+        let later = start + Duration::from_secs(1);
+        assert_eq!(bucket.reserve(750, later), Duration::from_millis(250));
+
+        // Idle time never banks more than the capacity
+        let much_later = later + Duration::from_secs(60);
+        assert_eq!(bucket.reserve(1000, much_later), Duration::ZERO);
+        assert_eq!(bucket.reserve(100, much_later), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::merger;
+use crate::mp3::{self, StreamInfo};
+
+/// Allowance for rounding when adding up frame durations.
+const EPSILON: f64 = 1e-9;
+
+/// How much to cut from the start and end of an input (e.g. publisher jingles).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Trim {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl Trim {
+    pub fn is_zero(&self) -> bool {
+        self.start.is_zero() && self.end.is_zero()
+    }
+}
+
+/// A per-file trim in a plan file. Sides that are not set use `--trim-start`/`--trim-end`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrimOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_secs: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_secs: Option<f64>,
+}
+
+impl TrimOverride {
+    /// Sides that can't be a duration (see `problem`) fall back to the default.
+    pub fn apply(&self, default: Trim) -> Trim {
+        let secs = |value: Option<f64>, fallback| {
+            value.and_then(|v| Duration::try_from_secs_f64(v).ok()).unwrap_or(fallback)
+        };
+        Trim {
+            start: secs(self.start_secs, default.start),
+            end: secs(self.end_secs, default.end),
+        }
+    }
+
+    /// Why a side can't be used, e.g. a negative or absurdly large number of seconds.
+    pub fn problem(&self) -> Option<String> {
+        [("start_secs", self.start_secs), ("end_secs", self.end_secs)]
+            .into_iter()
+            .find_map(|(name, value)| {
+                let value = value?;
+                Duration::try_from_secs_f64(value).err().map(|e| format!("{} {:?} is invalid: {}", name, value, e))
+            })
+    }
+}
+
+/// The part of an input that survives trimming.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrimmedInput {
+    /// Byte ranges of the file to copy, in order
+    pub ranges: Vec<Range<u64>>,
+    /// Kept audio, as offsets from the start of the file's audio
+    pub start: Duration,
+    pub end: Duration,
+    /// Playing time of the untrimmed file
+    pub full: Duration,
+}
+
+impl TrimmedInput {
+    /// The whole file, untouched.
+    pub fn whole(info: &StreamInfo) -> Self {
+        TrimmedInput { ranges: vec![merger::WHOLE_FILE], start: Duration::ZERO, end: info.duration, full: info.duration }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// Frame-accurate trim: whole frames starting before `trim.start`, or ending after
+/// `trim.end` before the end, are dropped. Anything in front of the first frame (the ID3
+/// tag) is kept, so the first input's metadata still leads the merged file.
+pub fn trim_input(path: &Path, trim: Trim) -> io::Result<TrimmedInput> {
+    let mut frames: Vec<(u64, u64, f64)> = Vec::new();
+    mp3::scan_frames(path, |offset, frame| frames.push((offset, frame.frame_len() as u64, frame.duration())))?;
+    let total: f64 = frames.iter().map(|(_, _, d)| d).sum();
+    let keep_from = trim.start.as_secs_f64();
+    let keep_until = total - trim.end.as_secs_f64();
+
+    let mut time = 0.0;
+    let mut kept: Option<(usize, f64, usize, f64)> = None;
+    for (i, (_, _, duration)) in frames.iter().enumerate() {
+        let (frame_start, frame_end) = (time, time + duration);
+        time = frame_end;
+        if frame_start + EPSILON >= keep_from && frame_end <= keep_until + EPSILON {
+            kept = Some(match kept {
+                None => (i, frame_start, i, frame_end),
+                Some((first, start, _, _)) => (first, start, i, frame_end),
+            });
+        }
+    }
+    let Some((first, start, last, end)) = kept else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "trimming would remove all of its audio"));
+    };
+
+    let mut ranges = Vec::new();
+    let lead = frames[0].0;
+    if first > 0 && lead > 0 {
+        ranges.push(0..lead);
+    }
+    let begin = if first == 0 { 0 } else { frames[first].0 };
+    ranges.push(begin..frames[last].0 + frames[last].1);
+
+    Ok(TrimmedInput {
+        ranges,
+        start: Duration::from_secs_f64(start),
+        end: Duration::from_secs_f64(end),
+        full: Duration::from_secs_f64(total),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const FRAME: f64 = 1152.0 / 44100.0;
+
+    #[test]
+    fn test_trim_input() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.mp3");
+        let tag = vec![b'I', b'D', b'3', 4, 0, 0, 0, 0, 0, 2, 1, 2];
+        let mut data = tag.clone();
+        data.extend(mp3::synth_frames(100));
+        fs::write(&path, data).unwrap();
+
+        // 3.5 frames from the start rounds up to 4 whole frames; 10 from the end
+        let trim = Trim {
+            start: Duration::from_secs_f64(3.5 * FRAME),
+            end: Duration::from_secs_f64(10.0 * FRAME),
+        };
+        let trimmed = trim_input(&path, trim).unwrap();
+        let frame_start = |n: u64| tag.len() as u64 + 417 * n;
+        assert_eq!(trimmed.ranges, vec![0..tag.len() as u64, frame_start(4)..frame_start(90)]);
+        assert!((trimmed.duration().as_secs_f64() - 86.0 * FRAME).abs() < 1e-6);
+        assert!((trimmed.full.as_secs_f64() - 100.0 * FRAME).abs() < 1e-6);
+
+        let end_only = trim_input(&path, Trim { start: Duration::ZERO, end: Duration::from_secs_f64(FRAME) }).unwrap();
+        assert_eq!(end_only.ranges, vec![0..frame_start(99)]);
+
+        let everything = Trim { start: Duration::from_secs(2), end: Duration::from_secs(1) };
+        assert_eq!(trim_input(&path, everything).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_trim_override() {
+        let default = Trim { start: Duration::from_secs(3), end: Duration::from_secs(5) };
+        let custom = TrimOverride { start_secs: None, end_secs: Some(12.5) };
+        assert_eq!(custom.apply(default), Trim { start: Duration::from_secs(3), end: Duration::from_millis(12500) });
+        assert_eq!(TrimOverride::default().apply(default), default);
+        assert_eq!(TrimOverride::default().problem(), None);
+
+        let huge = TrimOverride { start_secs: Some(1e300), end_secs: None };
+        assert_eq!(huge.apply(default), default);
+        assert!(huge.problem().unwrap().starts_with("start_secs 1e300 is invalid"));
+        let negative = TrimOverride { start_secs: None, end_secs: Some(-2.0) };
+        assert_eq!(negative.apply(default), default);
+        assert!(negative.problem().is_some());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::mp3::StreamInfo;
+
+/// Why an input is not fit to be merged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    TooSmall { bytes: u64, min: u64 },
+    /// Typically a failed download saved under an .mp3 name
+    Html,
+    NoAudio,
+    /// More than half of the file is not MPEG audio
+    MostlyUnrecognised { unrecognised: u64, bytes: u64 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::TooSmall { bytes, min } => write!(f, "only {} bytes (minimum is {})", bytes, min),
+            Problem::Html => write!(f, "looks like an HTML page, not audio"),
+            Problem::NoAudio => write!(f, "contains no MPEG audio frames"),
+            Problem::MostlyUnrecognised { unrecognised, bytes } => {
+                write!(f, "{} of {} bytes are not MPEG audio", unrecognised, bytes)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidInput {
+    pub path: PathBuf,
+    pub problem: Problem,
+}
+
+/// Checks one input against its scan results. Returns the first problem found.
+pub fn check_input(path: &Path, info: &StreamInfo, min_size: u64) -> io::Result<Option<Problem>> {
+    let bytes = path.metadata()?.len();
+    if bytes < min_size || bytes == 0 {
+        return Ok(Some(Problem::TooSmall { bytes, min: min_size }));
+    }
+    if looks_like_html(path)? {
+        return Ok(Some(Problem::Html));
+    }
+    if info.frames == 0 {
+        return Ok(Some(Problem::NoAudio));
+    }
+    if info.unrecognised_bytes * 2 > bytes {
+        return Ok(Some(Problem::MostlyUnrecognised { unrecognised: info.unrecognised_bytes, bytes }));
+    }
+    Ok(None)
+}
+
+/// Checks every input of a group, in order.
+pub fn find_invalid_inputs(files: &[PathBuf], infos: &[StreamInfo], min_size: u64) -> io::Result<Vec<InvalidInput>> {
+    let mut invalid = Vec::new();
+    for (path, info) in files.iter().zip(infos) {
+        if let Some(problem) = check_input(path, info, min_size)? {
+            invalid.push(InvalidInput { path: path.clone(), problem });
+        }
+    }
+    Ok(invalid)
+}
+
+fn looks_like_html(path: &Path) -> io::Result<bool> {
+    let mut start = [0u8; 512];
+    let mut file = File::open(path)?;
+    let mut len = 0;
+    while len < start.len() {
+        match file.read(&mut start[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    let text = String::from_utf8_lossy(&start[..len]).trim_start_matches('\u{feff}').trim_start().to_lowercase();
+    Ok(text.starts_with("<!doctype html") || text.starts_with("<html") || text.starts_with("<?xml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp3;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_invalid_inputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let write = |name: &str, data: &[u8]| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, data).unwrap();
+            path
+        };
+        let mut noisy = vec![0x42; 417 * 3];
+        noisy.extend(mp3::synth_frames(2));
+
+        let files = vec![
+            write("good.mp3", &mp3::synth_frames(10)),
+            write("empty.mp3", b""),
+            write("tiny.mp3", &mp3::synth_frames(1)),
+            write("error.mp3", format!("\n<!DOCTYPE html><html>{}</html>", "x".repeat(2000)).as_bytes()),
+            write("text.mp3", "not audio at all ".repeat(100).as_bytes()),
+            write("noisy.mp3", &noisy),
+        ];
+        let infos: Vec<_> = files.iter().map(|f| mp3::scan(f).unwrap()).collect();
+
+        let invalid = find_invalid_inputs(&files, &infos, 1024).unwrap();
+        let problems: Vec<_> = invalid.iter().map(|i| (i.path.clone(), i.problem.clone())).collect();
+        assert_eq!(problems, vec![
+            (files[1].clone(), Problem::TooSmall { bytes: 0, min: 1024 }),
+            (files[2].clone(), Problem::TooSmall { bytes: 417, min: 1024 }),
+            (files[3].clone(), Problem::Html),
+            (files[4].clone(), Problem::NoAudio),
+            (files[5].clone(), Problem::MostlyUnrecognised { unrecognised: 417 * 3, bytes: 417 * 5 }),
+        ]);
+
+        // Size checks can be turned off, but empty files are never valid
+        let invalid = find_invalid_inputs(&files[..3], &infos[..3], 0).unwrap();
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].problem.to_string(), "only 0 bytes (minimum is 0)");
+    }
+}
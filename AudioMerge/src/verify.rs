@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use crate::mp3::StreamInfo;
+
+/// Expected (sum of inputs) versus actual (merged output) playing time.
+#[derive(Debug, PartialEq)]
+pub struct DurationCheck {
+    pub expected: Duration,
+    pub actual: Duration,
+}
+
+impl DurationCheck {
+    pub fn difference(&self) -> Duration {
+        self.expected.abs_diff(self.actual)
+    }
+
+    pub fn is_within(&self, tolerance: Duration) -> bool {
+        self.difference() <= tolerance
+    }
+}
+
+pub fn check_duration(inputs: &[StreamInfo], output: &StreamInfo) -> DurationCheck {
+    DurationCheck {
+        expected: inputs.iter().map(|info| info.duration).sum(),
+        actual: output.duration,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::{merge_files, CopyOptions};
+    use crate::mp3;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_duration() {
+        let temp_dir = TempDir::new().unwrap();
+        let file1 = temp_dir.path().join("1.mp3");
+        let file2 = temp_dir.path().join("2.mp3");
+        let output = temp_dir.path().join("out.mp3");
+        fs::write(&file1, mp3::synth_frames(20)).unwrap();
+        fs::write(&file2, mp3::synth_frames(30)).unwrap();
+
+        let inputs = vec![file1, file2];
+        let infos: Vec<_> = inputs.iter().map(|p| mp3::scan(p).unwrap()).collect();
+        merge_files(&inputs, &output, &CopyOptions::default()).unwrap();
+        let check = check_duration(&infos, &mp3::scan(&output).unwrap());
+        assert!(check.is_within(Duration::from_millis(1)));
+
+        // Simulate a truncated merge
+        fs::write(&output, mp3::synth_frames(20)).unwrap();
+        let check = check_duration(&infos, &mp3::scan(&output).unwrap());
+        assert!(!check.is_within(Duration::from_millis(500)));
+    }
+}
@@ -0,0 +1,93 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::scanner;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Directories with recent changes, released once they have been quiet for the settle time.
+pub struct PendingDirs {
+    settle: Duration,
+    last_change: HashMap<PathBuf, Instant>,
+}
+
+impl PendingDirs {
+    pub fn new(settle: Duration) -> Self {
+        PendingDirs { settle, last_change: HashMap::new() }
+    }
+
+    pub fn touch(&mut self, dir: PathBuf, now: Instant) {
+        self.last_change.insert(dir, now);
+    }
+
+    /// Removes and returns (sorted) every directory that has not changed for the settle time.
+    pub fn take_ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready: Vec<PathBuf> = self.last_change.iter()
+            .filter(|(_, changed)| now.duration_since(**changed) >= self.settle)
+            .map(|(dir, _)| dir.clone())
+            .collect();
+        for dir in &ready {
+            self.last_change.remove(dir);
+        }
+        ready.sort();
+        ready
+    }
+}
+
+/// Watches `source_root` until the watcher shuts down, calling `on_ready` with each folder
+/// whose MP3 files have stopped changing. Anything under `ignore` (the output directory)
+/// is not tracked, so our own writes never trigger a merge.
+pub fn watch(source_root: &Path, ignore: &Path, settle: Duration, mut on_ready: impl FnMut(&Path)) -> notify::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(source_root, RecursiveMode::Recursive)?;
+
+    let mut pending = PendingDirs::new(settle);
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        if scanner::is_mp3(&path) && !path.starts_with(ignore)
+                            && let Some(parent) = path.parent()
+                        {
+                            pending.touch(parent.to_path_buf(), Instant::now());
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => log::error!("Watch error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        for dir in pending.take_ready(Instant::now()) {
+            on_ready(&dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_dirs_wait_for_settle() {
+        let start = Instant::now();
+        let mut pending = PendingDirs::new(Duration::from_secs(10));
+        pending.touch(PathBuf::from("a"), start);
+        pending.touch(PathBuf::from("b"), start + Duration::from_secs(5));
+
+        assert!(pending.take_ready(start + Duration::from_secs(9)).is_empty());
+        assert_eq!(pending.take_ready(start + Duration::from_secs(10)), vec![PathBuf::from("a")]);
+
+        // A new change restarts the clock
+        pending.touch(PathBuf::from("b"), start + Duration::from_secs(12));
+        assert!(pending.take_ready(start + Duration::from_secs(16)).is_empty());
+        assert_eq!(pending.take_ready(start + Duration::from_secs(22)), vec![PathBuf::from("b")]);
+        assert!(pending.take_ready(start + Duration::from_secs(100)).is_empty());
+    }
+}
@@ -1,29 +1,74 @@
+use std::collections::HashSet;
 use std::time::Duration;
 
 use color_eyre::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use git2::{DiffFormat, DiffOptions, ErrorClass, ErrorCode, StashApplyOptions, StashSaveOptions, Status, StatusOptions};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+use git2::build::CheckoutBuilder;
+use git2::{
+    DiffFormat, DiffOptions, DiffStatsFormat, ErrorClass, ErrorCode, StashApplyOptions, StashSaveOptions, Status,
+    StatusOptions, StatusShow,
+};
 use ratatui::backend::Backend;
-use ratatui::layout::{Constraint, Direction, Flex, Layout, Position};
+use ratatui::layout::{Constraint, Direction, Flex, Layout, Position, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap};
 use ratatui::{Frame, Terminal};
 use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
 
+use crate::backup;
+use crate::binary;
+use crate::config::Config;
+use crate::conflicts::{self, Conflict};
+use crate::diff_loader::DiffLoader;
+use crate::diff_search::{DiffSearch, Match};
+use crate::external::{self, Launch};
+use crate::history;
+use crate::hunks::{self, FileSelection, Hunk};
+use crate::keymap::Action;
+use crate::op_log::OpLog;
+use crate::recover::{self, DroppedStash};
+use crate::side_by_side;
+use crate::status_loader::StatusLoader;
+use crate::submodules;
+use crate::tui;
+use crate::watcher::RepoWatcher;
+use crate::word_diff;
+use crate::worktrees;
+
 // ── Color palette ────────────────────────────────────────────────────
-const ACCENT: Color = Color::Indexed(75);        // soft blue — tab highlight, titles
-const HIGHLIGHT_BG: Color = Color::Indexed(236);  // dark gray — selected row background
-const HIGHLIGHT_FG: Color = Color::Indexed(75);   // soft blue — selected row text
-const SUCCESS: Color = Color::Indexed(114);       // soft green — status messages, diff +
-const ERROR: Color = Color::Indexed(203);         // soft red — errors, diff -, drop popup
-const DIFF_HUNK: Color = Color::Indexed(139);     // muted purple — @@ hunk headers
-const DIM: Color = Color::Indexed(242);           // gray — help text, borders
+pub(crate) const ACCENT: Color = Color::Indexed(75);         // soft blue — tab highlight, titles
+pub(crate) const HIGHLIGHT_BG: Color = Color::Indexed(236);  // dark gray — selected row background
+pub(crate) const HIGHLIGHT_FG: Color = Color::Indexed(75);   // soft blue — selected row text
+pub(crate) const SUCCESS: Color = Color::Indexed(114);       // soft green — status messages, diff +
+pub(crate) const ERROR: Color = Color::Indexed(203);         // soft red — errors, diff -, drop popup
+pub(crate) const WARNING: Color = Color::Indexed(179);       // soft amber — stale stashes
+pub(crate) const DIFF_HUNK: Color = Color::Indexed(139);     // muted purple — @@ hunk headers
+pub(crate) const DIM: Color = Color::Indexed(242);           // gray — help text, borders
+pub(crate) const WORD_ADD_BG: Color = Color::Indexed(22);    // dark green — changed words in + lines
+pub(crate) const WORD_DEL_BG: Color = Color::Indexed(52);    // dark red — changed words in - lines
+pub(crate) const SEARCH_MATCH_BG: Color = Color::Indexed(58); // dark olive — diff search matches
+pub(crate) const SEARCH_CURRENT_BG: Color = Color::Indexed(178); // gold — the diff search match jumped to
 
 /// Maximum number of diff lines to display before truncation.
 /// Prevents UI freezes on very large diffs. Well below ratatui's u16::MAX buffer limit.
 const MAX_DIFF_LINES: usize = 10_000;
 
+/// Shown in the diff panel while a stash's diff is built in the background.
+const LOADING_DIFF: &str = "Loading diff…";
+
+/// Frames of the spinner shown while the working directory is scanned, one per 100ms.
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Width of the diffstat view, the 80 columns `git diff --stat` assumes off a terminal.
+const DIFF_STAT_WIDTH: usize = 80;
+
+/// Columns the diff panel moves per horizontal scroll step.
+const HORIZONTAL_SCROLL_COLUMNS: u16 = 8;
+
+/// Lines the diff panel moves per mouse wheel step.
+const MOUSE_SCROLL_LINES: u16 = 3;
+
 /// Maximum number of files to display in the Create Stash file list.
 /// Prevents UI freezes in repositories with extremely large working trees.
 const MAX_FILES_TO_DISPLAY: usize = 1_000;
@@ -46,6 +91,9 @@ pub fn friendly_error_message(err: &git2::Error) -> String {
         (ErrorCode::UnbornBranch, _) => {
             "Repository has no commits yet. Create an initial commit first.".to_string()
         }
+        (ErrorCode::Conflict, ErrorClass::Checkout) => {
+            format!("Local changes would be overwritten ({}). Commit or stash them first.", err.message())
+        }
         (ErrorCode::Conflict, _) | (ErrorCode::MergeConflict, _) => {
             "Cannot perform operation: merge conflicts present. Resolve conflicts first.".to_string()
         }
@@ -55,6 +103,195 @@ pub fn friendly_error_message(err: &git2::Error) -> String {
     }
 }
 
+/// A stash's own message, without the "On branch: " or "WIP on branch: abc1234 " part
+fn stash_subject(message: &str) -> &str {
+    let subject = message.split_once(": ").map_or(message, |(_, m)| m);
+    if message.starts_with("WIP on ") {
+        subject.split_once(' ').map_or(subject, |(_, subject)| subject)
+    } else {
+        subject
+    }
+}
+
+/// Seconds since the epoch
+pub(crate) fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Whether a stash is older than `days`; 0 means nothing is stale
+fn is_stale(stash: &StashEntry, days: u64, now: i64) -> bool {
+    days > 0 && now - stash.time > days as i64 * 24 * 60 * 60
+}
+
+/// How long ago, e.g. `5m ago`, from a number of seconds
+fn format_age(seconds: i64) -> String {
+    match seconds.max(0) {
+        s if s < 60 => "just now".to_string(),
+        s if s < 60 * 60 => format!("{}m ago", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h ago", s / (60 * 60)),
+        s => format!("{}d ago", s / (24 * 60 * 60)),
+    }
+}
+
+/// A moment (seconds since the epoch) in local time as `YYYY-MM-DD` and `HH:MM:SS`, using
+/// git's idea of the local offset
+pub(crate) fn local_date_time(unix: i64) -> (String, String) {
+    let offset = git2::Signature::now("stash-mgr", "stash-mgr").map_or(0, |s| s.when().offset_minutes());
+    let local = unix + offset as i64 * 60;
+    let (days, seconds) = (local.div_euclid(24 * 60 * 60), local.rem_euclid(24 * 60 * 60));
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60),
+    )
+}
+
+/// A commit signature as `Name <email>`
+fn signature_label(signature: &git2::Signature) -> String {
+    format!(
+        "{} <{}>",
+        String::from_utf8_lossy(signature.name_bytes()),
+        String::from_utf8_lossy(signature.email_bytes())
+    )
+}
+
+/// The spinner frame for the current time
+fn spinner_frame() -> char {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    SPINNER[(millis / 100) as usize % SPINNER.len()]
+}
+
+/// The configured message template with `{branch}`, `{date}` and `{time}` filled in
+fn expand_template(template: &str, branch: &str) -> String {
+    if template.is_empty() {
+        return String::new();
+    }
+    let (date, time) = local_date_time(unix_now());
+    // Minutes are enough for a message
+    template.replace("{branch}", branch).replace("{date}", &date).replace("{time}", &time[..5])
+}
+
+/// A stash message describing what is stashed, used when the message is left empty,
+/// e.g. `3 files in src/app on feature/x — 2024-05-01`
+fn auto_message(paths: &[String], branch: &str) -> String {
+    let (date, _) = local_date_time(unix_now());
+    let what = match paths {
+        [path] => path.clone(),
+        _ => {
+            // The deepest folder holding every file
+            let mut common: Vec<&str> = paths[0].split('/').collect();
+            common.pop();
+            for path in &paths[1..] {
+                let dirs: Vec<&str> = path.split('/').collect();
+                let dirs = &dirs[..dirs.len() - 1];
+                let shared = common.iter().zip(dirs).take_while(|(a, b)| a == b).count();
+                common.truncate(shared);
+            }
+            if common.is_empty() {
+                format!("{} files", paths.len())
+            } else {
+                format!("{} files in {}", paths.len(), common.join("/"))
+            }
+        }
+    };
+    format!("{} on {} — {}", what, branch, date)
+}
+
+/// A file touched by a stash, with where its section starts in the diff preview
+#[derive(Clone, Debug)]
+pub struct StashFile {
+    pub path: String,
+    pub status: char,
+    pub untracked: bool,
+    pub marked: bool,
+    /// Line of the file's `diff --git` header; `None` when the preview was truncated before it
+    pub line: Option<usize>,
+}
+
+/// The file list of one stash, shown in place of the stash list
+pub struct StashFileListState {
+    pub list_state: ListState,
+    pub index: usize,
+    pub files: Vec<StashFile>,
+}
+
+impl StashFileListState {
+    /// Mark or unmark the highlighted file for a partial apply
+    pub fn toggle_selected(&mut self) {
+        if let Some(selected_idx) = self.list_state.selected()
+            && let Some(file) = self.files.get_mut(selected_idx)
+        {
+            file.marked = !file.marked;
+        }
+    }
+
+    /// The marked files, or the highlighted one when none are marked
+    pub fn chosen_files(&self) -> Vec<&StashFile> {
+        let marked: Vec<&StashFile> = self.files.iter().filter(|f| f.marked).collect();
+        if !marked.is_empty() {
+            return marked;
+        }
+        self.list_state.selected().and_then(|i| self.files.get(i)).into_iter().collect()
+    }
+
+    /// Move selection to next file (wraps around)
+    pub fn select_next(&mut self) {
+        if self.files.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0);
+        self.list_state.select(Some((current + 1) % self.files.len()));
+    }
+
+    /// Move selection to previous file (wraps around)
+    pub fn select_previous(&mut self) {
+        if self.files.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0);
+        self.list_state
+            .select(Some((current + self.files.len() - 1) % self.files.len()));
+    }
+}
+
+/// Dropped stashes that can still be restored, shown in place of the stash list
+pub struct DroppedListState {
+    pub list_state: ListState,
+    pub stashes: Vec<DroppedStash>,
+    /// Diff of the highlighted dropped stash
+    pub preview: String,
+    pub summary: Option<DiffSummary>,
+    pub stat: String,
+}
+
+/// Dropped stashes listed in the recovery view
+const MAX_DROPPED_STASHES: usize = 50;
+
+/// Size of a stash's changes, shown above its diff
+#[derive(Clone, Copy, Debug)]
+pub struct DiffSummary {
+    pub files: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Of `files`, how many were stored as untracked
+    pub untracked: usize,
+}
+
 /// A single stash entry with metadata
 #[derive(Clone, Debug)]
 pub struct StashEntry {
@@ -62,6 +299,19 @@ pub struct StashEntry {
     pub message: String,
     pub branch: String,
     pub oid: git2::Oid,
+    pub marked: bool,
+    /// Commit time, seconds since the epoch
+    pub time: i64,
+    /// Who made the stash, as `Name <email>`
+    pub author: String,
+    /// Who committed the stash, when not its author (e.g. one rebuilt from a patch)
+    pub committer: Option<String>,
+    /// Paths the stash touches, loaded on first use by the filter
+    pub paths: Option<Vec<String>>,
+    /// Changed lines (insertions + deletions), loaded on first use by the size sort
+    pub size: Option<usize>,
+    /// Another worktree with the stash's branch checked out, where it was most likely made
+    pub worktree: Option<worktrees::Worktree>,
 }
 
 /// A file entry in the working directory for stash creation
@@ -70,6 +320,54 @@ pub struct FileEntry {
     pub path: String,
     pub status: git2::Status,
     pub selected: bool,
+    /// Per-hunk choices when only part of the file is selected (None = whole file)
+    pub hunks: Option<Vec<bool>>,
+    /// A submodule, whose change is the commit it points at
+    pub submodule: bool,
+}
+
+/// Recent stash messages offered with Up/Down in the stash message popup
+#[derive(Default)]
+pub struct MessageHistory {
+    /// Most recent first
+    messages: Vec<String>,
+    /// The message shown, or None while editing the draft
+    position: Option<usize>,
+    /// What was typed before stepping into the history
+    draft: String,
+}
+
+impl MessageHistory {
+    fn load() -> Self {
+        Self { messages: history::recent_messages(), ..Self::default() }
+    }
+
+    /// The next older message, remembering `current` as the draft when leaving it
+    fn older(&mut self, current: &str) -> Option<&str> {
+        let next = self.position.map_or(0, |p| p + 1);
+        if next >= self.messages.len() {
+            return None;
+        }
+        if self.position.is_none() {
+            self.draft = current.to_string();
+        }
+        self.position = Some(next);
+        Some(&self.messages[next])
+    }
+
+    /// The next newer message, or the draft after the newest one
+    fn newer(&mut self) -> Option<&str> {
+        match self.position? {
+            0 => {
+                self.position = None;
+                Some(&self.draft)
+            }
+            p => {
+                self.position = Some(p - 1);
+                Some(&self.messages[p - 1])
+            }
+        }
+    }
 }
 
 /// State management for the message input popup
@@ -87,6 +385,14 @@ impl MessageInputState {
         }
     }
 
+    /// Initialize with a prefilled value and the cursor at its end
+    pub fn with_value(value: &str) -> Self {
+        Self {
+            input: value.to_string(),
+            cursor_position: value.chars().count(),
+        }
+    }
+
     /// Insert character at cursor position
     pub fn enter_char(&mut self, c: char) {
         let byte_index = self.byte_index();
@@ -157,6 +463,39 @@ impl FileListState {
             && let Some(file) = self.files.get_mut(selected_idx)
         {
             file.selected = !file.selected;
+            file.hunks = None;
+        }
+    }
+
+    /// Whether a file can be checked: submodules can't be stashed, and the entry counting
+    /// the files past the cap is not a file
+    fn selectable(file: &FileEntry) -> bool {
+        !file.submodule && !file.status.is_empty()
+    }
+
+    /// Select every listed file in full, dropping hunk choices; submodules can't be stashed
+    /// and are skipped
+    pub fn select_all(&mut self) {
+        for file in &mut self.files {
+            file.selected = Self::selectable(file);
+            file.hunks = None;
+        }
+    }
+
+    /// Select every listed file in full, or clear them all if they already are
+    pub fn toggle_all(&mut self) {
+        if self.files.iter().filter(|f| Self::selectable(f)).all(|f| f.selected && f.hunks.is_none()) {
+            self.select_none();
+        } else {
+            self.select_all();
+        }
+    }
+
+    /// Clear every file's selection, hunk choices included
+    pub fn select_none(&mut self) {
+        for file in &mut self.files {
+            file.selected = false;
+            file.hunks = None;
         }
     }
 
@@ -192,6 +531,272 @@ impl FileListState {
     pub fn has_selection(&self) -> bool {
         self.files.iter().any(|f| f.selected)
     }
+
+    /// Check if any selected file has only some of its hunks chosen
+    pub fn has_partial_selection(&self) -> bool {
+        self.files.iter().any(|f| f.selected && f.hunks.is_some())
+    }
+}
+
+/// State for the hunk drill-down of a single file in the Create Stash tab
+pub struct HunkListState {
+    pub list_state: ListState,
+    pub path: String,
+    pub hunks: Vec<Hunk>,
+}
+
+impl HunkListState {
+    /// Initialize with the file's hunks, preselected from the file's current selection
+    pub fn new(file: &FileEntry, mut hunks: Vec<Hunk>) -> Self {
+        for (i, hunk) in hunks.iter_mut().enumerate() {
+            hunk.selected = match file.hunks {
+                Some(ref chosen) => chosen.get(i).copied().unwrap_or(false),
+                None => file.selected,
+            };
+        }
+        let mut list_state = ListState::default();
+        if !hunks.is_empty() {
+            list_state.select(Some(0));
+        }
+        Self { list_state, path: file.path.clone(), hunks }
+    }
+
+    /// Select every hunk, or clear them all if they already are
+    pub fn toggle_all(&mut self) {
+        let all = self.hunks.iter().all(|h| h.selected);
+        for hunk in &mut self.hunks {
+            hunk.selected = !all;
+        }
+    }
+
+    /// Clear every hunk's selection
+    pub fn select_none(&mut self) {
+        for hunk in &mut self.hunks {
+            hunk.selected = false;
+        }
+    }
+
+    /// Toggle the selected state of the currently highlighted hunk
+    pub fn toggle_selected(&mut self) {
+        if let Some(selected_idx) = self.list_state.selected()
+            && let Some(hunk) = self.hunks.get_mut(selected_idx)
+        {
+            hunk.selected = !hunk.selected;
+        }
+    }
+
+    /// Move selection to next hunk (wraps around)
+    pub fn select_next(&mut self) {
+        if self.hunks.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0);
+        self.list_state.select(Some((current + 1) % self.hunks.len()));
+    }
+
+    /// Move selection to previous hunk (wraps around)
+    pub fn select_previous(&mut self) {
+        if self.hunks.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0);
+        self.list_state
+            .select(Some((current + self.hunks.len() - 1) % self.hunks.len()));
+    }
+
+    /// Write the hunk choices back to the file entry: all or none collapse to a
+    /// file-level selection, anything in between is kept per hunk
+    pub fn store_into(&self, file: &mut FileEntry) {
+        let chosen: Vec<bool> = self.hunks.iter().map(|h| h.selected).collect();
+        if chosen.iter().all(|c| *c) {
+            file.selected = true;
+            file.hunks = None;
+        } else if !chosen.iter().any(|c| *c) {
+            file.selected = false;
+            file.hunks = None;
+        } else {
+            file.selected = true;
+            file.hunks = Some(chosen);
+        }
+    }
+}
+
+/// Which files the Create Stash list shows, cycled like `git stash` / `-u` / `-a`
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum FileFilter {
+    #[default]
+    Tracked,
+    Untracked,
+    Ignored,
+}
+
+impl FileFilter {
+    pub fn next(self) -> Self {
+        match self {
+            FileFilter::Tracked => FileFilter::Untracked,
+            FileFilter::Untracked => FileFilter::Ignored,
+            FileFilter::Ignored => FileFilter::Tracked,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            FileFilter::Tracked => "tracked",
+            FileFilter::Untracked => "tracked + untracked",
+            FileFilter::Ignored => "tracked + untracked + ignored",
+        }
+    }
+}
+
+/// How both diff previews are laid out
+#[derive(Clone, Copy, Default)]
+pub struct DiffView {
+    /// Columns scrolled to the right
+    pub hscroll: u16,
+    /// Wrap long lines instead of clipping them (not side by side)
+    pub wrap: bool,
+    /// Old and new lines in two columns instead of a unified diff
+    pub side_by_side: bool,
+    /// A `--stat` summary instead of the patch (Manage tab only)
+    pub stat: bool,
+}
+
+/// A row of the Manage list: a stash, or a branch heading while grouped by branch
+#[derive(Clone, Debug, PartialEq)]
+enum StashRow {
+    /// Position in `stashes`
+    Stash(usize),
+    Branch {
+        name: String,
+        /// The branch no longer exists, so its stashes are likely leftovers
+        gone: bool,
+    },
+}
+
+/// Order of the Manage list
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum StashSort {
+    #[default]
+    Index,
+    Date,
+    Branch,
+    Size,
+}
+
+impl StashSort {
+    pub fn next(self) -> Self {
+        match self {
+            StashSort::Index => StashSort::Date,
+            StashSort::Date => StashSort::Branch,
+            StashSort::Branch => StashSort::Size,
+            StashSort::Size => StashSort::Index,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            StashSort::Index => "by index",
+            StashSort::Date => "newest first",
+            StashSort::Branch => "by branch",
+            StashSort::Size => "largest first",
+        }
+    }
+}
+
+/// What the text input popup is collecting
+#[derive(Clone, Copy, PartialEq)]
+pub enum InputPurpose {
+    StashMessage,
+    BranchName,
+    ExportPath,
+    StashFilter,
+    CommitMessage,
+    CommitBranch,
+    StashRename,
+    DiffSearch,
+    WorktreePath,
+    BackupPath,
+    GotoStash,
+}
+
+impl InputPurpose {
+    /// Popup title
+    fn title(self) -> &'static str {
+        match self {
+            InputPurpose::StashMessage => "Enter Stash Message (Enter: confirm, Esc: cancel)",
+            InputPurpose::BranchName => "New Branch Name (Enter: create, Esc: cancel)",
+            InputPurpose::ExportPath => "Export Patch To (Enter: write, Esc: cancel)",
+            InputPurpose::BackupPath => "Back Up All Stashes To (Enter: write, Esc: cancel)",
+            InputPurpose::GotoStash => "Go To Stash (Enter: jump, Esc: cancel)",
+            InputPurpose::StashFilter => "Filter Stashes by Message, Branch or File (Enter: keep, Esc: clear)",
+            InputPurpose::CommitMessage => "Commit Message (Enter: next, Esc: cancel)",
+            InputPurpose::CommitBranch => "Commit To Branch (Enter: commit, Esc: cancel)",
+            InputPurpose::StashRename => "Rename Stash (Enter: save, Esc: cancel)",
+            InputPurpose::DiffSearch => "Search the Diff (Enter: search, Esc: cancel)",
+            InputPurpose::WorktreePath => "New Worktree At (Enter: create, Esc: cancel)",
+        }
+    }
+
+    /// Help line shown while the popup is open
+    fn help(self) -> &'static str {
+        match self {
+            InputPurpose::StashMessage => {
+                "Enter: Create Stash | Esc: Cancel | Up/Down: Recent Messages | Leave empty for an automatic message"
+            }
+            InputPurpose::BranchName => "Enter: Create Branch | Esc: Cancel | Type the new branch name",
+            InputPurpose::ExportPath => "Enter: Write Patch | Esc: Cancel | Path is relative to the repository root",
+            InputPurpose::BackupPath => {
+                "Enter: Write Backup | Esc: Cancel | A new folder, relative to the repository root"
+            }
+            InputPurpose::GotoStash => "Enter: Jump | Esc: Cancel | A stash index, like 12 or stash@{12}",
+            InputPurpose::StashFilter => "Enter: Keep Filter | Esc: Clear Filter | Type to filter the stash list",
+            InputPurpose::CommitMessage => "Enter: Choose Branch | Esc: Cancel | Type the commit message",
+            InputPurpose::CommitBranch => {
+                "Enter: Commit | Esc: Cancel | The current branch commits on top of HEAD; a new name branches from the stash's base"
+            }
+            InputPurpose::StashRename => "Enter: Rename | Esc: Cancel | Type the new stash message",
+            InputPurpose::DiffSearch => "Enter: Search | Esc: Cancel | Search an empty text to clear the search",
+            InputPurpose::WorktreePath => {
+                "Enter: Create Worktree | Esc: Cancel | Relative to the repository root; the folder name also names the branch"
+            }
+        }
+    }
+}
+
+/// Which step of trying a stash out in a new worktree went wrong
+enum StashWorktreeError {
+    /// Nothing was left behind: the branch or the worktree could not be created
+    Create(git2::Error),
+    /// The worktree exists but the stash did not apply there
+    Apply(git2::Error),
+}
+
+/// Which step of creating a branch from a stash went wrong
+enum BranchFromStashError {
+    /// Nothing changed: the branch could not be created or checked out
+    Branch(git2::Error),
+    /// The branch is checked out but the stash did not apply and was kept
+    Apply(git2::Error),
+    /// The stash applied but could not be dropped
+    Drop(git2::Error),
+}
+
+/// A help popup row: the actions whose keys are listed, and what they do
+type HelpEntry = (&'static [Action], &'static str);
+
+/// An apply or pop held back because the stash would conflict, comes from another worktree,
+/// or touches files with uncommitted changes
+struct ConflictWarning {
+    index: usize,
+    restore_index: bool,
+    pop: bool,
+    conflicts: Vec<Conflict>,
+    /// The other worktree the stash's branch is checked out in
+    worktree: Option<worktrees::Worktree>,
+    /// Whether tracked files have uncommitted changes that could be stashed out of the way
+    dirty: bool,
+    /// Files with uncommitted changes that the stash also changes, besides those in `conflicts`
+    overlapping: Vec<String>,
 }
 
 /// The currently selected tab in the application
@@ -222,21 +827,87 @@ impl SelectedTab {
 
 /// Main application state
 pub struct App {
+    config: Config,
     selected_tab: SelectedTab,
     should_quit: bool,
     repo: git2::Repository,
     stashes: Vec<StashEntry>,
     stash_list_state: ListState,
+    /// Name of the worktree the app runs in, when the repository has linked worktrees
+    worktree_name: Option<String>,
+    stash_filter: String,
+    stash_sort: StashSort,
+    /// Positions in `stashes` shown in the list, in order (all of them unless filtered)
+    visible_stashes: Vec<usize>,
+    /// The list's rows: `visible_stashes`, under branch headings when grouped
+    stash_rows: Vec<StashRow>,
+    group_by_branch: bool,
+    /// Branches whose stashes are hidden under their heading
+    collapsed_branches: HashSet<String>,
+    /// The stash last created in the Create tab, to jump to from there
+    created_stash: Option<git2::Oid>,
     diff_content: String,
+    /// Layout shared by both diff previews
+    diff_view: DiffView,
+    /// The selected stash (or comparison) as `--stat` output, shown in place of `diff_content`
+    diff_stat: String,
+    /// Builds `diff_content`, `diff_summary` and `diff_stat` off the UI thread
+    diff_loader: DiffLoader,
+    /// Whether `diff_content` is still the loading placeholder
+    diff_loading: bool,
+    diff_summary: Option<DiffSummary>,
     diff_scroll: u16,
+    /// Stashes (older mark, selected) whose difference the diff panel shows instead
+    comparison: Option<(usize, usize)>,
+    /// Preview what applying the selected stash now would change instead of its own diff
+    against_current: bool,
+    stash_file_list_state: Option<StashFileListState>,
+    dropped_list_state: Option<DroppedListState>,
+    /// `g` was pressed once; a second press jumps to the top of the diff
+    pending_top: bool,
+    /// Text to find in the diff previews of both tabs
+    diff_search: DiffSearch,
     status_message: Option<String>,
     show_confirm_popup: bool,
-    confirm_stash_index: Option<usize>,
+    /// The stashes the drop popup asks about, by commit so that stashes created or dropped
+    /// elsewhere in the meantime can't shift a different one into their place
+    confirm_stashes: Vec<git2::Oid>,
+    conflict_warning: Option<ConflictWarning>,
+    show_help: bool,
+    help_scroll: u16,
+    /// Recent operations and how they went, shown in the log popup
+    op_log: OpLog,
+    show_log: bool,
+    log_scroll: u16,
     file_list_state: Option<FileListState>,
+    file_filter: FileFilter,
+    /// Builds `file_list_state` off the UI thread
+    status_loader: StatusLoader,
+    /// Whether the file list is still being scanned
+    files_loading: bool,
+    /// Whether the scan in progress keeps the checked files of the current list
+    files_keep_selection: bool,
+    hunk_list_state: Option<HunkListState>,
     create_diff_content: String,
     create_diff_scroll: u16,
     show_message_input: bool,
+    input_purpose: InputPurpose,
     message_input: MessageInputState,
+    /// Recent stash messages for the stash message popup
+    message_history: MessageHistory,
+    /// Message entered for a stash-to-commit while its branch is being asked for
+    commit_message: String,
+    /// A pager or difftool to run once the current key is handled
+    pending_launch: Option<Launch>,
+    /// Notices stash and file changes made outside the app; `None` if watching failed
+    watcher: Option<RepoWatcher>,
+    /// External changes not yet reloaded because a popup or the hunk view was open
+    stashes_stale: bool,
+    files_stale: bool,
+    /// Screen areas from the last draw, for mouse hit-testing
+    tabs_area: Rect,
+    list_area: Rect,
+    diff_area: Rect,
 }
 
 impl App {
@@ -258,34 +929,85 @@ impl App {
         Ok(())
     }
 
-    pub fn new(mut repo: git2::Repository) -> Self {
+    pub fn new(mut repo: git2::Repository, config: Config) -> Self {
         let stashes = Self::load_stashes(&mut repo);
+        let visible_stashes: Vec<usize> = (0..stashes.len()).collect();
+        let stash_rows = visible_stashes.iter().map(|&i| StashRow::Stash(i)).collect();
         let mut stash_list_state = ListState::default();
 
-        // Select first stash if any exist and load its diff
-        let diff_content = if !stashes.is_empty() {
+        // Select first stash if any exist and start loading its diff
+        let diff_loader = DiffLoader::new(repo.path());
+        let (diff_content, diff_loading) = if !stashes.is_empty() {
             stash_list_state.select(Some(0));
-            Self::get_stash_diff(&repo, stashes[0].oid)
+            diff_loader.request(stashes[0].oid, false);
+            (LOADING_DIFF.to_string(), true)
         } else {
-            String::new()
+            (String::new(), false)
         };
 
+        let (watcher, watcher_error) = match RepoWatcher::new(&repo) {
+            Ok(watcher) => (Some(watcher), None),
+            Err(e) => (None, Some(format!("Auto-refresh is off: {}", e))),
+        };
+        let status_loader = StatusLoader::new(repo.path());
+        let worktree_name = worktrees::list(&repo).into_iter().find(|w| w.current).map(|w| w.name);
         let mut app = Self {
+            config,
             selected_tab: SelectedTab::default(),
             should_quit: false,
             repo,
             stashes,
             stash_list_state,
+            worktree_name,
+            stash_filter: String::new(),
+            stash_sort: StashSort::default(),
+            visible_stashes,
+            stash_rows,
+            group_by_branch: false,
+            collapsed_branches: HashSet::new(),
+            created_stash: None,
+            diff_stat: diff_content.clone(),
             diff_content,
+            diff_summary: None,
             diff_scroll: 0,
-            status_message: None,
+            diff_view: DiffView::default(),
+            diff_loader,
+            diff_loading,
+            comparison: None,
+            against_current: false,
+            stash_file_list_state: None,
+            dropped_list_state: None,
+            pending_top: false,
+            diff_search: DiffSearch::default(),
+            status_message: watcher_error,
             show_confirm_popup: false,
-            confirm_stash_index: None,
+            confirm_stashes: Vec::new(),
+            conflict_warning: None,
+            show_help: false,
+            help_scroll: 0,
+            op_log: OpLog::default(),
+            show_log: false,
+            log_scroll: 0,
             file_list_state: None,
+            file_filter: FileFilter::default(),
+            status_loader,
+            files_loading: false,
+            files_keep_selection: false,
+            hunk_list_state: None,
             create_diff_content: String::new(),
             create_diff_scroll: 0,
             show_message_input: false,
+            input_purpose: InputPurpose::StashMessage,
             message_input: MessageInputState::new(),
+            message_history: MessageHistory::default(),
+            commit_message: String::new(),
+            pending_launch: None,
+            watcher,
+            stashes_stale: false,
+            files_stale: false,
+            tabs_area: Rect::default(),
+            list_area: Rect::default(),
+            diff_area: Rect::default(),
         };
 
         // Load file list on startup since Create is the default tab
@@ -293,11 +1015,20 @@ impl App {
         app
     }
 
-    /// Load working directory files for stash creation (tracked files only)
-    fn load_working_files(repo: &git2::Repository) -> Vec<FileEntry> {
+    /// Load working directory files for stash creation; untracked and ignored files only
+    /// when the filter asks for them (directories of them collapse to one entry, as in `git status`)
+    pub(crate) fn load_working_files(
+        repo: &git2::Repository,
+        filter: FileFilter,
+        exclude_submodules: bool,
+        show: StatusShow,
+    ) -> Vec<FileEntry> {
         let mut opts = StatusOptions::new();
-        opts.include_untracked(false);
-        opts.include_ignored(false);
+        opts.show(show);
+        opts.include_untracked(filter != FileFilter::Tracked);
+        opts.include_ignored(filter == FileFilter::Ignored);
+        opts.exclude_submodules(exclude_submodules);
+        let submodules = submodules::paths(repo);
 
         let statuses = match repo.statuses(Some(&mut opts)) {
             Ok(s) => s,
@@ -315,6 +1046,8 @@ impl App {
                     path: format!("... ({} more files not shown)", hidden_count),
                     status: Status::empty(),
                     selected: false,
+                    hunks: None,
+                    submodule: false,
                 });
                 break;
             }
@@ -327,13 +1060,17 @@ impl App {
                     | Status::WT_DELETED
                     | Status::INDEX_MODIFIED
                     | Status::INDEX_NEW
-                    | Status::INDEX_DELETED,
+                    | Status::INDEX_DELETED
+                    | Status::WT_NEW
+                    | Status::IGNORED,
             )
                 && let Some(path) = entry.path() {
                     files.push(FileEntry {
                         path: path.to_string(),
                         status,
                         selected: false,
+                        hunks: None,
+                        submodule: submodules.iter().any(|s| s == path),
                     });
                 }
         }
@@ -354,6 +1091,10 @@ impl App {
             "modified"
         } else if status.contains(Status::WT_DELETED) {
             "deleted"
+        } else if status.contains(Status::WT_NEW) {
+            "untracked"
+        } else if status.contains(Status::IGNORED) {
+            "ignored"
         } else {
             "changed"
         }
@@ -361,22 +1102,57 @@ impl App {
 
     /// Refresh the file list for the Create Stash tab
     fn refresh_file_list(&mut self) {
-        let files = Self::load_working_files(&self.repo);
-        self.file_list_state = Some(FileListState::new(files));
+        self.file_list_state = None;
+        self.hunk_list_state = None;
         self.update_create_diff_preview();
+        self.load_files(false);
+    }
+
+    /// Start scanning the working directory in the background
+    fn load_files(&mut self, keep_selection: bool) {
+        self.status_loader.request(self.file_filter, self.config.exclude_submodules);
+        self.files_loading = true;
+        self.files_keep_selection = keep_selection;
+    }
+
+    /// Show the scanned files as they come in
+    fn receive_loaded_files(&mut self) {
+        if !self.files_loading {
+            return;
+        }
+        for loaded in self.status_loader.try_recv() {
+            if let Some(error) = loaded.error {
+                self.files_loading = false;
+                self.status_message = Some(format!("Failed to scan the working tree: {}", error));
+                continue;
+            }
+            if loaded.complete {
+                self.files_loading = false;
+            } else if self.file_list_state.is_some() && self.files_keep_selection {
+                // Staged changes alone would drop the other files' choices; wait for them all
+                continue;
+            }
+            if self.files_keep_selection {
+                self.show_files_keeping_selection(loaded.files);
+            } else {
+                self.file_list_state = Some(FileListState::new(loaded.files));
+                self.update_create_diff_preview();
+            }
+            // Later batches of the same scan build on this one
+            self.files_keep_selection = true;
+        }
     }
 
     /// Load all stashes from the repository
-    fn load_stashes(repo: &mut git2::Repository) -> Vec<StashEntry> {
+    pub(crate) fn load_stashes(repo: &mut git2::Repository) -> Vec<StashEntry> {
         let mut stashes = Vec::new();
 
         let _ = repo.stash_foreach(|index, name, oid| {
             // Parse the stash message to extract branch and user message
             // Format is typically: "WIP on branch: hash message" or "On branch: message"
 
-            // Extract branch name between "on " and ":"
-            let branch = if let Some(start) = name.find("on ") {
-                let after_on = &name[start + 3..];
+            // Extract branch name between "on "/"On " and ":"
+            let branch = if let Some(after_on) = name.strip_prefix("WIP on ").or(name.strip_prefix("On ")) {
                 if let Some(colon_pos) = after_on.find(':') {
                     after_on[..colon_pos].trim().to_string()
                 } else {
@@ -394,16 +1170,42 @@ impl App {
                 message,
                 branch,
                 oid: *oid,
+                marked: false,
+                time: 0,
+                author: String::new(),
+                committer: None,
+                paths: None,
+                size: None,
+                worktree: None,
             });
 
             true // Continue iteration
         });
 
+        // The repository is borrowed by stash_foreach until it finishes
+        for stash in &mut stashes {
+            if let Ok(commit) = repo.find_commit(stash.oid) {
+                stash.time = commit.time().seconds();
+                stash.author = signature_label(&commit.author());
+                let committer = signature_label(&commit.committer());
+                stash.committer = (committer != stash.author).then_some(committer);
+            }
+        }
+
+        // Stashes are shared by all worktrees, but a branch is checked out in only one of them
+        let worktrees = worktrees::list(repo);
+        for stash in &mut stashes {
+            stash.worktree = worktrees
+                .iter()
+                .find(|w| !w.current && w.branch.as_deref() == Some(stash.branch.as_str()))
+                .cloned();
+        }
+
         stashes
     }
 
     /// Get the diff for a stash
-    fn get_stash_diff(repo: &git2::Repository, stash_oid: git2::Oid) -> String {
+    pub(crate) fn get_stash_diff(repo: &git2::Repository, stash_oid: git2::Oid) -> String {
         // Try to generate the diff, return error string on failure
         match Self::try_get_stash_diff(repo, stash_oid, MAX_DIFF_LINES) {
             Ok(diff) => diff,
@@ -421,24 +1223,21 @@ impl App {
 
         let mut diff_text = String::new();
         let mut line_count = 0;
-        diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
-            // Check if we've hit the line limit
-            if line_count >= max_lines {
-                return false;
-            }
-
-            // Add origin character for context, addition, deletion lines
-            let origin = line.origin();
-            if matches!(origin, ' ' | '+' | '-' | 'B') {
-                diff_text.push(origin);
-            }
-            if let Ok(content) = std::str::from_utf8(line.content()) {
-                diff_text.push_str(content);
-                // Count lines in the content
-                line_count += content.lines().count().max(1);
+        Self::append_diff_text(repo, &diff, &mut diff_text, &mut line_count, max_lines)?;
+
+        // Untracked files (stash -u / -a) live in a third parent with no base of their own
+        if stash_commit.parent_count() > 2 && line_count < max_lines {
+            let untracked_tree = stash_commit.parent(2)?.tree()?;
+            let untracked = repo.diff_tree_to_tree(None, Some(&untracked_tree), None)?;
+            if untracked.deltas().len() > 0 {
+                if !diff_text.is_empty() && !diff_text.ends_with('\n') {
+                    diff_text.push('\n');
+                }
+                diff_text.push_str(&format!("\nUntracked files ({}):\n", untracked.deltas().len()));
+                line_count += 2;
+                Self::append_diff_text(repo, &untracked, &mut diff_text, &mut line_count, max_lines)?;
             }
-            true
-        })?;
+        }
 
         // Add truncation message if we hit the limit
         if line_count >= max_lines {
@@ -448,44 +1247,310 @@ impl App {
         Ok(diff_text)
     }
 
-    /// Main event loop - draw and handle events
-    pub fn run(&mut self, terminal: &mut Terminal<impl Backend>) -> Result<()> {
-        while !self.should_quit {
-            terminal.draw(|f| self.draw(f))?;
-            self.handle_events()?;
+    /// Patch, `--stat` text and totals of what applying a stash now would change
+    pub(crate) fn get_diff_against_current(repo: &git2::Repository, stash_oid: git2::Oid) -> (String, String, Option<DiffSummary>) {
+        match Self::try_diff_against_current(repo, stash_oid, MAX_DIFF_LINES) {
+            Ok((diff, stat, summary)) => (diff, stat, Some(summary)),
+            Err(e) => {
+                let message = format!("Failed to generate diff: {}", friendly_error_message(&e));
+                (message.clone(), message, None)
+            }
         }
-        Ok(())
     }
 
-    /// Handle keyboard events
-    fn handle_events(&mut self) -> Result<()> {
-        // Poll for events with 100ms timeout for responsive but low-CPU polling
-        if event::poll(Duration::from_millis(100))?
-            && let Event::Key(key) = event::read()?
-        {
-            self.handle_key_event(key);
+    /// Get the `--stat` summary for a stash
+    pub(crate) fn get_stash_stat(repo: &git2::Repository, stash_oid: git2::Oid) -> String {
+        match Self::try_get_stash_stat(repo, stash_oid) {
+            Ok(stat) => stat,
+            Err(e) => format!("Failed to generate diffstat: {}", friendly_error_message(&e)),
         }
-        Ok(())
     }
 
-    /// Handle individual key press events
-    fn handle_key_event(&mut self, key: KeyEvent) {
-        // Only handle key press events (Windows compatibility)
-        if key.kind != KeyEventKind::Press {
-            return;
+    /// Try to get the `--stat` summary for a stash (internal helper)
+    fn try_get_stash_stat(repo: &git2::Repository, stash_oid: git2::Oid) -> Result<String, git2::Error> {
+        let stash_commit = repo.find_commit(stash_oid)?;
+        let parent_tree = stash_commit.parent(0)?.tree()?;
+        let tracked = repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_commit.tree()?), None)?;
+        let untracked = if stash_commit.parent_count() > 2 {
+            Some(repo.diff_tree_to_tree(None, Some(&stash_commit.parent(2)?.tree()?), None)?)
+        } else {
+            None
+        };
+        Self::diff_stat_text(&tracked, untracked.as_ref())
+    }
+
+    /// Per-file `+`/`-` bars and totals like `git diff --stat`, untracked files in their own section
+    fn diff_stat_text(tracked: &git2::Diff, untracked: Option<&git2::Diff>) -> Result<String, git2::Error> {
+        let stat = |diff: &git2::Diff| -> Result<String, git2::Error> {
+            let buf = diff.stats()?.to_buf(DiffStatsFormat::FULL, DIFF_STAT_WIDTH)?;
+            Ok(String::from_utf8_lossy(&buf).into_owned())
+        };
+        let mut stat_text = String::new();
+        if tracked.deltas().len() > 0 {
+            stat_text.push_str(&stat(tracked)?);
+        }
+        if let Some(untracked) = untracked.filter(|d| d.deltas().len() > 0) {
+            if !stat_text.is_empty() {
+                stat_text.push('\n');
+            }
+            stat_text.push_str(&format!("Untracked files ({}):\n", untracked.deltas().len()));
+            stat_text.push_str(&stat(untracked)?);
         }
+        Ok(stat_text)
+    }
 
-        // Clear status message on any keypress
-        self.status_message = None;
+    /// Files changed, insertions, and deletions of a stash, untracked files included
+    pub(crate) fn stash_summary(repo: &git2::Repository, stash_oid: git2::Oid) -> Result<DiffSummary, git2::Error> {
+        let stash_commit = repo.find_commit(stash_oid)?;
+        let parent_tree = stash_commit.parent(0)?.tree()?;
+        let stats = repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_commit.tree()?), None)?.stats()?;
+        let mut summary = DiffSummary {
+            files: stats.files_changed(),
+            insertions: stats.insertions(),
+            deletions: stats.deletions(),
+            untracked: 0,
+        };
+        if stash_commit.parent_count() > 2 {
+            let untracked_tree = stash_commit.parent(2)?.tree()?;
+            let stats = repo.diff_tree_to_tree(None, Some(&untracked_tree), None)?.stats()?;
+            summary.files += stats.files_changed();
+            summary.insertions += stats.insertions();
+            summary.untracked = stats.files_changed();
+        }
+        Ok(summary)
+    }
+
+    /// Diff from one stash to another: their working trees, then their untracked files
+    fn try_compare_stashes(
+        repo: &git2::Repository,
+        from: git2::Oid,
+        to: git2::Oid,
+        max_lines: usize,
+    ) -> Result<(String, String, DiffSummary), git2::Error> {
+        let from = repo.find_commit(from)?;
+        let to = repo.find_commit(to)?;
+        let tracked = repo.diff_tree_to_tree(Some(&from.tree()?), Some(&to.tree()?), None)?;
+        let from_untracked = from.parent(2).ok().map(|p| p.tree()).transpose()?;
+        let to_untracked = to.parent(2).ok().map(|p| p.tree()).transpose()?;
+        let untracked = match (&from_untracked, &to_untracked) {
+            (None, None) => None,
+            _ => Some(repo.diff_tree_to_tree(from_untracked.as_ref(), to_untracked.as_ref(), None)?),
+        };
+
+        let (mut diff_text, mut stat_text, summary) = Self::diff_texts(repo, &tracked, untracked.as_ref(), max_lines)?;
+        if diff_text.is_empty() {
+            diff_text.push_str("The two stashes contain the same changes.");
+            stat_text.push_str(&diff_text);
+        }
+        Ok((diff_text, stat_text, summary))
+    }
+
+    /// What applying a stash now would change: its changes merged into the current index as
+    /// `git stash apply` does, plus its untracked files. Files that would not apply cleanly
+    /// are listed first; conflicted ones are left out of the diff.
+    fn try_diff_against_current(
+        repo: &git2::Repository,
+        stash_oid: git2::Oid,
+        max_lines: usize,
+    ) -> Result<(String, String, DiffSummary), git2::Error> {
+        let stash_commit = repo.find_commit(stash_oid)?;
+        let (current, applied) = conflicts::applied_tree(repo, stash_oid)?;
+        let tracked = repo.diff_tree_to_tree(Some(&current), Some(&applied), None)?;
+        let untracked = if stash_commit.parent_count() > 2 {
+            Some(repo.diff_tree_to_tree(None, Some(&stash_commit.parent(2)?.tree()?), None)?)
+        } else {
+            None
+        };
+
+        let (mut diff_text, mut stat_text, summary) = Self::diff_texts(repo, &tracked, untracked.as_ref(), max_lines)?;
+        let problems = conflicts::predict(repo, stash_oid, false)?;
+        if !problems.is_empty() {
+            let mut heading = format!("Would not apply cleanly ({}):\n", problems.len());
+            for problem in &problems {
+                heading.push_str(&format!("  {}: {}\n", problem.path, problem.kind));
+            }
+            if !diff_text.is_empty() {
+                heading.push('\n');
+            }
+            diff_text.insert_str(0, &heading);
+            stat_text.insert_str(0, &heading);
+        } else if diff_text.is_empty() {
+            diff_text.push_str("Applying this stash now would change nothing.");
+            stat_text.push_str(&diff_text);
+        }
+        Ok((diff_text, stat_text, summary))
+    }
+
+    /// Patch text, `--stat` text and totals for a diff and the untracked files that go with it
+    fn diff_texts(
+        repo: &git2::Repository,
+        tracked: &git2::Diff,
+        untracked: Option<&git2::Diff>,
+        max_lines: usize,
+    ) -> Result<(String, String, DiffSummary), git2::Error> {
+        let stats = tracked.stats()?;
+        let mut summary = DiffSummary {
+            files: stats.files_changed(),
+            insertions: stats.insertions(),
+            deletions: stats.deletions(),
+            untracked: 0,
+        };
+        let stat_text = Self::diff_stat_text(tracked, untracked)?;
+        let mut diff_text = String::new();
+        let mut line_count = 0;
+        Self::append_diff_text(repo, tracked, &mut diff_text, &mut line_count, max_lines)?;
+        if let Some(untracked) = untracked.filter(|d| d.deltas().len() > 0) {
+            let stats = untracked.stats()?;
+            summary.files += stats.files_changed();
+            summary.insertions += stats.insertions();
+            summary.deletions += stats.deletions();
+            summary.untracked = stats.files_changed();
+            if line_count < max_lines {
+                if !diff_text.is_empty() && !diff_text.ends_with('\n') {
+                    diff_text.push('\n');
+                }
+                diff_text.push_str(&format!("\nUntracked files ({}):\n", untracked.deltas().len()));
+                line_count += 2;
+                Self::append_diff_text(repo, untracked, &mut diff_text, &mut line_count, max_lines)?;
+            }
+        }
+
+        if line_count >= max_lines {
+            diff_text.push_str(&format!("\n... (diff truncated — showing first {} lines) ...", max_lines));
+        }
+        Ok((diff_text, stat_text, summary))
+    }
+
+    /// The complete patch for a stash, untracked files included, ready for `git apply`
+    pub(crate) fn stash_patch(repo: &git2::Repository, stash_oid: git2::Oid) -> Result<Vec<u8>, git2::Error> {
+        // Binary changes as git binary patches so the file applies without the repository
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true);
+
+        let stash_commit = repo.find_commit(stash_oid)?;
+        let parent_tree = stash_commit.parent(0)?.tree()?;
+        let mut diffs = vec![repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_commit.tree()?), Some(&mut opts))?];
+        if stash_commit.parent_count() > 2 {
+            let untracked_tree = stash_commit.parent(2)?.tree()?;
+            diffs.push(repo.diff_tree_to_tree(None, Some(&untracked_tree), Some(&mut opts))?);
+        }
+
+        let mut patch = Vec::new();
+        for diff in &diffs {
+            diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+                if matches!(line.origin(), ' ' | '+' | '-') {
+                    patch.push(line.origin() as u8);
+                }
+                patch.extend_from_slice(line.content());
+                true
+            })?;
+        }
+        Ok(patch)
+    }
+
+    /// Print a diff as patch text, stopping once `line_count` reaches `max_lines`
+    fn append_diff_text(
+        repo: &git2::Repository,
+        diff: &git2::Diff,
+        diff_text: &mut String,
+        line_count: &mut usize,
+        max_lines: usize,
+    ) -> Result<(), git2::Error> {
+        diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+            // Check if we've hit the line limit
+            if *line_count >= max_lines {
+                return false;
+            }
+
+            // A submodule's content is a commit id; summarize it instead
+            if submodules::is_submodule(&delta) {
+                if line.origin() == 'F' {
+                    let text = submodules::change_text(&delta);
+                    *line_count += text.lines().count();
+                    diff_text.push_str(&text);
+                }
+                return true;
+            }
+            if line.origin() == 'B' {
+                diff_text.push_str(&binary::change_line(repo, &delta));
+                diff_text.push('\n');
+                *line_count += 1;
+                return true;
+            }
+
+            // Add origin character for context, addition, deletion lines
+            let origin = line.origin();
+            if matches!(origin, ' ' | '+' | '-') {
+                diff_text.push(origin);
+            }
+            if let Ok(content) = std::str::from_utf8(line.content()) {
+                diff_text.push_str(content);
+                // Count lines in the content
+                *line_count += content.lines().count().max(1);
+            }
+            true
+        })
+        .or_else(|e| if e.code() == ErrorCode::User { Ok(()) } else { Err(e) })
+    }
+
+    /// Main event loop - draw and handle events
+    pub fn run(&mut self, terminal: &mut Terminal<impl Backend>) -> Result<()> {
+        while !self.should_quit {
+            terminal.draw(|f| self.draw(f))?;
+            self.handle_events()?;
+
+            // Hand the terminal to the external program, then take it back
+            if let Some(launch) = self.pending_launch.take() {
+                tui::restore()?;
+                let result = external::launch(&self.repo, launch);
+                tui::resume()?;
+                terminal.clear()?;
+                if let Err(e) = result {
+                    self.status_message = Some(format!("External viewer failed: {}", e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle keyboard events
+    fn handle_events(&mut self) -> Result<()> {
+        // Poll for events with 100ms timeout for responsive but low-CPU polling,
+        // checking more often while a diff is loading so it appears promptly
+        let timeout = if self.diff_loading || self.files_loading { Duration::from_millis(20) } else { Duration::from_millis(100) };
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) => self.handle_key_event(key),
+                Event::Mouse(mouse) => self.handle_mouse_event(mouse),
+                _ => {}
+            }
+        }
+        self.reload_external_changes();
+        self.receive_loaded_diff();
+        self.receive_loaded_files();
+        Ok(())
+    }
+
+    /// Handle individual key press events
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        // Only handle key press events (Windows compatibility)
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+
+        // Clear status message on any keypress
+        self.status_message = None;
 
         // Handle message input popup keys first (intercepts all other keys)
         if self.show_message_input {
             match key.code {
                 KeyCode::Char(c) => {
                     self.message_input.enter_char(c);
+                    self.update_live_filter();
                 }
                 KeyCode::Backspace => {
                     self.message_input.delete_char();
+                    self.update_live_filter();
                 }
                 KeyCode::Left => {
                     self.message_input.move_cursor_left();
@@ -493,13 +1558,39 @@ impl App {
                 KeyCode::Right => {
                     self.message_input.move_cursor_right();
                 }
-                KeyCode::Enter => {
-                    self.create_stash();
+                KeyCode::Up if self.input_purpose == InputPurpose::StashMessage => {
+                    if let Some(message) = self.message_history.older(self.message_input.value()) {
+                        self.message_input = MessageInputState::with_value(message);
+                    }
+                }
+                KeyCode::Down if self.input_purpose == InputPurpose::StashMessage => {
+                    if let Some(message) = self.message_history.newer() {
+                        self.message_input = MessageInputState::with_value(message);
+                    }
                 }
+                KeyCode::Enter => match self.input_purpose {
+                    InputPurpose::StashMessage => self.create_stash(),
+                    InputPurpose::BranchName => self.branch_from_stash(),
+                    InputPurpose::WorktreePath => self.stash_worktree(),
+                    InputPurpose::ExportPath => self.export_stash(),
+                    InputPurpose::BackupPath => self.backup_stashes(),
+                    InputPurpose::GotoStash => self.goto_stash(),
+                    InputPurpose::CommitMessage => self.choose_commit_branch(),
+                    InputPurpose::CommitBranch => self.commit_stash(),
+                    InputPurpose::StashRename => self.rename_stash(),
+                    InputPurpose::DiffSearch => self.search_diff(),
+                    InputPurpose::StashFilter => {
+                        self.show_message_input = false;
+                        self.message_input = MessageInputState::new();
+                    }
+                },
                 KeyCode::Esc => {
                     // Cancel message input
                     self.show_message_input = false;
                     self.message_input = MessageInputState::new();
+                    if self.input_purpose == InputPurpose::StashFilter {
+                        self.update_live_filter();
+                    }
                 }
                 _ => {
                     // Ignore other keys when popup is visible
@@ -524,29 +1615,449 @@ impl App {
             return; // Don't process any other keys while popup is visible
         }
 
-        match key.code {
-            KeyCode::Char('q') | KeyCode::Char('Q') => {
-                self.should_quit = true;
+        // Handle conflict warning keys (intercepts all other keys)
+        if let Some(warning) = &self.conflict_warning {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    let (restore_index, pop) = (warning.restore_index, warning.pop);
+                    self.conflict_warning = None;
+                    self.finish_apply(restore_index, pop);
+                }
+                KeyCode::Char('s') | KeyCode::Char('S') if warning.dirty => {
+                    let (index, restore_index, pop) = (warning.index, warning.restore_index, warning.pop);
+                    self.conflict_warning = None;
+                    self.stash_local_changes_then_apply(index, restore_index, pop);
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.conflict_warning = None;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let action = self.config.keymap.action(&key, self.selected_tab);
+
+        // Help popup: scroll or close, nothing else
+        if self.show_help {
+            match action {
+                Some(Action::Down | Action::ScrollDown) => self.help_scroll = self.help_scroll.saturating_add(1),
+                Some(Action::Up | Action::ScrollUp) => self.help_scroll = self.help_scroll.saturating_sub(1),
+                Some(Action::Help | Action::Quit | Action::Clear) => self.show_help = false,
+                _ if key.code == KeyCode::Esc => self.show_help = false,
+                _ => {}
+            }
+            return;
+        }
+
+        // Operation log popup: scroll or close, nothing else
+        if self.show_log {
+            match action {
+                Some(Action::Down | Action::ScrollDown) => self.log_scroll = self.log_scroll.saturating_add(1),
+                Some(Action::Up | Action::ScrollUp) => self.log_scroll = self.log_scroll.saturating_sub(1),
+                Some(Action::Log | Action::Quit | Action::Clear) => self.show_log = false,
+                _ if key.code == KeyCode::Esc => self.show_log = false,
+                _ => {}
+            }
+            return;
+        }
+
+        // Unbound digits jump straight to that stash in the Manage list
+        if action.is_none()
+            && self.selected_tab == SelectedTab::Manage
+            && self.dropped_list_state.is_none()
+            && let KeyCode::Char(digit @ '0'..='9') = key.code
+        {
+            self.jump_to_stash(digit as usize - '0' as usize);
+            return;
+        }
+
+        if let Some(action) = action {
+            self.handle_action(action);
+        }
+    }
+
+    /// Reload the stash list and file list after changes from outside the app, waiting
+    /// while a popup or the hunk view holds on to the current entries
+    fn reload_external_changes(&mut self) {
+        if let Some(ref mut watcher) = self.watcher {
+            let changes = watcher.changes(&self.repo);
+            self.stashes_stale |= changes.stashes;
+            self.files_stale |= changes.files;
+        }
+        if self.show_message_input || self.show_confirm_popup || self.conflict_warning.is_some() {
+            return;
+        }
+
+        if self.stashes_stale {
+            self.stashes_stale = false;
+            self.reload_stashes_keeping_selection();
+        }
+        // The file list is reloaded on entering the Create tab anyway
+        if self.files_stale && self.selected_tab == SelectedTab::Create && self.hunk_list_state.is_none() {
+            self.files_stale = false;
+            self.reload_files_keeping_selection();
+        }
+        // What applying a stash would change follows the working directory
+        if self.files_stale
+            && self.selected_tab == SelectedTab::Manage
+            && self.against_current
+            && self.comparison.is_none()
+            && self.stash_file_list_state.is_none()
+            && self.dropped_list_state.is_none()
+        {
+            self.files_stale = false;
+            let scroll = self.diff_scroll;
+            self.update_diff_preview();
+            self.diff_scroll = scroll;
+        }
+    }
+
+    /// Reload stashes, keeping marks and the selected stash where they still exist
+    fn reload_stashes_keeping_selection(&mut self) {
+        let mut stashes = Self::load_stashes(&mut self.repo);
+        if stashes.iter().map(|s| s.oid).eq(self.stashes.iter().map(|s| s.oid)) {
+            return;
+        }
+        for stash in &mut stashes {
+            stash.marked = self.stashes.iter().any(|old| old.oid == stash.oid && old.marked);
+        }
+
+        let selected = self.selected_stash();
+        let prefer = selected
+            .and_then(|i| stashes.iter().position(|s| s.oid == self.stashes[i].oid))
+            .or(selected);
+        self.stashes = stashes;
+        self.refresh_visible_stashes(prefer);
+    }
+
+    /// Reload the working directory files, keeping file and hunk choices, the highlighted
+    /// file, and the preview's scroll position
+    fn reload_files_keeping_selection(&mut self) {
+        self.load_files(true);
+    }
+
+    /// Replace the file list, keeping choices of files still listed
+    fn show_files_keeping_selection(&mut self, mut files: Vec<FileEntry>) {
+        let Some(previous) = self.file_list_state.take() else {
+            self.file_list_state = Some(FileListState::new(files));
+            self.update_create_diff_preview();
+            return;
+        };
+        for file in &mut files {
+            if let Some(old) = previous.files.iter().find(|old| old.path == file.path) {
+                file.selected = old.selected;
+                file.hunks = old.hunks.clone();
+            }
+        }
+
+        let highlighted = previous.list_state.selected().and_then(|i| previous.files.get(i)).map(|f| f.path.clone());
+        let position = highlighted
+            .as_ref()
+            .and_then(|path| files.iter().position(|f| &f.path == path))
+            .or_else(|| previous.list_state.selected().map(|i| i.min(files.len().saturating_sub(1))));
+        // With nothing highlighted before (an empty list), keep the first file FileListState::new picked
+        let mut file_list_state = FileListState::new(files);
+        if !file_list_state.files.is_empty() && position.is_some() {
+            file_list_state.list_state.select(position);
+        }
+        let same_file = file_list_state.list_state.selected().and_then(|i| file_list_state.files.get(i)).map(|f| &f.path)
+            == highlighted.as_ref();
+        self.file_list_state = Some(file_list_state);
+
+        let scroll = self.create_diff_scroll;
+        self.update_create_diff_preview();
+        if same_file {
+            self.create_diff_scroll = scroll;
+        }
+    }
+
+    /// Handle mouse clicks (tabs, list rows) and wheel scrolling (lists, diff panel)
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) {
+        // Popups take the keyboard; leave the mouse alone while one is open
+        if self.show_message_input
+            || self.show_confirm_popup
+            || self.conflict_warning.is_some()
+            || self.show_help
+            || self.show_log
+        {
+            return;
+        }
+
+        let position = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if self.tabs_area.contains(position) {
+                    if let Some(tab) = self.tab_at(mouse.column) {
+                        self.switch_tab(tab);
+                    }
+                } else if self.list_area.contains(position) {
+                    self.click_list_row(mouse.row);
+                }
+            }
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let down = mouse.kind == MouseEventKind::ScrollDown;
+                if self.list_area.contains(position) {
+                    self.handle_action(if down { Action::Down } else { Action::Up });
+                } else if self.diff_area.contains(position) {
+                    let lines = MOUSE_SCROLL_LINES as isize;
+                    self.scroll_diff_by(if down { lines } else { -lines });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The tab whose title is at a column of the tab bar (titles are padded by one space
+    /// on each side and separated by a 3-column divider)
+    fn tab_at(&self, column: u16) -> Option<SelectedTab> {
+        let mut x = self.tabs_area.x + 1;
+        for tab in SelectedTab::iter() {
+            let width = tab.to_string().chars().count() as u16 + 2;
+            if (x..x + width).contains(&column) {
+                return Some(tab);
+            }
+            x += width + 3;
+        }
+        None
+    }
+
+    /// Select the list row under the mouse in whichever list is showing
+    fn click_list_row(&mut self, row: u16) {
+        // Rows start inside the top border
+        let Some(line) = row.checked_sub(self.list_area.y + 1) else {
+            return;
+        };
+        let line = line as usize;
+        match self.selected_tab {
+            SelectedTab::Create => {
+                if let Some(ref mut hunk_list_state) = self.hunk_list_state {
+                    let index = hunk_list_state.list_state.offset() + line;
+                    if index < hunk_list_state.hunks.len() {
+                        hunk_list_state.list_state.select(Some(index));
+                        self.update_hunk_preview();
+                    }
+                } else if let Some(ref mut file_list_state) = self.file_list_state {
+                    let index = file_list_state.list_state.offset() + line;
+                    if index < file_list_state.files.len() && file_list_state.list_state.selected() != Some(index) {
+                        file_list_state.list_state.select(Some(index));
+                        self.update_create_diff_preview();
+                    }
+                }
+            }
+            SelectedTab::Manage => {
+                if let Some(ref mut dropped_list_state) = self.dropped_list_state {
+                    let index = dropped_list_state.list_state.offset() + line;
+                    if index < dropped_list_state.stashes.len() && dropped_list_state.list_state.selected() != Some(index) {
+                        dropped_list_state.list_state.select(Some(index));
+                        self.update_dropped_preview();
+                    }
+                    return;
+                }
+                if let Some(ref mut stash_file_list_state) = self.stash_file_list_state {
+                    let index = stash_file_list_state.list_state.offset() + line;
+                    if index < stash_file_list_state.files.len() {
+                        stash_file_list_state.list_state.select(Some(index));
+                        self.jump_to_stash_file();
+                    }
+                    return;
+                }
+                let index = self.stash_list_state.offset() + line;
+                if index < self.stash_rows.len() && self.stash_list_state.selected() != Some(index) {
+                    self.stash_list_state.select(Some(index));
+                    self.update_diff_preview();
+                }
+            }
+        }
+    }
+
+    /// Switch to a tab, refreshing the file list when entering the Create tab; checked
+    /// files stay checked as long as they are still listed
+    fn switch_tab(&mut self, tab: SelectedTab) {
+        if tab == self.selected_tab {
+            return;
+        }
+        self.selected_tab = tab;
+        if self.selected_tab == SelectedTab::Create {
+            self.close_hunk_view();
+            self.files_stale = false;
+            self.reload_files_keeping_selection();
+        }
+    }
+
+    /// Carry out an action from a key binding (or its mouse equivalent)
+    fn handle_action(&mut self, action: Action) {
+        // `gg`: the first press only arms the jump to the top
+        if action == Action::Top {
+            if !std::mem::take(&mut self.pending_top) {
+                self.pending_top = true;
+                return;
+            }
+        } else {
+            self.pending_top = false;
+        }
+
+        // Handle hunk drill-down keys (other keys fall through to the Create tab)
+        if self.selected_tab == SelectedTab::Create && self.hunk_list_state.is_some() {
+            match action {
+                Action::Down => {
+                    if let Some(ref mut hunk_list_state) = self.hunk_list_state {
+                        hunk_list_state.select_next();
+                    }
+                    self.update_hunk_preview();
+                    return;
+                }
+                Action::Up => {
+                    if let Some(ref mut hunk_list_state) = self.hunk_list_state {
+                        hunk_list_state.select_previous();
+                    }
+                    self.update_hunk_preview();
+                    return;
+                }
+                Action::Toggle => {
+                    if let Some(ref mut hunk_list_state) = self.hunk_list_state {
+                        hunk_list_state.toggle_selected();
+                    }
+                    return;
+                }
+                Action::SelectAll | Action::SelectNone => {
+                    if let Some(ref mut hunk_list_state) = self.hunk_list_state {
+                        if action == Action::SelectAll {
+                            hunk_list_state.toggle_all();
+                        } else {
+                            hunk_list_state.select_none();
+                        }
+                    }
+                    return;
+                }
+                Action::Hunks | Action::Clear => {
+                    self.close_hunk_view();
+                    return;
+                }
+                Action::Stash | Action::StashAll => {
+                    // Keep the hunk choices before prompting for the message
+                    self.close_hunk_view();
+                }
+                _ => {}
             }
-            KeyCode::Tab => {
-                self.selected_tab = self.selected_tab.next();
-                // Refresh file list when switching to Create tab
-                if self.selected_tab == SelectedTab::Create {
-                    self.refresh_file_list();
+        }
+
+        // Handle the recently dropped list; only scrolling, help, tabs and quit fall through
+        if self.selected_tab == SelectedTab::Manage && self.dropped_list_state.is_some() {
+            match action {
+                Action::Down | Action::Up => {
+                    if let Some(ref mut dropped_list_state) = self.dropped_list_state
+                        && !dropped_list_state.stashes.is_empty()
+                    {
+                        let count = dropped_list_state.stashes.len();
+                        let current = dropped_list_state.list_state.selected().unwrap_or(0);
+                        let next = if action == Action::Down { (current + 1) % count } else { (current + count - 1) % count };
+                        dropped_list_state.list_state.select(Some(next));
+                    }
+                    self.update_dropped_preview();
+                    return;
+                }
+                Action::Files | Action::Hunks => {
+                    self.restore_dropped_stash();
+                    return;
                 }
+                Action::Recover | Action::Clear => {
+                    self.dropped_list_state = None;
+                    self.update_diff_preview();
+                    return;
+                }
+                Action::Filter => {
+                    // No stash list to filter here, so search the preview instead
+                    self.open_diff_search();
+                    return;
+                }
+                Action::Quit
+                | Action::Help
+                | Action::NextTab
+                | Action::PrevTab
+                | Action::ScrollDown
+                | Action::ScrollUp
+                | Action::HalfPageDown
+                | Action::HalfPageUp
+                | Action::PageDown
+                | Action::PageUp
+                | Action::ScrollLeft
+                | Action::ScrollRight
+                | Action::Wrap
+                | Action::SideBySide
+                | Action::Stat
+                | Action::Search
+                | Action::SearchNext
+                | Action::SearchPrev
+                | Action::Top
+                | Action::Bottom => {}
+                _ => return,
             }
-            KeyCode::BackTab => {
-                self.selected_tab = self.selected_tab.previous();
-                // Refresh file list when switching to Create tab
-                if self.selected_tab == SelectedTab::Create {
-                    self.refresh_file_list();
+        }
+
+        // Handle stash file list keys (other keys fall through to the Manage tab)
+        if self.selected_tab == SelectedTab::Manage && self.stash_file_list_state.is_some() {
+            match action {
+                Action::Down => {
+                    if let Some(ref mut stash_file_list_state) = self.stash_file_list_state {
+                        stash_file_list_state.select_next();
+                    }
+                    self.jump_to_stash_file();
+                    return;
+                }
+                Action::Up => {
+                    if let Some(ref mut stash_file_list_state) = self.stash_file_list_state {
+                        stash_file_list_state.select_previous();
+                    }
+                    self.jump_to_stash_file();
+                    return;
+                }
+                Action::Toggle => {
+                    if let Some(ref mut stash_file_list_state) = self.stash_file_list_state {
+                        stash_file_list_state.toggle_selected();
+                    }
+                    return;
+                }
+                Action::Apply => {
+                    self.apply_stash_files();
+                    return;
+                }
+                Action::Files | Action::Clear => {
+                    self.stash_file_list_state = None;
+                    return;
+                }
+                Action::Filter => {
+                    // The stash list is hidden, so search the diff instead
+                    self.open_diff_search();
+                    return;
                 }
+                _ => {}
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.selected_tab == SelectedTab::Manage && !self.stashes.is_empty() {
+        }
+
+        match action {
+            Action::Quit => {
+                self.should_quit = true;
+            }
+            Action::Help => {
+                self.show_help = true;
+                self.help_scroll = 0;
+            }
+            Action::Log => {
+                self.show_log = true;
+                self.log_scroll = 0;
+            }
+            Action::NextTab => {
+                self.switch_tab(self.selected_tab.next());
+            }
+            Action::PrevTab => {
+                self.switch_tab(self.selected_tab.previous());
+            }
+            Action::Down => {
+                if self.selected_tab == SelectedTab::Manage && !self.stash_rows.is_empty() {
                     let old_selection = self.stash_list_state.selected();
                     let current = old_selection.unwrap_or(0);
-                    self.stash_list_state.select(Some((current + 1) % self.stashes.len()));
+                    self.stash_list_state.select(Some((current + 1) % self.stash_rows.len()));
                     if old_selection != self.stash_list_state.selected() {
                         self.update_diff_preview();
                     }
@@ -560,11 +2071,12 @@ impl App {
                     }
                 }
             }
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.selected_tab == SelectedTab::Manage && !self.stashes.is_empty() {
+            Action::Up => {
+                if self.selected_tab == SelectedTab::Manage && !self.stash_rows.is_empty() {
                     let old_selection = self.stash_list_state.selected();
                     let current = old_selection.unwrap_or(0);
-                    self.stash_list_state.select(Some((current + self.stashes.len() - 1) % self.stashes.len()));
+                    let count = self.stash_rows.len();
+                    self.stash_list_state.select(Some((current + count - 1) % count));
                     if old_selection != self.stash_list_state.selected() {
                         self.update_diff_preview();
                     }
@@ -578,212 +2090,1675 @@ impl App {
                     }
                 }
             }
-            KeyCode::Char(' ') => {
+            Action::Toggle => {
                 if self.selected_tab == SelectedTab::Create
-                    && let Some(ref mut file_list_state) = self.file_list_state {
+                    && let Some(ref mut file_list_state) = self.file_list_state
+                {
+                    let highlighted = file_list_state.list_state.selected().and_then(|i| file_list_state.files.get(i));
+                    if let Some(file) = highlighted.filter(|f| f.submodule) {
+                        self.status_message = Some(format!(
+                            "{} is a submodule, which can't be stashed; set exclude_submodules to hide submodules",
+                            file.path
+                        ));
+                    } else {
                         file_list_state.toggle_selected();
                     }
-            }
-            KeyCode::Char('s') => {
-                if self.selected_tab == SelectedTab::Create {
-                    // Check if any files are selected
-                    if let Some(ref file_list_state) = self.file_list_state
-                        && file_list_state.has_selection()
-                    {
-                        // Show message input popup
-                        self.show_message_input = true;
-                        self.message_input = MessageInputState::new();
-                    } else {
-                        self.status_message = Some("No files selected. Use Space to select files first.".to_string());
+                } else if self.selected_tab == SelectedTab::Manage
+                    && let Some(stash) = self.selected_stash().and_then(|i| self.stashes.get_mut(i))
+                {
+                    stash.marked = !stash.marked;
+                } else if self.selected_tab == SelectedTab::Manage
+                    && let Some(branch) = self.selected_branch_heading()
+                {
+                    // Mark the branch's stashes, or unmark them if they all are
+                    let branch = branch.to_string();
+                    let stashes: Vec<usize> =
+                        self.visible_stashes.iter().copied().filter(|&i| self.stashes[i].branch == branch).collect();
+                    let mark = !stashes.iter().all(|&i| self.stashes[i].marked);
+                    for i in stashes {
+                        self.stashes[i].marked = mark;
                     }
                 }
             }
-            KeyCode::Right | KeyCode::Char('l') => {
-                if self.selected_tab == SelectedTab::Manage {
-                    self.diff_scroll = self.diff_scroll.saturating_add(1);
-                } else if self.selected_tab == SelectedTab::Create {
-                    self.create_diff_scroll = self.create_diff_scroll.saturating_add(1);
+            Action::Clear if self.selected_tab == SelectedTab::Manage && self.comparison.is_some() => {
+                self.update_diff_preview();
+            }
+            Action::Commit if self.selected_tab == SelectedTab::Manage => {
+                if let Some(index) = self.selected_stash() {
+                    let message = stash_subject(&self.stashes[index].message).to_string();
+                    self.open_input(InputPurpose::CommitMessage);
+                    self.message_input = MessageInputState::with_value(&message);
                 }
             }
-            KeyCode::Left | KeyCode::Char('h') => {
-                if self.selected_tab == SelectedTab::Manage {
-                    self.diff_scroll = self.diff_scroll.saturating_sub(1);
-                } else if self.selected_tab == SelectedTab::Create {
-                    self.create_diff_scroll = self.create_diff_scroll.saturating_sub(1);
+            Action::Rename if self.selected_tab == SelectedTab::Manage => {
+                if let Some(index) = self.selected_stash() {
+                    let message = stash_subject(&self.stashes[index].message).to_string();
+                    self.open_input(InputPurpose::StashRename);
+                    self.message_input = MessageInputState::with_value(&message);
                 }
             }
-            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                if self.selected_tab == SelectedTab::Manage {
-                    self.diff_scroll = self.diff_scroll.saturating_add(10);
-                } else if self.selected_tab == SelectedTab::Create {
-                    self.create_diff_scroll = self.create_diff_scroll.saturating_add(10);
+            Action::Recover if self.selected_tab == SelectedTab::Manage => {
+                self.open_dropped_view();
+            }
+            Action::Compare if self.selected_tab == SelectedTab::Manage => {
+                self.compare_stashes();
+            }
+            Action::Clear if self.selected_tab == SelectedTab::Manage => {
+                for stash in &mut self.stashes {
+                    stash.marked = false;
                 }
+                if !self.stash_filter.is_empty() {
+                    self.stash_filter.clear();
+                    self.refresh_visible_stashes(self.selected_stash());
+                }
+                self.diff_search = DiffSearch::default();
+            }
+            Action::Filter if self.selected_tab == SelectedTab::Manage => {
+                self.open_input(InputPurpose::StashFilter);
+                self.message_input = MessageInputState::with_value(&self.stash_filter);
+            }
+            Action::Sort if self.selected_tab == SelectedTab::Manage => {
+                self.stash_sort = self.stash_sort.next();
+                self.refresh_visible_stashes(self.selected_stash());
+            }
+            Action::FileFilter if self.selected_tab == SelectedTab::Create => {
+                self.file_filter = self.file_filter.next();
+                self.reload_files_keeping_selection();
             }
-            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                if self.selected_tab == SelectedTab::Manage {
-                    self.diff_scroll = self.diff_scroll.saturating_sub(10);
-                } else if self.selected_tab == SelectedTab::Create {
-                    self.create_diff_scroll = self.create_diff_scroll.saturating_sub(10);
+            Action::Hunks if self.selected_tab == SelectedTab::Create => {
+                self.open_hunk_view();
+            }
+            Action::ShowCreated if self.selected_tab == SelectedTab::Create => {
+                self.show_created_stash();
+            }
+            Action::SelectAll | Action::SelectNone if self.selected_tab == SelectedTab::Create => {
+                if let Some(ref mut file_list_state) = self.file_list_state {
+                    if action == Action::SelectAll {
+                        file_list_state.toggle_all();
+                    } else {
+                        file_list_state.select_none();
+                    }
                 }
             }
-            KeyCode::Char('a') => {
-                if self.selected_tab == SelectedTab::Manage {
-                    self.apply_stash();
+            Action::Stash if self.selected_tab == SelectedTab::Create => {
+                // Check if any files are selected
+                if let Some(ref file_list_state) = self.file_list_state
+                    && file_list_state.has_selection()
+                {
+                    // Show message input popup
+                    self.open_stash_message_input();
+                } else {
+                    self.status_message = Some(format!(
+                        "No files selected. Use {} to select files first.",
+                        self.config.keymap.key_label(Action::Toggle)
+                    ));
                 }
             }
-            KeyCode::Char('p') => {
-                if self.selected_tab == SelectedTab::Manage {
-                    self.pop_stash();
+            Action::StashAll if self.selected_tab == SelectedTab::Create => {
+                // Everything listed, so untracked and ignored files follow the file filter
+                if self.files_loading {
+                    self.status_message = Some("Still scanning the working tree; try again in a moment".to_string());
+                    return;
+                }
+                match self.file_list_state {
+                    Some(ref mut file_list_state) if !file_list_state.files.is_empty() => {
+                        file_list_state.select_all();
+                        if file_list_state.has_selection() {
+                            self.open_stash_message_input();
+                        } else {
+                            self.status_message = Some("Only submodules changed, and they can't be stashed".to_string());
+                        }
+                    }
+                    _ => self.status_message = Some("No changes to stash".to_string()),
                 }
             }
-            KeyCode::Char('d') if !key.modifiers.contains(KeyModifiers::CONTROL) => {
-                if self.selected_tab == SelectedTab::Manage {
-                    self.initiate_drop_stash();
+            Action::ScrollDown => self.scroll_diff_by(1),
+            Action::ScrollUp => self.scroll_diff_by(-1),
+            Action::HalfPageDown => self.scroll_diff_by(self.diff_page_height() / 2),
+            Action::HalfPageUp => self.scroll_diff_by(-(self.diff_page_height() / 2)),
+            Action::PageDown => self.scroll_diff_by(self.diff_page_height()),
+            Action::PageUp => self.scroll_diff_by(-self.diff_page_height()),
+            Action::ScrollRight => {
+                // Stop once the longest line's end is in view
+                let longest = self.diff_text().lines().map(|l| l.chars().count()).max().unwrap_or(0);
+                let last = longest.saturating_sub(self.diff_area.width.saturating_sub(2) as usize).min(u16::MAX as usize) as u16;
+                if self.diff_view.hscroll < last {
+                    self.diff_view.hscroll = (self.diff_view.hscroll + HORIZONTAL_SCROLL_COLUMNS).min(last);
                 }
             }
+            Action::ScrollLeft => {
+                self.diff_view.hscroll = self.diff_view.hscroll.saturating_sub(HORIZONTAL_SCROLL_COLUMNS);
+            }
+            Action::Wrap => self.diff_view.wrap = !self.diff_view.wrap,
+            Action::SideBySide => self.toggle_side_by_side(),
+            Action::Stat if self.selected_tab == SelectedTab::Manage => self.toggle_stat(),
+            Action::AgainstCurrent if self.selected_tab == SelectedTab::Manage => self.toggle_against_current(),
+            Action::Search => self.open_diff_search(),
+            Action::SearchNext => self.jump_to_match(true),
+            Action::SearchPrev => self.jump_to_match(false),
+            Action::Top => self.scroll_diff_to(0),
+            Action::Bottom => {
+                let bottom = self.diff_len().saturating_sub(self.diff_page_height() as usize);
+                self.scroll_diff_to(bottom);
+            }
+            Action::Apply if self.selected_tab == SelectedTab::Manage => {
+                self.start_apply(false, false);
+            }
+            Action::ApplyIndex if self.selected_tab == SelectedTab::Manage => {
+                self.start_apply(true, false);
+            }
+            Action::Pop if self.selected_tab == SelectedTab::Manage => {
+                self.start_apply(false, true);
+            }
+            Action::PopIndex if self.selected_tab == SelectedTab::Manage => {
+                self.start_apply(true, true);
+            }
+            Action::Branch if self.selected_tab == SelectedTab::Manage && self.selected_stash().is_some() => {
+                self.open_input(InputPurpose::BranchName);
+            }
+            Action::Files if self.selected_tab == SelectedTab::Manage => {
+                if let Some(branch) = self.selected_branch_heading() {
+                    let branch = branch.to_string();
+                    self.toggle_collapsed(branch);
+                } else {
+                    self.open_stash_file_view();
+                }
+            }
+            Action::Goto if self.selected_tab == SelectedTab::Manage => {
+                self.open_input(InputPurpose::GotoStash);
+            }
+            Action::Group if self.selected_tab == SelectedTab::Manage => {
+                self.group_by_branch = !self.group_by_branch;
+                self.refresh_visible_stashes(self.selected_stash());
+            }
+            Action::Pager if self.selected_tab == SelectedTab::Manage => {
+                if let Some(index) = self.selected_stash() {
+                    match Self::stash_patch(&self.repo, self.stashes[index].oid) {
+                        Ok(patch) => self.pending_launch = Some(Launch::Pager(patch)),
+                        Err(e) => self.status_message = Some(format!("Pager failed: {}", friendly_error_message(&e))),
+                    }
+                }
+            }
+            Action::Difftool if self.selected_tab == SelectedTab::Manage => {
+                self.open_difftool();
+            }
+            Action::Worktree if self.selected_tab == SelectedTab::Manage => {
+                if let Some(index) = self.selected_stash() {
+                    // A sibling of the working directory, named after the repository
+                    let repo_name = self
+                        .repo
+                        .workdir()
+                        .and_then(|w| w.file_name())
+                        .map_or_else(|| "repo".to_string(), |n| n.to_string_lossy().into_owned());
+                    self.open_input(InputPurpose::WorktreePath);
+                    self.message_input = MessageInputState::with_value(&format!("../{}-stash-{}", repo_name, index));
+                }
+            }
+            Action::Export if self.selected_tab == SelectedTab::Manage => {
+                if let Some(index) = self.selected_stash() {
+                    self.open_input(InputPurpose::ExportPath);
+                    self.message_input = MessageInputState::with_value(&format!("stash-{}.patch", index));
+                }
+            }
+            Action::Backup if self.selected_tab == SelectedTab::Manage => {
+                if self.stashes.is_empty() {
+                    self.status_message = Some("No stashes to back up".to_string());
+                } else {
+                    let (date, _) = local_date_time(unix_now());
+                    self.open_input(InputPurpose::BackupPath);
+                    self.message_input = MessageInputState::with_value(&format!("stash-backup-{}", date));
+                }
+            }
+            Action::Drop if self.selected_tab == SelectedTab::Manage => {
+                self.initiate_drop_stash();
+            }
+            Action::Cleanup if self.selected_tab == SelectedTab::Manage => {
+                self.initiate_cleanup();
+            }
             _ => {}
         }
     }
 
+    /// The text in the active tab's diff preview
+    fn diff_text(&self) -> &str {
+        match self.selected_tab {
+            SelectedTab::Create => &self.create_diff_content,
+            SelectedTab::Manage => match self.dropped_list_state {
+                Some(ref dropped_list_state) if self.diff_view.stat => &dropped_list_state.stat,
+                Some(ref dropped_list_state) => &dropped_list_state.preview,
+                None if self.diff_view.stat => &self.diff_stat,
+                None => &self.diff_content,
+            },
+        }
+    }
+
+    /// How the active tab's diff preview is laid out; only Manage has a diffstat view,
+    /// which has no columns
+    fn active_diff_view(&self) -> DiffView {
+        match self.selected_tab {
+            SelectedTab::Create => DiffView { stat: false, ..self.diff_view },
+            SelectedTab::Manage if self.diff_view.stat => DiffView { side_by_side: false, ..self.diff_view },
+            SelectedTab::Manage => self.diff_view,
+        }
+    }
+
+    /// Switch the Manage diff preview between the patch and its `--stat` summary
+    fn toggle_stat(&mut self) {
+        self.diff_view.stat = !self.diff_view.stat;
+        // Lines of the patch and the summary don't correspond; start over at the top
+        self.diff_scroll = 0;
+        self.diff_search.current = None;
+        self.jump_to_stash_file();
+    }
+
+    /// Switch between unified and side-by-side previews, keeping the same part in view
+    fn toggle_side_by_side(&mut self) {
+        let side_by_side = !self.diff_view.side_by_side;
+        let convert = |text: &str, scroll: u16| {
+            let scroll = scroll as usize;
+            let converted = if side_by_side {
+                side_by_side::row_of_line(text, scroll)
+            } else {
+                side_by_side::line_of_row(text, scroll)
+            };
+            converted.min(u16::MAX as usize) as u16
+        };
+        self.create_diff_scroll = convert(&self.create_diff_content, self.create_diff_scroll);
+        if !self.diff_view.stat {
+            let manage_text = match self.dropped_list_state {
+                Some(ref dropped_list_state) => &dropped_list_state.preview,
+                None => &self.diff_content,
+            };
+            self.diff_scroll = convert(manage_text, self.diff_scroll);
+        }
+        self.diff_view.side_by_side = side_by_side;
+    }
+
+    /// Lines of diff that fit in the preview panel (at least 1)
+    fn diff_page_height(&self) -> isize {
+        self.diff_area.height.saturating_sub(2).max(1) as isize
+    }
+
+    /// Lines (or rows, side by side) in the active tab's diff preview
+    fn diff_len(&self) -> usize {
+        if self.active_diff_view().side_by_side {
+            side_by_side::row_count(self.diff_text())
+        } else {
+            self.diff_text().lines().count()
+        }
+    }
+
+    /// Scroll the active tab's diff preview so `line` is at the top, stopping at the last line
+    fn scroll_diff_to(&mut self, line: usize) {
+        let last = self.diff_len().saturating_sub(1);
+        let line = line.min(last).min(u16::MAX as usize) as u16;
+        match self.selected_tab {
+            SelectedTab::Create => self.create_diff_scroll = line,
+            SelectedTab::Manage => self.diff_scroll = line,
+        }
+    }
+
+    /// Scroll the active tab's diff preview by some lines, negative for up
+    fn scroll_diff_by(&mut self, lines: isize) {
+        let current = match self.selected_tab {
+            SelectedTab::Create => self.create_diff_scroll,
+            SelectedTab::Manage => self.diff_scroll,
+        };
+        self.scroll_diff_to((current as isize + lines).max(0) as usize);
+    }
+
+    /// Ask for the text to find in the diff preview, starting from the last one
+    fn open_diff_search(&mut self) {
+        self.open_input(InputPurpose::DiffSearch);
+        self.message_input = MessageInputState::with_value(&self.diff_search.query);
+    }
+
+    /// Search the diff preview for the entered text and jump to the first match in view or below
+    fn search_diff(&mut self) {
+        self.show_message_input = false;
+        self.diff_search = DiffSearch { query: self.message_input.value().to_string(), current: None };
+        self.message_input = MessageInputState::new();
+        if !self.diff_search.query.is_empty() {
+            self.jump_to_match(true);
+        }
+    }
+
+    /// Jump to the next (or previous) search match, wrapping around; without a current match,
+    /// start from the top of the panel
+    fn jump_to_match(&mut self, forward: bool) {
+        if self.diff_search.query.is_empty() {
+            self.status_message = Some(format!(
+                "No search yet. Press {} to search the diff.",
+                self.config.keymap.key_label(Action::Search)
+            ));
+            return;
+        }
+        let matches = self.diff_search.matches(self.diff_text());
+        if matches.is_empty() {
+            self.diff_search.current = None;
+            self.status_message = Some(format!("No matches for '{}'", self.diff_search.query));
+            return;
+        }
+
+        let count = matches.len();
+        let index = match self.diff_search.current.as_ref().and_then(|c| matches.iter().position(|m| m == c)) {
+            Some(i) if forward => (i + 1) % count,
+            Some(i) => (i + count - 1) % count,
+            None => {
+                let top = self.diff_top_line();
+                if forward {
+                    matches.iter().position(|m| m.line >= top).unwrap_or(0)
+                } else {
+                    matches.iter().rposition(|m| m.line < top).unwrap_or(count - 1)
+                }
+            }
+        };
+        self.status_message = Some(format!("Match {} of {} for '{}'", index + 1, count, self.diff_search.query));
+        self.scroll_to_match(&matches[index]);
+        self.diff_search.current = Some(matches[index].clone());
+    }
+
+    /// The diff line at the top of the active tab's preview
+    fn diff_top_line(&self) -> usize {
+        let scroll = match self.selected_tab {
+            SelectedTab::Create => self.create_diff_scroll,
+            SelectedTab::Manage => self.diff_scroll,
+        } as usize;
+        if self.active_diff_view().side_by_side {
+            side_by_side::line_of_row(self.diff_text(), scroll)
+        } else {
+            scroll
+        }
+    }
+
+    /// Scroll the active tab's preview just enough to show a match, down to a third of the
+    /// panel when it is off screen
+    fn scroll_to_match(&mut self, found: &Match) {
+        let view = self.active_diff_view();
+        let text = self.diff_text();
+        let row = if view.side_by_side { side_by_side::row_of_line(text, found.line) } else { found.line };
+        let line = text.lines().nth(found.line).unwrap_or_default();
+        let mut column = line[..found.range.start].chars().count();
+        let length = line[found.range.clone()].chars().count();
+
+        // Columns of text on screen: the panel less its borders, or one side less the gutter
+        let inner = self.diff_area.width.saturating_sub(2) as usize;
+        let width = if view.side_by_side {
+            column = column.saturating_sub(1);
+            (inner.saturating_sub(1) / 2).saturating_sub(6)
+        } else {
+            inner
+        };
+
+        let height = self.diff_page_height() as usize;
+        let top = match self.selected_tab {
+            SelectedTab::Create => self.create_diff_scroll,
+            SelectedTab::Manage => self.diff_scroll,
+        } as usize;
+        if row < top || row >= top + height {
+            self.scroll_diff_to(row.saturating_sub(height / 3));
+        }
+        let hscroll = self.diff_view.hscroll as usize;
+        if !view.wrap && width > 0 && (column < hscroll || column + length > hscroll + width) {
+            let step = HORIZONTAL_SCROLL_COLUMNS as usize;
+            let start = (column + length).saturating_sub(width).max(column.saturating_sub(step));
+            self.diff_view.hscroll = (start / step * step).min(u16::MAX as usize) as u16;
+        }
+    }
+
     /// Update the diff preview for the currently selected stash
     fn update_diff_preview(&mut self) {
         self.diff_scroll = 0;
-        if let Some(selected) = self.stash_list_state.selected()
+        self.diff_search.current = None;
+        // The file list and comparison belong to the stash that was selected
+        self.stash_file_list_state = None;
+        self.comparison = None;
+        self.diff_summary = None;
+        if let Some(selected) = self.selected_stash()
             && let Some(stash) = self.stashes.get(selected)
         {
-            self.diff_content = Self::get_stash_diff(&self.repo, stash.oid);
+            self.diff_loader.request(stash.oid, self.against_current);
+            self.diff_content = LOADING_DIFF.to_string();
+            self.diff_stat = LOADING_DIFF.to_string();
+            self.diff_loading = true;
+        } else {
+            self.cancel_diff_load();
+            self.diff_content = String::new();
+            self.diff_stat = String::new();
+        }
+    }
+
+    /// Show the background diff once it is ready
+    fn receive_loaded_diff(&mut self) {
+        if self.diff_loading
+            && let Some(loaded) = self.diff_loader.try_recv()
+        {
+            self.diff_content = loaded.diff;
+            self.diff_summary = loaded.summary;
+            self.diff_stat = loaded.stat;
+            self.diff_loading = false;
+        }
+    }
+
+    /// Stop waiting for the background diff, e.g. when something else takes the panel
+    fn cancel_diff_load(&mut self) {
+        self.diff_loader.cancel();
+        self.diff_loading = false;
+    }
+
+    /// Build the selected stash's diff right away, for views that need its exact lines
+    fn finish_diff_load(&mut self) {
+        if !self.diff_loading {
+            return;
+        }
+        self.cancel_diff_load();
+        if let Some(selected) = self.selected_stash()
+            && let Some(stash) = self.stashes.get(selected)
+        {
+            if self.against_current {
+                (self.diff_content, self.diff_stat, self.diff_summary) = Self::get_diff_against_current(&self.repo, stash.oid);
+            } else {
+                self.diff_content = Self::get_stash_diff(&self.repo, stash.oid);
+                self.diff_summary = Self::stash_summary(&self.repo, stash.oid).ok();
+                self.diff_stat = Self::get_stash_stat(&self.repo, stash.oid);
+            }
+        }
+    }
+
+    /// Switch the Manage preview between the stash's own diff and what applying it now would
+    /// change, keeping the file list open
+    fn toggle_against_current(&mut self) {
+        self.against_current = !self.against_current;
+        let file_list = self.stash_file_list_state.take();
+        self.update_diff_preview();
+        if let Some(mut file_list) = file_list {
+            self.finish_diff_load();
+            Self::locate_stash_files(&mut file_list.files, &self.diff_content);
+            self.stash_file_list_state = Some(file_list);
+            self.jump_to_stash_file();
+        }
+    }
+
+    /// Find each file's `diff --git` header in the diff preview
+    fn locate_stash_files(files: &mut [StashFile], diff: &str) {
+        for file in files {
+            let suffix = format!(" b/{}", file.path);
+            file.line = diff.lines().position(|l| l.starts_with("diff --git ") && l.ends_with(&suffix));
+        }
+    }
+
+    /// Title of the Manage tab's diff panel
+    fn manage_diff_title(&self) -> String {
+        match self.comparison {
+            Some((from, to)) => format!("Compare stash@{{{}}} → stash@{{{}}}", from, to),
+            None if self.against_current => "Changes If Applied Now".to_string(),
+            None => match self.selected_stash().and_then(|i| self.stashes.get(i)) {
+                Some(stash) => match stash.committer {
+                    Some(ref committer) => format!("Diff Preview — by {}, committed by {}", stash.author, committer),
+                    None => format!("Diff Preview — by {}", stash.author),
+                },
+                None => "Diff Preview".to_string(),
+            },
+        }
+    }
+
+    /// Position in `stashes` (which is also the stash index) of the highlighted list row
+    fn selected_stash(&self) -> Option<usize> {
+        match self.stash_list_state.selected().and_then(|row| self.stash_rows.get(row)) {
+            Some(&StashRow::Stash(index)) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// The branch whose heading is highlighted, while grouped by branch
+    fn selected_branch_heading(&self) -> Option<&str> {
+        match self.stash_list_state.selected().and_then(|row| self.stash_rows.get(row)) {
+            Some(StashRow::Branch { name, .. }) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Switch to the Manage tab with the stash just created highlighted and its diff loading,
+    /// clearing a filter or expanding a branch that hides it
+    fn show_created_stash(&mut self) {
+        let Some(index) = self.created_stash.and_then(|oid| self.stashes.iter().position(|s| s.oid == oid)) else {
+            self.status_message = Some(match self.created_stash {
+                Some(_) => "The stash created here is gone".to_string(),
+                None => "No stash created yet in this session".to_string(),
+            });
+            return;
+        };
+        // Leave any view on top of the list, so the stash shows as itself
+        self.dropped_list_state = None;
+        self.against_current = false;
+        self.switch_tab(SelectedTab::Manage);
+        self.highlight_stash(index);
+    }
+
+    /// Highlight stash@{index} in the Manage list and load its diff, clearing a filter or
+    /// expanding a branch that hides it
+    fn highlight_stash(&mut self, index: usize) {
+        if !self.visible_stashes.contains(&index) {
+            self.stash_filter.clear();
+        }
+        self.collapsed_branches.remove(&self.stashes[index].branch);
+        self.refresh_visible_stashes(Some(index));
+    }
+
+    /// Jump to stash@{index}, if there is one
+    fn jump_to_stash(&mut self, index: usize) {
+        if index < self.stashes.len() {
+            self.highlight_stash(index);
+        } else {
+            self.status_message = Some(match self.stashes.len() {
+                0 => "There are no stashes".to_string(),
+                count => format!("There is no stash@{{{}}}; the last is stash@{{{}}}", index, count - 1),
+            });
+        }
+    }
+
+    /// Jump to the stash index typed in the popup
+    fn goto_stash(&mut self) {
+        let input = self.message_input.value().trim().to_string();
+        // Accept what `git stash list` prints as well as a plain number
+        let number = input.strip_prefix("stash@{").and_then(|rest| rest.strip_suffix('}')).unwrap_or(&input);
+        let Ok(index) = number.parse::<usize>() else {
+            self.status_message = Some(format!("Please enter a stash index ('{}' is not one)", input));
+            return;
+        };
+        self.show_message_input = false;
+        self.message_input = MessageInputState::new();
+        self.jump_to_stash(index);
+    }
+
+    /// Collapse or expand a branch's stashes, keeping its heading highlighted
+    fn toggle_collapsed(&mut self, branch: String) {
+        if !self.collapsed_branches.remove(&branch) {
+            self.collapsed_branches.insert(branch.clone());
+        }
+        self.refresh_visible_stashes(None);
+        let row = self.stash_rows.iter().position(|r| matches!(r, StashRow::Branch { name, .. } if *name == branch));
+        self.stash_list_state.select(row);
+        self.update_diff_preview();
+    }
+
+    /// Lay the visible stashes out as rows, under a heading per branch when grouped. Branches
+    /// come in the order of their first stash, so the sort order still counts.
+    fn build_stash_rows(&self) -> Vec<StashRow> {
+        if !self.group_by_branch {
+            return self.visible_stashes.iter().map(|&i| StashRow::Stash(i)).collect();
+        }
+        let mut branches: Vec<&str> = Vec::new();
+        for &i in &self.visible_stashes {
+            if !branches.contains(&self.stashes[i].branch.as_str()) {
+                branches.push(&self.stashes[i].branch);
+            }
+        }
+
+        let mut rows = Vec::new();
+        for branch in branches {
+            // Names like `(no branch)` or `unknown` were never branches
+            let gone = !branch.starts_with('(')
+                && branch != "unknown"
+                && self.repo.find_branch(branch, git2::BranchType::Local).is_err();
+            rows.push(StashRow::Branch { name: branch.to_string(), gone });
+            if !self.collapsed_branches.contains(branch) {
+                rows.extend(
+                    self.visible_stashes.iter().filter(|&&i| self.stashes[i].branch == branch).map(|&i| StashRow::Stash(i)),
+                );
+            }
+        }
+        rows
+    }
+
+    /// Paths touched by a stash, including untracked files it stored
+    fn stash_paths(repo: &git2::Repository, stash_oid: git2::Oid) -> Result<Vec<String>, git2::Error> {
+        Ok(Self::stash_files(repo, stash_oid)?.into_iter().map(|f| f.path).collect())
+    }
+
+    /// The files a stash touches, in diff order, untracked files last
+    fn stash_files(repo: &git2::Repository, stash_oid: git2::Oid) -> Result<Vec<StashFile>, git2::Error> {
+        let stash_commit = repo.find_commit(stash_oid)?;
+        let parent_tree = stash_commit.parent(0)?.tree()?;
+        let mut diffs = vec![repo.diff_tree_to_tree(Some(&parent_tree), Some(&stash_commit.tree()?), None)?];
+        if stash_commit.parent_count() > 2 {
+            let untracked_tree = stash_commit.parent(2)?.tree()?;
+            diffs.push(repo.diff_tree_to_tree(None, Some(&untracked_tree), None)?);
+        }
+
+        Ok(diffs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, diff)| diff.deltas().map(move |delta| (i > 0, delta)))
+            .filter_map(|(untracked, delta)| {
+                let path = delta.new_file().path().or(delta.old_file().path())?;
+                Some(StashFile {
+                    path: path.to_string_lossy().into_owned(),
+                    status: match delta.status() {
+                        git2::Delta::Added => 'A',
+                        git2::Delta::Deleted => 'D',
+                        git2::Delta::Renamed => 'R',
+                        git2::Delta::Typechange => 'T',
+                        _ => 'M',
+                    },
+                    untracked,
+                    marked: false,
+                    line: None,
+                })
+            })
+            .collect())
+    }
+
+    /// Recompute which stashes the filter lets through and keep `prefer` (a position in
+    /// `stashes`) highlighted if visible, else the next visible stash after it
+    fn refresh_visible_stashes(&mut self, prefer: Option<usize>) {
+        let filter = self.stash_filter.to_lowercase();
+        if !filter.is_empty() {
+            for stash in self.stashes.iter_mut().filter(|s| s.paths.is_none()) {
+                stash.paths = Some(Self::stash_paths(&self.repo, stash.oid).unwrap_or_default());
+            }
+        }
+
+        self.visible_stashes = self
+            .stashes
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| {
+                filter.is_empty()
+                    || s.message.to_lowercase().contains(&filter)
+                    || s.branch.to_lowercase().contains(&filter)
+                    || s.paths.iter().flatten().any(|p| p.to_lowercase().contains(&filter))
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        if self.stash_sort == StashSort::Size {
+            for stash in self.stashes.iter_mut().filter(|s| s.size.is_none()) {
+                stash.size = Some(Self::stash_summary(&self.repo, stash.oid).map_or(0, |s| s.insertions + s.deletions));
+            }
+        }
+        // Ties keep index order (the sort is stable)
+        let stashes = &self.stashes;
+        match self.stash_sort {
+            StashSort::Index => {}
+            StashSort::Date => self.visible_stashes.sort_by_key(|&i| std::cmp::Reverse(stashes[i].time)),
+            StashSort::Branch => self.visible_stashes.sort_by(|&a, &b| stashes[a].branch.cmp(&stashes[b].branch)),
+            StashSort::Size => self.visible_stashes.sort_by_key(|&i| std::cmp::Reverse(stashes[i].size)),
+        }
+
+        self.stash_rows = self.build_stash_rows();
+
+        // The preferred stash, else the next one in the list, else its collapsed branch
+        let stash_row = |wanted: &dyn Fn(usize) -> bool| {
+            self.stash_rows.iter().position(|r| matches!(*r, StashRow::Stash(i) if wanted(i)))
+        };
+        let row = prefer
+            .and_then(|p| {
+                stash_row(&|i| i == p)
+                    .or_else(|| {
+                        let branch = &self.stashes.get(p)?.branch;
+                        self.stash_rows.iter().position(|r| matches!(r, StashRow::Branch { name, .. } if name == branch))
+                    })
+                    .or_else(|| stash_row(&|i| i >= p))
+            })
+            .or_else(|| self.stash_rows.len().checked_sub(1));
+        self.stash_list_state.select(row);
+        self.update_diff_preview();
+    }
+
+    /// Narrow the stash list to the filter being typed
+    fn update_live_filter(&mut self) {
+        if self.input_purpose != InputPurpose::StashFilter {
+            return;
+        }
+        let selected = self.selected_stash();
+        self.stash_filter = self.message_input.value().to_string();
+        self.refresh_visible_stashes(selected);
+    }
+
+    /// Get the working directory diff for a single file
+    fn get_file_diff(repo: &git2::Repository, path: &str) -> String {
+        match Self::try_get_file_diff(repo, path, MAX_DIFF_LINES) {
+            Ok(diff) => diff,
+            Err(e) => format!("Failed to generate diff: {}", friendly_error_message(&e)),
+        }
+    }
+
+    /// Try to get the working directory diff for a single file (internal helper)
+    fn try_get_file_diff(repo: &git2::Repository, path: &str, max_lines: usize) -> Result<String, git2::Error> {
+        let mut opts = DiffOptions::new();
+        opts.pathspec(path);
+        // Untracked and ignored entries (possibly whole directories) show as new files
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true)
+            .include_ignored(true)
+            .recurse_ignored_dirs(true);
+
+        // Try workdir diff first (unstaged changes)
+        let diff = repo.diff_index_to_workdir(None, Some(&mut opts))?;
+
+        let mut diff_text = String::new();
+        let mut line_count = 0;
+
+        // If workdir diff is empty, try staged changes (index vs HEAD)
+        let diff = if diff.stats()?.files_changed() == 0 {
+            let head = repo.head()?.peel_to_tree()?;
+            repo.diff_tree_to_index(Some(&head), None, Some(&mut opts))?
+        } else {
+            diff
+        };
+
+        diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+            if line_count >= max_lines {
+                return false;
+            }
+
+            if submodules::is_submodule(&delta) {
+                if line.origin() == 'F' {
+                    let text = submodules::change_text(&delta);
+                    line_count += text.lines().count();
+                    diff_text.push_str(&text);
+                }
+                return true;
+            }
+            if line.origin() == 'B' {
+                diff_text.push_str(&binary::change_line(repo, &delta));
+                diff_text.push('\n');
+                line_count += 1;
+                return true;
+            }
+
+            let origin = line.origin();
+            if matches!(origin, ' ' | '+' | '-') {
+                diff_text.push(origin);
+            }
+            if let Ok(content) = std::str::from_utf8(line.content()) {
+                diff_text.push_str(content);
+                line_count += content.lines().count().max(1);
+            }
+            true
+        })?;
+
+        if line_count >= max_lines {
+            diff_text.push_str(&format!("\n... (diff truncated — showing first {} lines) ...", max_lines));
+        }
+
+        Ok(diff_text)
+    }
+
+    /// Update the diff preview for the currently highlighted file in Create tab
+    fn update_create_diff_preview(&mut self) {
+        self.create_diff_scroll = 0;
+        self.diff_search.current = None;
+        if let Some(ref file_list_state) = self.file_list_state
+            && let Some(selected) = file_list_state.list_state.selected()
+            && let Some(file) = file_list_state.files.get(selected)
+            && !file.status.is_empty()
+        {
+            self.create_diff_content = Self::get_file_diff(&self.repo, &file.path);
+        } else {
+            self.create_diff_content = String::new();
+        }
+    }
+
+    /// Open the hunk drill-down for the currently highlighted file
+    fn open_hunk_view(&mut self) {
+        let Some(ref file_list_state) = self.file_list_state else {
+            return;
+        };
+        let Some(file) = file_list_state
+            .list_state
+            .selected()
+            .and_then(|i| file_list_state.files.get(i))
+            .filter(|f| !f.status.is_empty())
+        else {
+            return;
+        };
+        if file.submodule {
+            self.status_message = Some(format!("{} is a submodule, which can't be stashed", file.path));
+            return;
+        }
+
+        match hunks::file_hunks(&self.repo, &file.path) {
+            Ok(file_hunks) if file_hunks.is_empty() => {
+                self.status_message = Some(format!("No hunks to select in {} (binary or mode-only change)", file.path));
+            }
+            Ok(file_hunks) => {
+                self.hunk_list_state = Some(HunkListState::new(file, file_hunks));
+                self.update_hunk_preview();
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Loading hunks failed: {}", friendly_error_message(&e)));
+            }
+        }
+    }
+
+    /// Close the hunk drill-down, keeping its choices on the file
+    fn close_hunk_view(&mut self) {
+        if let Some(hunk_list_state) = self.hunk_list_state.take()
+            && let Some(ref mut file_list_state) = self.file_list_state
+            && let Some(file) = file_list_state.files.iter_mut().find(|f| f.path == hunk_list_state.path)
+        {
+            hunk_list_state.store_into(file);
+        }
+        self.update_create_diff_preview();
+    }
+
+    /// Show the highlighted hunk in the diff preview
+    fn update_hunk_preview(&mut self) {
+        self.create_diff_scroll = 0;
+        if let Some(ref hunk_list_state) = self.hunk_list_state
+            && let Some(hunk) = hunk_list_state.list_state.selected().and_then(|i| hunk_list_state.hunks.get(i))
+        {
+            self.create_diff_content = hunk.text.clone();
+        }
+    }
+
+    /// List the selected stash's files in place of the stash list
+    fn open_stash_file_view(&mut self) {
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+        match Self::stash_files(&self.repo, self.stashes[index].oid) {
+            Ok(files) if files.is_empty() => {
+                self.status_message = Some(format!("stash@{{{}}} has no file changes", index));
+            }
+            Ok(mut files) => {
+                self.finish_diff_load();
+                Self::locate_stash_files(&mut files, &self.diff_content);
+                let mut list_state = ListState::default();
+                list_state.select(Some(0));
+                self.stash_file_list_state = Some(StashFileListState { list_state, index, files });
+                self.jump_to_stash_file();
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Listing files failed: {}", friendly_error_message(&e)));
+            }
+        }
+    }
+
+    /// Scroll the diff preview to the highlighted file's section
+    fn jump_to_stash_file(&mut self) {
+        let Some(ref stash_file_list_state) = self.stash_file_list_state else {
+            return;
+        };
+        let Some(file) = stash_file_list_state.list_state.selected().and_then(|i| stash_file_list_state.files.get(i)) else {
+            return;
+        };
+        if self.diff_view.stat {
+            // Files left out of what would be applied shift the lines; stay put
+            if self.against_current {
+                return;
+            }
+            // One line per file in the same order; untracked files come after the tracked
+            // files' totals, a blank line and their heading
+            let position = stash_file_list_state.list_state.selected().unwrap_or(0);
+            let has_tracked = stash_file_list_state.files.iter().any(|f| !f.untracked);
+            let line = match (file.untracked, has_tracked) {
+                (false, _) => position,
+                (true, true) => position + 3,
+                (true, false) => position + 1,
+            };
+            self.diff_scroll = line.min(u16::MAX as usize) as u16;
+            return;
+        }
+        match file.line {
+            Some(line) => {
+                let row = if self.diff_view.side_by_side {
+                    side_by_side::row_of_line(&self.diff_content, line)
+                } else {
+                    line
+                };
+                self.diff_scroll = row.min(u16::MAX as usize) as u16;
+            }
+            None if self.against_current => {
+                self.status_message = Some(format!("{} would not change if the stash were applied now", file.path))
+            }
+            None => self.status_message = Some(format!("{} is past the end of the truncated preview", file.path)),
+        }
+    }
+
+    /// Apply only the chosen files of the stash to the working directory, keeping the stash
+    fn apply_stash_files(&mut self) {
+        if let Err(msg) = self.validate_repository_state() {
+            self.status_message = Some(msg);
+            return;
+        }
+        let Some(ref stash_file_list_state) = self.stash_file_list_state else {
+            return;
+        };
+        let index = stash_file_list_state.index;
+        let files = stash_file_list_state.chosen_files();
+        if files.is_empty() {
+            return;
+        }
+
+        let outcome = match Self::checkout_stash_files(&self.repo, self.stashes[index].oid, &files) {
+            Ok(()) => Ok(match files.as_slice() {
+                [file] => format!("Applied {} from stash@{{{}}}", file.path, index),
+                _ => format!("Applied {} files from stash@{{{}}}", files.len(), index),
+            }),
+            Err(e) => Err(format!("Apply failed: {}", friendly_error_message(&e))),
+        };
+        self.report(outcome);
+    }
+
+    /// Check out some of a stash's files into the working directory, like
+    /// `git checkout stash@{N} -- <paths>` but leaving the index alone and refusing to
+    /// overwrite local changes
+    fn checkout_stash_files(repo: &git2::Repository, stash_oid: git2::Oid, files: &[&StashFile]) -> Result<(), git2::Error> {
+        let stash_commit = repo.find_commit(stash_oid)?;
+        let (untracked, tracked): (Vec<&StashFile>, Vec<&StashFile>) = files.iter().partition(|f| f.untracked);
+
+        let mut checkouts = vec![(stash_commit.tree()?, tracked)];
+        if !untracked.is_empty() {
+            checkouts.push((stash_commit.parent(2)?.tree()?, untracked));
+        }
+        for (tree, files) in checkouts {
+            // Without any paths a checkout would cover the whole tree
+            if files.is_empty() {
+                continue;
+            }
+            let mut checkout = CheckoutBuilder::new();
+            checkout.safe().update_index(false).disable_pathspec_match(true);
+            for file in files {
+                checkout.path(&file.path);
+            }
+            repo.checkout_tree(tree.as_object(), Some(&mut checkout))?;
+        }
+        Ok(())
+    }
+
+    /// Options for apply/pop; `restore_index` brings back staged changes as staged (`--index`)
+    fn stash_apply_options(restore_index: bool) -> StashApplyOptions<'static> {
+        let mut opts = StashApplyOptions::new();
+        if restore_index {
+            opts.reinstantiate_index();
+        }
+        opts
+    }
+
+    /// Apply or pop the selected stash, first warning about files that would conflict or
+    /// that have uncommitted changes the stash would be mixed into
+    fn start_apply(&mut self, restore_index: bool, pop: bool) {
+        if let Err(msg) = self.validate_repository_state() {
+            self.status_message = Some(msg);
+            return;
+        }
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+
+        // If the prediction itself fails, the apply still refuses to overwrite local changes
+        let oid = self.stashes[index].oid;
+        let conflicts = conflicts::predict(&self.repo, oid, restore_index).unwrap_or_default();
+        let worktree = self.stashes[index].worktree.clone();
+        let local = Self::local_changes(&self.repo);
+        let stash_paths = Self::stash_paths(&self.repo, oid).unwrap_or_default();
+        let overlapping: Vec<String> = local
+            .iter()
+            .filter(|path| stash_paths.contains(path) && !conflicts.iter().any(|c| &c.path == *path))
+            .cloned()
+            .collect();
+        if !conflicts.is_empty() || worktree.is_some() || !overlapping.is_empty() {
+            let dirty = !local.is_empty();
+            self.conflict_warning = Some(ConflictWarning { index, restore_index, pop, conflicts, worktree, dirty, overlapping });
+            return;
+        }
+        self.finish_apply(restore_index, pop);
+    }
+
+    /// Tracked files with staged or unstaged changes; untracked files and submodules are
+    /// left out since a plain stash wouldn't move them
+    fn local_changes(repo: &git2::Repository) -> Vec<String> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false).include_ignored(false).exclude_submodules(true);
+        let Ok(statuses) = repo.statuses(Some(&mut opts)) else {
+            return Vec::new();
+        };
+        statuses
+            .iter()
+            .filter(|entry| entry.status() != Status::CURRENT)
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect()
+    }
+
+    /// Save the uncommitted changes as a safety stash, then apply or pop the stash that was
+    /// at `index`, now one further down. If it would still conflict with HEAD, warn again.
+    fn stash_local_changes_then_apply(&mut self, index: usize, restore_index: bool, pop: bool) {
+        let Ok(signature) = self.repo.signature() else {
+            self.report(Err("Stash failed: git user.name/email not configured".to_string()));
+            return;
+        };
+        let message = format!("safety stash before applying stash@{{{}}}", index);
+        if let Err(e) = self.repo.stash_save2(&signature, Some(&message), Some(git2::StashFlags::DEFAULT)) {
+            self.report(Err(format!("Safety stash failed: {}", friendly_error_message(&e))));
+            return;
+        }
+        self.report(Ok("Saved your uncommitted changes as stash@{0}".to_string()));
+        self.reload_stashes_following(Some(index + 1));
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+
+        let conflicts = conflicts::predict(&self.repo, self.stashes[index].oid, restore_index).unwrap_or_default();
+        if !conflicts.is_empty() {
+            self.conflict_warning = Some(ConflictWarning {
+                index,
+                restore_index,
+                pop,
+                conflicts,
+                worktree: None,
+                dirty: false,
+                overlapping: Vec::new(),
+            });
+            return;
+        }
+        self.finish_apply(restore_index, pop);
+        if let Some(status) = &mut self.status_message {
+            status.push_str("; your own changes are in stash@{0}");
+        }
+    }
+
+    fn finish_apply(&mut self, restore_index: bool, pop: bool) {
+        if pop {
+            self.pop_stash(restore_index);
+        } else {
+            self.apply_stash(restore_index);
+        }
+    }
+
+    /// Apply the currently selected stash (keeps stash in list)
+    fn apply_stash(&mut self, restore_index: bool) {
+        // Validate repository state first
+        if let Err(msg) = self.validate_repository_state() {
+            self.status_message = Some(msg);
+            return;
+        }
+
+        // Extract the index first to avoid borrow issues
+        let selected_index = match self.selected_stash() {
+            Some(idx) => idx,
+            None => return, // No stash selected, do nothing
+        };
+
+        // Apply the stash
+        let with_index = if restore_index { " with index" } else { "" };
+        match self.repo.stash_apply(selected_index, Some(&mut Self::stash_apply_options(restore_index))) {
+            Ok(()) => {
+                self.report(Ok(format!("Applied stash@{{{}}}{} successfully", selected_index, with_index)));
+            }
+            Err(e) => {
+                self.report(Err(format!("Apply failed: {}", friendly_error_message(&e))));
+            }
+        }
+    }
+
+    /// Pop the currently selected stash (removes stash from list)
+    fn pop_stash(&mut self, restore_index: bool) {
+        // Validate repository state first
+        if let Err(msg) = self.validate_repository_state() {
+            self.status_message = Some(msg);
+            return;
+        }
+
+        // Extract the index first to avoid borrow issues
+        let selected_index = match self.selected_stash() {
+            Some(idx) => idx,
+            None => return, // No stash selected, do nothing
+        };
+
+        // Pop the stash
+        let with_index = if restore_index { " with index" } else { "" };
+        match self.repo.stash_pop(selected_index, Some(&mut Self::stash_apply_options(restore_index))) {
+            Ok(()) => {
+                self.report(Ok(format!("Popped stash@{{{}}}{} successfully", selected_index, with_index)));
+                self.reload_stashes_after_removal(selected_index);
+            }
+            Err(e) => {
+                self.report(Err(format!("Pop failed: {}", friendly_error_message(&e))));
+            }
+        }
+    }
+
+    /// Show how the selected stash differs from the one marked stash
+    fn compare_stashes(&mut self) {
+        let Some(selected) = self.selected_stash() else {
+            return;
+        };
+        let marked: Vec<usize> = self.stashes.iter().filter(|s| s.marked && s.index != selected).map(|s| s.index).collect();
+        let [other] = marked.as_slice() else {
+            self.status_message = Some(format!(
+                "Mark one stash with {}, select another, then press {} to compare",
+                self.config.keymap.key_label(Action::Toggle),
+                self.config.keymap.key_label(Action::Compare)
+            ));
+            return;
+        };
+
+        // Read from the older stash (higher index) to the newer one
+        let (from, to) = if *other > selected { (*other, selected) } else { (selected, *other) };
+        match Self::try_compare_stashes(&self.repo, self.stashes[from].oid, self.stashes[to].oid, MAX_DIFF_LINES) {
+            Ok((diff, stat, summary)) => {
+                self.update_diff_preview();
+                self.cancel_diff_load();
+                self.diff_content = diff;
+                self.diff_stat = stat;
+                self.diff_summary = Some(summary);
+                self.comparison = Some((from, to));
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Compare failed: {}", friendly_error_message(&e)));
+            }
+        }
+    }
+
+    /// Queue `git difftool` for the highlighted file of the stash file list, or for the
+    /// whole selected stash
+    fn open_difftool(&mut self) {
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+        match self.difftool_diffs(self.stashes[index].oid) {
+            Ok(diffs) if !diffs.is_empty() => self.pending_launch = Some(Launch::Difftool(diffs)),
+            Ok(_) => {}
+            Err(e) => self.status_message = Some(format!("Difftool failed: {}", friendly_error_message(&e))),
+        }
+    }
+
+    /// The commit pairs and paths to show: tracked changes against the stash's base, and
+    /// untracked files (stored in a commit of their own) by path against the same base
+    fn difftool_diffs(&self, stash_oid: git2::Oid) -> Result<Vec<(git2::Oid, git2::Oid, Vec<String>)>, git2::Error> {
+        let stash_commit = self.repo.find_commit(stash_oid)?;
+        let base = stash_commit.parent_id(0)?;
+        let untracked = stash_commit.parent_id(2).ok();
+
+        let files = match self.stash_file_list_state {
+            Some(ref stash_file_list_state) => stash_file_list_state
+                .list_state
+                .selected()
+                .and_then(|i| stash_file_list_state.files.get(i))
+                .into_iter()
+                .cloned()
+                .collect(),
+            None => Self::stash_files(&self.repo, stash_oid)?,
+        };
+        let (untracked_files, tracked_files): (Vec<StashFile>, Vec<StashFile>) = files.into_iter().partition(|f| f.untracked);
+
+        let mut diffs = Vec::new();
+        if !tracked_files.is_empty() {
+            let paths = match self.stash_file_list_state {
+                Some(_) => tracked_files.into_iter().map(|f| f.path).collect(),
+                None => Vec::new(),
+            };
+            diffs.push((base, stash_oid, paths));
+        }
+        if let Some(untracked) = untracked
+            && !untracked_files.is_empty()
+        {
+            diffs.push((base, untracked, untracked_files.into_iter().map(|f| f.path).collect()));
+        }
+        Ok(diffs)
+    }
+
+    /// Write the selected stash's patch to the path entered in the popup
+    fn export_stash(&mut self) {
+        let input = self.message_input.value().trim().to_string();
+        if input.is_empty() {
+            self.status_message = Some("Please enter a file name for the patch".to_string());
+            return;
+        }
+        self.show_message_input = false;
+        self.message_input = MessageInputState::new();
+
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+        let Some(oid) = self.stashes.get(index).map(|s| s.oid) else {
+            return;
+        };
+
+        // Relative paths land in the repository root rather than wherever we were started
+        let path = match self.repo.workdir() {
+            Some(workdir) => workdir.join(&input),
+            None => std::path::PathBuf::from(&input),
+        };
+        if path.exists() {
+            self.report(Err(format!("Export failed: {} already exists", path.display())));
+            return;
+        }
+
+        let outcome = match Self::stash_patch(&self.repo, oid) {
+            Ok(patch) => match std::fs::write(&path, patch) {
+                Ok(()) => Ok(format!("Exported stash@{{{}}} to {}", index, path.display())),
+                Err(e) => Err(format!("Export failed: {}: {}", path.display(), e)),
+            },
+            Err(e) => Err(format!("Export failed: {}", friendly_error_message(&e))),
+        };
+        self.report(outcome);
+    }
+
+    /// Write every stash to the folder entered in the popup
+    fn backup_stashes(&mut self) {
+        let input = self.message_input.value().trim().to_string();
+        if input.is_empty() {
+            self.status_message = Some("Please enter a folder for the backup".to_string());
+            return;
+        }
+        self.show_message_input = false;
+        self.message_input = MessageInputState::new();
+
+        let dir = match self.repo.workdir() {
+            Some(workdir) => workdir.join(&input),
+            None => std::path::PathBuf::from(&input),
+        };
+        let outcome = match backup::write(&mut self.repo, &dir) {
+            Ok(count) => Ok(format!("Backed up {} stash(es) to {}", count, dir.display())),
+            Err(e) => Err(format!("Backup failed: {}", e)),
+        };
+        self.report(outcome);
+    }
+
+    /// Give the selected stash the message typed in the popup
+    fn rename_stash(&mut self) {
+        let input = self.message_input.value().trim().to_string();
+        if input.is_empty() {
+            self.status_message = Some("Please enter a stash message".to_string());
+            return;
+        }
+        self.show_message_input = false;
+        self.message_input = MessageInputState::new();
+
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+        // Keep the "On branch: " prefix git uses, so the branch still shows in the list
+        let message = match self.stashes[index].branch.as_str() {
+            "unknown" => input,
+            branch => format!("On {}: {}", branch, input),
+        };
+        match Self::try_rename_stash(&self.repo, index, &message) {
+            Ok(()) => {
+                self.stashes = Self::load_stashes(&mut self.repo);
+                self.refresh_visible_stashes(Some(index));
+                self.report(Ok(format!("Renamed stash@{{{}}} to '{}'", index, message)));
+            }
+            Err(e) => {
+                self.report(Err(format!("Rename failed: {}", friendly_error_message(&e))));
+            }
+        }
+    }
+
+    /// Replace stash@{index} with a copy of its commit carrying a new message. The stash
+    /// list is the reflog of `refs/stash`, so the reflog is rewritten with the copy in place.
+    fn try_rename_stash(repo: &git2::Repository, index: usize, message: &str) -> Result<(), git2::Error> {
+        let reflog = repo.reflog("refs/stash")?;
+        let entry = reflog.get(index).ok_or_else(|| git2::Error::from_str("stash not found"))?;
+        let old = repo.find_commit(entry.id_new())?;
+        let parents: Vec<git2::Commit> = old.parents().collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        let renamed = repo.commit(None, &old.author(), &old.committer(), message, &old.tree()?, &parents)?;
+
+        // Newest first, as the reflog lists them
+        let entries: Vec<(git2::Oid, git2::Signature<'static>, String)> = reflog
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let (oid, message) = if i == index {
+                    (renamed, message.to_string())
+                } else {
+                    (e.id_new(), e.message().unwrap_or_default().to_string())
+                };
+                (oid, e.committer().to_owned(), message)
+            })
+            .collect();
+
+        // Moving the ref logs an extra entry, which the rewrite below discards
+        if index == 0 {
+            repo.reference("refs/stash", renamed, true, message)?;
         }
+        let mut reflog = repo.reflog("refs/stash")?;
+        while !reflog.is_empty() {
+            reflog.remove(0, false)?;
+        }
+        for (oid, committer, message) in entries.iter().rev() {
+            reflog.append(*oid, committer, Some(message))?;
+        }
+        reflog.write()
     }
 
-    /// Get the working directory diff for a single file
-    fn get_file_diff(repo: &git2::Repository, path: &str) -> String {
-        match Self::try_get_file_diff(repo, path, MAX_DIFF_LINES) {
-            Ok(diff) => diff,
-            Err(e) => format!("Failed to generate diff: {}", friendly_error_message(&e)),
-        }
+    /// Reload the stash list after the stash at `index` was removed and fix up the selection
+    fn reload_stashes_after_removal(&mut self, index: usize) {
+        // If the highlighted stash is gone, the next one slides into its index
+        self.reload_stashes_following(Some(index));
     }
 
-    /// Try to get the working directory diff for a single file (internal helper)
-    fn try_get_file_diff(repo: &git2::Repository, path: &str, max_lines: usize) -> Result<String, git2::Error> {
-        let mut opts = DiffOptions::new();
-        opts.pathspec(path);
+    /// Reload the stash list after it changed here, keeping the highlight and marks on the
+    /// same stashes even though their indices shifted; `fallback` is highlighted instead
+    /// when the highlighted stash is gone
+    fn reload_stashes_following(&mut self, fallback: Option<usize>) {
+        let selected = self.selected_stash().map(|i| self.stashes[i].oid);
+        let marked: Vec<git2::Oid> = self.stashes.iter().filter(|s| s.marked).map(|s| s.oid).collect();
+        self.stashes = Self::load_stashes(&mut self.repo);
+        for stash in &mut self.stashes {
+            stash.marked = marked.contains(&stash.oid);
+        }
+        let prefer = selected.and_then(|oid| self.stashes.iter().position(|s| s.oid == oid)).or(fallback);
+        self.refresh_visible_stashes(prefer);
+    }
 
-        // Try workdir diff first (unstaged changes)
-        let diff = repo.diff_index_to_workdir(None, Some(&mut opts))?;
+    /// Create a branch from the selected stash, like `git stash branch <name> stash@{n}`
+    fn branch_from_stash(&mut self) {
+        let name = self.message_input.value().trim().to_string();
+        if name.is_empty() {
+            self.status_message = Some("Please enter a branch name".to_string());
+            return;
+        }
+        if !git2::Branch::name_is_valid(&name).unwrap_or(false) {
+            self.status_message = Some(format!("Please enter a valid branch name ('{}' is not)", name));
+            return;
+        }
 
-        let mut diff_text = String::new();
-        let mut line_count = 0;
+        self.show_message_input = false;
+        self.message_input = MessageInputState::new();
 
-        // If workdir diff is empty, try staged changes (index vs HEAD)
-        let diff = if diff.stats()?.files_changed() == 0 {
-            let head = repo.head()?.peel_to_tree()?;
-            repo.diff_tree_to_index(Some(&head), None, Some(&mut opts))?
-        } else {
-            diff
+        if let Err(msg) = self.validate_repository_state() {
+            self.status_message = Some(msg);
+            return;
+        }
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+        let Some(oid) = self.stashes.get(index).map(|s| s.oid) else {
+            return;
         };
 
-        diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
-            if line_count >= max_lines {
-                return false;
+        let outcome = match Self::try_branch_from_stash(&mut self.repo, index, oid, &name) {
+            Ok(()) => Ok(format!("Created branch '{}' from stash@{{{}}} and dropped the stash", name, index)),
+            Err(BranchFromStashError::Branch(e)) => {
+                Err(format!("Branch creation failed: {}", friendly_error_message(&e)))
             }
+            Err(BranchFromStashError::Apply(e)) => Err(format!(
+                "Switched to '{}', but applying stash@{{{}}} failed (stash kept): {}",
+                name,
+                index,
+                friendly_error_message(&e)
+            )),
+            Err(BranchFromStashError::Drop(e)) => Err(format!(
+                "Applied stash@{{{}}} on '{}', but dropping it failed: {}",
+                index,
+                name,
+                friendly_error_message(&e)
+            )),
+        };
+        self.report(outcome);
 
-            let origin = line.origin();
-            if matches!(origin, ' ' | '+' | '-' | 'B') {
-                diff_text.push(origin);
-            }
-            if let Ok(content) = std::str::from_utf8(line.content()) {
-                diff_text.push_str(content);
-                line_count += content.lines().count().max(1);
+        self.reload_stashes_after_removal(index);
+        self.refresh_file_list();
+    }
+
+    /// Create the branch at the stash's base commit, check it out, apply the stash with its
+    /// index and drop it. Nothing is applied unless the checkout succeeded.
+    fn try_branch_from_stash(
+        repo: &mut git2::Repository,
+        index: usize,
+        stash_oid: git2::Oid,
+        name: &str,
+    ) -> Result<(), BranchFromStashError> {
+        {
+            let base = repo
+                .find_commit(stash_oid)
+                .and_then(|stash| stash.parent(0))
+                .map_err(BranchFromStashError::Branch)?;
+            let mut branch = repo.branch(name, &base, false).map_err(BranchFromStashError::Branch)?;
+
+            // Safe checkout refuses to overwrite local changes; undo the branch if it does
+            let checkout = repo
+                .checkout_tree(base.as_object(), Some(CheckoutBuilder::new().safe()))
+                .and_then(|()| repo.set_head(&format!("refs/heads/{}", name)));
+            if let Err(e) = checkout {
+                let _ = branch.delete();
+                return Err(BranchFromStashError::Branch(e));
             }
-            true
-        })?;
+        }
 
-        if line_count >= max_lines {
-            diff_text.push_str(&format!("\n... (diff truncated — showing first {} lines) ...", max_lines));
+        repo.stash_apply(index, Some(&mut Self::stash_apply_options(true))).map_err(BranchFromStashError::Apply)?;
+        repo.stash_drop(index).map_err(BranchFromStashError::Drop)
+    }
+
+    /// Apply the selected stash in a new worktree at the path entered in the popup
+    fn stash_worktree(&mut self) {
+        let input = self.message_input.value().trim().to_string();
+        if input.is_empty() {
+            self.status_message = Some("Please enter a folder for the worktree".to_string());
+            return;
         }
+        self.show_message_input = false;
+        self.message_input = MessageInputState::new();
 
-        Ok(diff_text)
+        let Some(index) = self.selected_stash() else {
+            return;
+        };
+        let oid = self.stashes[index].oid;
+        let path = match self.repo.workdir() {
+            Some(workdir) => workdir.join(&input),
+            None => std::path::PathBuf::from(&input),
+        };
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            self.report(Err(format!("Worktree failed: '{}' has no folder name", input)));
+            return;
+        };
+        if path.exists() {
+            self.report(Err(format!("Worktree failed: {} already exists", path.display())));
+            return;
+        }
+
+        let outcome = match Self::try_stash_worktree(&self.repo, index, oid, &path, &name) {
+            Ok(()) => Ok(format!("Applied stash@{{{}}} in new worktree {} on branch '{}'", index, path.display(), name)),
+            Err(StashWorktreeError::Create(e)) => Err(format!("Worktree failed: {}", friendly_error_message(&e))),
+            Err(StashWorktreeError::Apply(e)) => Err(format!(
+                "Created worktree {} on '{}', but applying stash@{{{}}} there failed: {}",
+                path.display(),
+                name,
+                index,
+                friendly_error_message(&e)
+            )),
+        };
+        self.report(outcome);
+        // The new branch is checked out in the new worktree now
+        self.stashes = Self::load_stashes(&mut self.repo);
+        self.refresh_visible_stashes(Some(index));
     }
 
-    /// Update the diff preview for the currently highlighted file in Create tab
-    fn update_create_diff_preview(&mut self) {
-        self.create_diff_scroll = 0;
-        if let Some(ref file_list_state) = self.file_list_state
-            && let Some(selected) = file_list_state.list_state.selected()
-            && let Some(file) = file_list_state.files.get(selected)
-            && !file.status.is_empty()
-        {
-            self.create_diff_content = Self::get_file_diff(&self.repo, &file.path);
-        } else {
-            self.create_diff_content = String::new();
+    /// Create branch `name` at the stash's base commit, check it out in a new worktree at
+    /// `path`, and apply the stash there with its index, like `git stash branch` would
+    fn try_stash_worktree(
+        repo: &git2::Repository,
+        index: usize,
+        stash_oid: git2::Oid,
+        path: &std::path::Path,
+        name: &str,
+    ) -> Result<(), StashWorktreeError> {
+        let base = repo.find_commit(stash_oid).and_then(|stash| stash.parent(0)).map_err(StashWorktreeError::Create)?;
+        let mut branch = repo.branch(name, &base, false).map_err(StashWorktreeError::Create)?;
+        let mut opts = git2::WorktreeAddOptions::new();
+        opts.reference(Some(branch.get()));
+        let worktree = match repo.worktree(name, path, Some(&opts)) {
+            Ok(worktree) => worktree,
+            Err(e) => {
+                let _ = branch.delete();
+                return Err(StashWorktreeError::Create(e));
+            }
+        };
+
+        // The stash list is shared, so the stash has the same index in the new worktree
+        let mut worktree_repo = git2::Repository::open_from_worktree(&worktree).map_err(StashWorktreeError::Apply)?;
+        worktree_repo
+            .stash_apply(index, Some(&mut Self::stash_apply_options(true)))
+            .map_err(StashWorktreeError::Apply)
+    }
+
+    /// Keep the commit message and ask which branch to commit to, offering the current one
+    fn choose_commit_branch(&mut self) {
+        let message = self.message_input.value().trim().to_string();
+        if message.is_empty() {
+            self.status_message = Some("Please enter a commit message".to_string());
+            return;
         }
+        self.commit_message = message;
+        let current = match self.repo.head() {
+            Ok(head) if head.is_branch() => head.shorthand().unwrap_or_default().to_string(),
+            _ => String::new(),
+        };
+        self.open_input(InputPurpose::CommitBranch);
+        self.message_input = MessageInputState::with_value(&current);
     }
 
-    /// Apply the currently selected stash (keeps stash in list)
-    fn apply_stash(&mut self) {
-        // Validate repository state first
+    /// Commit the selected stash to the branch entered in the popup, then offer to drop it
+    fn commit_stash(&mut self) {
+        let name = self.message_input.value().trim().to_string();
+        let current = self.repo.head().ok().filter(|h| h.is_branch()).and_then(|h| h.shorthand().map(str::to_string));
+        let new_branch = match current {
+            Some(ref current) if *current == name => None,
+            _ if name.is_empty() => {
+                self.status_message = Some("HEAD is detached; please enter a name for a new branch".to_string());
+                return;
+            }
+            _ if !git2::Branch::name_is_valid(&name).unwrap_or(false) => {
+                self.status_message = Some(format!("Please enter a valid branch name ('{}' is not)", name));
+                return;
+            }
+            _ => Some(name.as_str()),
+        };
+
+        self.show_message_input = false;
+        self.message_input = MessageInputState::new();
+
         if let Err(msg) = self.validate_repository_state() {
             self.status_message = Some(msg);
             return;
         }
-
-        // Extract the index first to avoid borrow issues
-        let selected_index = match self.stash_list_state.selected() {
-            Some(idx) => idx,
-            None => return, // No stash selected, do nothing
+        let Some(index) = self.selected_stash() else {
+            return;
         };
 
-        // Apply the stash
-        match self.repo.stash_apply(selected_index, Some(&mut StashApplyOptions::new())) {
-            Ok(()) => {
-                self.status_message = Some(format!("Applied stash@{{{}}} successfully", selected_index));
+        match Self::try_commit_stash(&self.repo, self.stashes[index].oid, &self.commit_message, new_branch) {
+            Ok(commit) => {
+                let short = commit.to_string()[..7].to_string();
+                self.report(Ok(match new_branch {
+                    Some(branch) => format!("Committed stash@{{{}}} as {} on new branch '{}'", index, short, branch),
+                    None => format!("Committed stash@{{{}}} as {} on '{}'", index, short, name),
+                }));
+                self.refresh_file_list();
+                // The stash is still there; offer to drop it
+                self.show_confirm_popup = true;
+                self.confirm_stashes = vec![self.stashes[index].oid];
             }
             Err(e) => {
-                self.status_message = Some(format!("Apply failed: {}", friendly_error_message(&e)));
+                self.report(Err(format!("Commit failed: {}", friendly_error_message(&e))));
             }
         }
     }
 
-    /// Pop the currently selected stash (removes stash from list)
-    fn pop_stash(&mut self) {
-        // Validate repository state first
-        if let Err(msg) = self.validate_repository_state() {
-            self.status_message = Some(msg);
-            return;
+    /// Commit a stash's changes, untracked files included. On a new branch the commit goes
+    /// on the stash's base commit as is; on the current branch the changes are merged onto
+    /// HEAD, and the index and working directory are updated before the branch moves, so
+    /// nothing changes if that would conflict or overwrite local changes.
+    fn try_commit_stash(
+        repo: &git2::Repository,
+        stash_oid: git2::Oid,
+        message: &str,
+        new_branch: Option<&str>,
+    ) -> Result<git2::Oid, git2::Error> {
+        let signature = repo.signature()?;
+        let stash = repo.find_commit(stash_oid)?;
+        let base = stash.parent(0)?;
+
+        let mut tree = stash.tree()?;
+        if let Ok(untracked) = stash.parent(2) {
+            // Against an empty ancestor both sides only add files, so this is a union
+            let empty = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+            let mut merged = repo.merge_trees(&empty, &tree, &untracked.tree()?, None)?;
+            tree = repo.find_tree(merged.write_tree_to(repo)?)?;
         }
 
-        // Extract the index first to avoid borrow issues
-        let selected_index = match self.stash_list_state.selected() {
-            Some(idx) => idx,
-            None => return, // No stash selected, do nothing
-        };
+        if let Some(name) = new_branch {
+            let commit = repo.commit(None, &signature, &signature, message, &tree, &[&base])?;
+            repo.branch(name, &repo.find_commit(commit)?, false)?;
+            return Ok(commit);
+        }
 
-        // Pop the stash
-        match self.repo.stash_pop(selected_index, Some(&mut StashApplyOptions::new())) {
-            Ok(()) => {
-                self.status_message = Some(format!("Popped stash@{{{}}} successfully", selected_index));
+        let mut head = repo.head()?;
+        let head_commit = head.peel_to_commit()?;
+        if head_commit.id() != base.id() {
+            let mut merged = repo.merge_trees(&base.tree()?, &head_commit.tree()?, &tree, None)?;
+            if merged.has_conflicts() {
+                let count = merged.conflicts()?.count();
+                return Err(git2::Error::from_str(&format!(
+                    "the stash conflicts with HEAD in {} file(s); commit it on a new branch instead",
+                    count
+                )));
+            }
+            tree = repo.find_tree(merged.write_tree_to(repo)?)?;
+        }
+        let commit = repo.commit(None, &signature, &signature, message, &tree, &[&head_commit])?;
 
-                // Reload stash list
-                self.stashes = Self::load_stashes(&mut self.repo);
+        repo.checkout_tree(repo.find_commit(commit)?.as_object(), Some(CheckoutBuilder::new().safe()))?;
+        head.set_target(commit, &format!("commit (from stash): {}", message))?;
+        Ok(commit)
+    }
 
-                // Adjust selection
-                if self.stashes.is_empty() {
-                    // No stashes left
-                    self.stash_list_state.select(None);
-                    self.diff_content = String::new();
-                } else if selected_index >= self.stashes.len() {
-                    // The popped stash was the last one, select new last item
-                    let new_selection = self.stashes.len() - 1;
-                    self.stash_list_state.select(Some(new_selection));
-                    self.diff_content = Self::get_stash_diff(&self.repo, self.stashes[new_selection].oid);
-                } else {
-                    // Keep same index (next stash slides into this position)
-                    self.stash_list_state.select(Some(selected_index));
-                    self.diff_content = Self::get_stash_diff(&self.repo, self.stashes[selected_index].oid);
-                }
+    /// List stash commits that were dropped but not yet garbage collected
+    fn open_dropped_view(&mut self) {
+        let current: Vec<git2::Oid> = self.stashes.iter().map(|s| s.oid).collect();
+        match recover::dropped_stashes(&self.repo, &current, MAX_DROPPED_STASHES) {
+            Ok(stashes) if stashes.is_empty() => {
+                self.status_message = Some("No dropped stashes found (they may have been garbage collected)".to_string());
+            }
+            Ok(stashes) => {
+                let mut list_state = ListState::default();
+                list_state.select(Some(0));
+                self.stash_file_list_state = None;
+                self.dropped_list_state = Some(DroppedListState { list_state, stashes, preview: String::new(), summary: None, stat: String::new() });
+                self.update_dropped_preview();
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Finding dropped stashes failed: {}", friendly_error_message(&e)));
+            }
+        }
+    }
+
+    fn update_dropped_preview(&mut self) {
+        self.diff_scroll = 0;
+        self.diff_search.current = None;
+        if let Some(ref mut dropped_list_state) = self.dropped_list_state
+            && let Some(stash) = dropped_list_state.list_state.selected().and_then(|i| dropped_list_state.stashes.get(i))
+        {
+            dropped_list_state.preview = Self::get_stash_diff(&self.repo, stash.oid);
+            dropped_list_state.summary = Self::stash_summary(&self.repo, stash.oid).ok();
+            dropped_list_state.stat = Self::get_stash_stat(&self.repo, stash.oid);
+        }
+    }
+
+    /// Put the highlighted dropped stash back as stash@{0}
+    fn restore_dropped_stash(&mut self) {
+        let Some(dropped_list_state) = self.dropped_list_state.take() else {
+            return;
+        };
+        let Some(stash) = dropped_list_state.list_state.selected().and_then(|i| dropped_list_state.stashes.get(i)) else {
+            return;
+        };
+        match recover::restore(&self.repo, stash) {
+            Ok(()) => {
+                let restored = format!("Restored '{}' as stash@{{0}}", stash.message);
+                self.report(Ok(restored));
+                self.stashes = Self::load_stashes(&mut self.repo);
+                self.refresh_visible_stashes(Some(0));
             }
             Err(e) => {
-                self.status_message = Some(format!("Pop failed: {}", friendly_error_message(&e)));
+                self.report(Err(format!("Restore failed: {}", friendly_error_message(&e))));
+                self.dropped_list_state = Some(dropped_list_state);
             }
         }
     }
@@ -796,52 +3771,87 @@ impl App {
             return;
         }
 
-        // Only show popup if a stash is selected
-        if let Some(selected) = self.stash_list_state.selected() {
+        // Marked stashes are dropped together; otherwise just the selected one
+        let marked: Vec<git2::Oid> = self.stashes.iter().filter(|s| s.marked).map(|s| s.oid).collect();
+        if !marked.is_empty() {
+            self.show_confirm_popup = true;
+            self.confirm_stashes = marked;
+        } else if let Some(selected) = self.selected_stash() {
             self.show_confirm_popup = true;
-            self.confirm_stash_index = Some(selected);
+            self.confirm_stashes = vec![self.stashes[selected].oid];
+        }
+    }
+
+    /// Mark every stale stash, and only those, and ask to drop them
+    fn initiate_cleanup(&mut self) {
+        let now = unix_now();
+        let mut stale = 0;
+        for stash in &mut self.stashes {
+            stash.marked = is_stale(stash, self.config.stale_after_days, now);
+            stale += usize::from(stash.marked);
+        }
+        if stale == 0 {
+            self.status_message = Some(match self.config.stale_after_days {
+                0 => "Stale stashes are turned off (stale_after_days = 0)".to_string(),
+                days => format!("No stashes older than {} days", days),
+            });
+            return;
         }
+        self.initiate_drop_stash();
     }
 
     /// Confirm and execute the stash drop
     fn confirm_drop_stash(&mut self) {
-        if let Some(index) = self.confirm_stash_index {
-            // Drop the stash
+        self.show_confirm_popup = false;
+        let oids = std::mem::take(&mut self.confirm_stashes);
+
+        // Where the stashes are now; another terminal may have pushed or dropped stashes
+        let current = Self::load_stashes(&mut self.repo);
+        let found: Option<Vec<usize>> =
+            oids.iter().map(|oid| current.iter().find(|s| s.oid == *oid).map(|s| s.index)).collect();
+        let Some(mut indices) = found else {
+            self.report(Err("The stash list changed while confirming; nothing was dropped".to_string()));
+            self.reload_stashes_following(None);
+            return;
+        };
+        // Highest index first so the remaining indices stay valid
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut dropped = 0;
+        let mut failure = None;
+        for &index in &indices {
             match self.repo.stash_drop(index) {
-                Ok(()) => {
-                    self.status_message = Some(format!("Dropped stash@{{{}}} successfully", index));
-
-                    // Reload stash list
-                    self.stashes = Self::load_stashes(&mut self.repo);
-
-                    // Adjust selection (same logic as pop)
-                    if self.stashes.is_empty() {
-                        self.stash_list_state.select(None);
-                        self.diff_content = String::new();
-                    } else if index >= self.stashes.len() {
-                        let new_selection = self.stashes.len() - 1;
-                        self.stash_list_state.select(Some(new_selection));
-                        self.diff_content = Self::get_stash_diff(&self.repo, self.stashes[new_selection].oid);
-                    } else {
-                        self.stash_list_state.select(Some(index));
-                        self.diff_content = Self::get_stash_diff(&self.repo, self.stashes[index].oid);
-                    }
-                }
+                Ok(()) => dropped += 1,
                 Err(e) => {
-                    self.status_message = Some(format!("Drop failed: {}", friendly_error_message(&e)));
+                    failure = Some((index, e));
+                    break;
                 }
             }
         }
 
-        // Reset popup state
-        self.show_confirm_popup = false;
-        self.confirm_stash_index = None;
+        self.report(match (failure, indices.as_slice()) {
+            (None, [index]) => Ok(format!("Dropped stash@{{{}}} successfully", index)),
+            (None, _) => Ok(format!("Dropped {} stashes successfully", dropped)),
+            (Some((_, e)), [_]) => Err(format!("Drop failed: {}", friendly_error_message(&e))),
+            (Some((index, e)), _) => Err(format!(
+                "Dropped {} of {} stashes; drop failed at stash@{{{}}}: {}",
+                dropped,
+                indices.len(),
+                index,
+                friendly_error_message(&e)
+            )),
+        });
+        if dropped > 0
+            && let Some(&lowest) = indices.last()
+        {
+            self.reload_stashes_after_removal(lowest);
+        }
     }
 
     /// Cancel the confirmation popup
     fn cancel_confirm_popup(&mut self) {
         self.show_confirm_popup = false;
-        self.confirm_stash_index = None;
+        self.confirm_stashes.clear();
     }
 
     /// Render the application UI
@@ -858,7 +3868,8 @@ impl App {
     }
 
     /// Render the tab bar at the top
-    fn render_tabs(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+    fn render_tabs(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        self.tabs_area = area;
         let tab_titles: Vec<String> = SelectedTab::iter().map(|t| t.to_string()).collect();
         let tabs = Tabs::new(tab_titles)
             .block(
@@ -882,10 +3893,52 @@ impl App {
 
     /// Render the content area for the selected tab
     fn render_tab_content(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        // Empty states have no list or diff to click
+        self.list_area = Rect::default();
+        self.diff_area = Rect::default();
+        let diff_view = self.active_diff_view();
+
         match self.selected_tab {
             SelectedTab::Create => {
-                // Render file list if available
-                if let Some(ref mut file_list_state) = self.file_list_state
+                if let Some(ref mut hunk_list_state) = self.hunk_list_state {
+                    let chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                        .split(area);
+
+                    let items: Vec<ListItem> = hunk_list_state
+                        .hunks
+                        .iter()
+                        .map(|hunk| {
+                            let checkbox = if hunk.selected { "[x] " } else { "[ ] " };
+                            ListItem::new(Line::from(vec![
+                                Span::raw(checkbox),
+                                Span::styled(hunk.header.as_str(), Style::default().fg(DIFF_HUNK)),
+                            ]))
+                        })
+                        .collect();
+
+                    let list = List::new(items)
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .border_type(BorderType::Rounded)
+                                .border_style(Style::default().fg(DIM))
+                                .title(format!("Hunks in {} (Space: toggle, Enter/Esc: back)", hunk_list_state.path))
+                                .title_style(Style::default().fg(ACCENT)),
+                        )
+                        .highlight_style(
+                            Style::default()
+                                .bg(HIGHLIGHT_BG)
+                                .fg(HIGHLIGHT_FG)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .highlight_symbol(" > ");
+
+                    frame.render_stateful_widget(list, chunks[0], &mut hunk_list_state.list_state);
+                    (self.list_area, self.diff_area) = (chunks[0], chunks[1]);
+                    Self::render_diff_panel(frame, chunks[1], &self.create_diff_content, self.create_diff_scroll, diff_view, &self.diff_search, None, "Diff Preview");
+                } else if let Some(ref mut file_list_state) = self.file_list_state
                     && !file_list_state.files.is_empty()
                 {
                     // Split the area horizontally: 40% file list, 60% diff
@@ -899,7 +3952,11 @@ impl App {
                         .files
                         .iter()
                         .map(|file| {
-                            let checkbox = if file.selected { "[x] " } else { "[ ] " };
+                            let checkbox = match (file.selected, &file.hunks) {
+                                (true, Some(_)) => "[~] ",
+                                (true, None) => "[x] ",
+                                (false, _) => "[ ] ",
+                            };
                             let status_str = Self::format_file_status(file.status);
                             let status_color = if file.status.intersects(
                                 Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED,
@@ -912,6 +3969,7 @@ impl App {
                                 Span::raw(checkbox),
                                 Span::raw(&file.path),
                                 Span::raw(" ("),
+                                Span::styled(if file.submodule { "submodule " } else { "" }, Style::default().fg(ACCENT)),
                                 Span::styled(status_str, Style::default().fg(status_color)),
                                 Span::raw(")"),
                             ]))
@@ -924,7 +3982,11 @@ impl App {
                                 .borders(Borders::ALL)
                                 .border_type(BorderType::Rounded)
                                 .border_style(Style::default().fg(DIM))
-                                .title("Select Files (Space: toggle, s: stash)")
+                                .title(format!(
+                                    "Select Files [{}] (Space: toggle, Enter: hunks, s: stash){}",
+                                    self.file_filter.label(),
+                                    if self.files_loading { format!(" {} scanning…", spinner_frame()) } else { String::new() }
+                                ))
                                 .title_style(Style::default().fg(ACCENT)),
                         )
                         .highlight_style(
@@ -936,12 +3998,29 @@ impl App {
                         .highlight_symbol(" > ");
 
                     frame.render_stateful_widget(list, chunks[0], &mut file_list_state.list_state);
+                    (self.list_area, self.diff_area) = (chunks[0], chunks[1]);
 
                     // Render diff preview on the right
-                    Self::render_diff_panel(frame, chunks[1], &self.create_diff_content, self.create_diff_scroll);
+                    Self::render_diff_panel(frame, chunks[1], &self.create_diff_content, self.create_diff_scroll, diff_view, &self.diff_search, None, "Diff Preview");
+                } else if self.files_loading {
+                    let content = Paragraph::new(format!("{} Loading working tree…", spinner_frame()))
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .border_type(BorderType::Rounded)
+                                .border_style(Style::default().fg(DIM))
+                                .title("Create Stash")
+                                .title_style(Style::default().fg(ACCENT)),
+                        )
+                        .centered();
+                    frame.render_widget(content, area);
                 } else {
                     // Empty state - no modified files
-                    let content = Paragraph::new("No modified files -- working directory is clean")
+                    let content = Paragraph::new(format!(
+                        "No modified files -- working directory is clean (showing {} files; {} to change)",
+                        self.file_filter.label(),
+                        self.config.keymap.key_label(Action::FileFilter)
+                    ))
                         .block(
                             Block::default()
                                 .borders(Borders::ALL)
@@ -955,7 +4034,58 @@ impl App {
                 }
             }
             SelectedTab::Manage => {
-                if self.stashes.is_empty() {
+                if let Some(ref mut dropped_list_state) = self.dropped_list_state {
+                    // Split the area horizontally: 40% list, 60% diff
+                    let chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                        .split(area);
+
+                    // Render the dropped stashes on the left, newest first
+                    let now = unix_now();
+                    let items: Vec<ListItem> = dropped_list_state
+                        .stashes
+                        .iter()
+                        .map(|s| {
+                            let oid = s.oid.to_string();
+                            ListItem::new(Line::from(vec![
+                                Span::styled(format!("{:>8} ", format_age(now - s.time)), Style::default().fg(DIM)),
+                                Span::raw(s.message.as_str()),
+                                Span::styled(format!(" ({})", &oid[..7]), Style::default().fg(DIM)),
+                            ]))
+                        })
+                        .collect();
+
+                    let list = List::new(items)
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .border_type(BorderType::Rounded)
+                                .border_style(Style::default().fg(DIM))
+                                .title(format!("Recently Dropped ({})", dropped_list_state.stashes.len()))
+                                .title_style(Style::default().fg(ACCENT)),
+                        )
+                        .highlight_style(
+                            Style::default()
+                                .bg(HIGHLIGHT_BG)
+                                .fg(HIGHLIGHT_FG)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .highlight_symbol(" > ");
+
+                    frame.render_stateful_widget(list, chunks[0], &mut dropped_list_state.list_state);
+                    (self.list_area, self.diff_area) = (chunks[0], chunks[1]);
+                    Self::render_diff_panel(
+                        frame,
+                        chunks[1],
+                        if diff_view.stat { &dropped_list_state.stat } else { &dropped_list_state.preview },
+                        self.diff_scroll,
+                        diff_view,
+                        &self.diff_search,
+                        dropped_list_state.summary,
+                        "Dropped Stash Preview",
+                    );
+                } else if self.stashes.is_empty() {
                     // Show empty state
                     let content = Paragraph::new(
                         "No stashes found. Use 'git stash' or the Create Stash tab to create one.",
@@ -970,6 +4100,64 @@ impl App {
                     )
                     .centered();
                     frame.render_widget(content, area);
+                } else if let Some(ref mut stash_file_list_state) = self.stash_file_list_state {
+                    // Split the area horizontally: 40% file list, 60% diff
+                    let chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                        .split(area);
+
+                    // Render the stash's files on the left
+                    let marking = stash_file_list_state.files.iter().any(|f| f.marked);
+                    let items: Vec<ListItem> = stash_file_list_state
+                        .files
+                        .iter()
+                        .map(|f| {
+                            let checkbox = match (marking, f.marked) {
+                                (false, _) => "",
+                                (true, true) => "[x] ",
+                                (true, false) => "[ ] ",
+                            };
+                            let status = if f.untracked { '?' } else { f.status };
+                            let style = match status {
+                                'A' | '?' => Style::default().fg(SUCCESS),
+                                'D' => Style::default().fg(ERROR),
+                                _ => Style::default(),
+                            };
+                            ListItem::new(Line::from(vec![
+                                Span::raw(checkbox),
+                                Span::styled(format!("{} ", status), style),
+                                Span::raw(f.path.as_str()),
+                            ]))
+                        })
+                        .collect();
+
+                    let list = List::new(items)
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .border_type(BorderType::Rounded)
+                                .border_style(Style::default().fg(DIM))
+                                .title(format!(
+                                    "Files in stash@{{{}}} ({})",
+                                    stash_file_list_state.index,
+                                    stash_file_list_state.files.len()
+                                ))
+                                .title_style(Style::default().fg(ACCENT)),
+                        )
+                        .highlight_style(
+                            Style::default()
+                                .bg(HIGHLIGHT_BG)
+                                .fg(HIGHLIGHT_FG)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .highlight_symbol(" > ");
+
+                    frame.render_stateful_widget(list, chunks[0], &mut stash_file_list_state.list_state);
+                    (self.list_area, self.diff_area) = (chunks[0], chunks[1]);
+                    let content = if diff_view.stat { &self.diff_stat } else { &self.diff_content };
+                    let title = self.manage_diff_title();
+                    Self::render_diff_panel(frame, chunks[1], content, self.diff_scroll, diff_view, &self.diff_search, self.diff_summary, &title);
                 } else {
                     // Split the area horizontally: 40% list, 60% diff
                     let chunks = Layout::default()
@@ -977,15 +4165,51 @@ impl App {
                         .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
                         .split(area);
 
-                    // Render stash list on the left
+                    // Render stash list on the left (with checkboxes while any are marked,
+                    // stale stashes in the warning color)
+                    let marking = self.stashes.iter().any(|s| s.marked);
+                    let now = unix_now();
                     let items: Vec<ListItem> = self
-                        .stashes
+                        .stash_rows
                         .iter()
-                        .map(|s| {
-                            ListItem::new(format!(
-                                "stash@{{{}}}: {} ({})",
-                                s.index, s.message, s.branch
-                            ))
+                        .map(|row| {
+                            let s = match *row {
+                                StashRow::Stash(i) => &self.stashes[i],
+                                StashRow::Branch { ref name, gone } => {
+                                    let count = self.visible_stashes.iter().filter(|&&i| self.stashes[i].branch == *name).count();
+                                    let arrow = if self.collapsed_branches.contains(name) { "▸" } else { "▾" };
+                                    let color = if gone { WARNING } else { ACCENT };
+                                    return ListItem::new(Line::from(vec![
+                                        Span::styled(format!("{} {}", arrow, name), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+                                        Span::styled(
+                                            format!(" — {} stash{}{}", count, if count == 1 { "" } else { "es" }, if gone { ", branch deleted" } else { "" }),
+                                            Style::default().fg(if gone { WARNING } else { DIM }),
+                                        ),
+                                    ]));
+                                }
+                            };
+                            let checkbox = match (marking, s.marked) {
+                                (false, _) => "",
+                                (true, true) => "[x] ",
+                                (true, false) => "[ ] ",
+                            };
+                            // Under a branch heading the branch goes without saying
+                            let origin = match (&s.worktree, self.group_by_branch) {
+                                (Some(worktree), true) => format!(" (worktree {})", worktree.name),
+                                (Some(worktree), false) => format!(" ({}, worktree {})", s.branch, worktree.name),
+                                (None, true) => String::new(),
+                                (None, false) => format!(" ({})", s.branch),
+                            };
+                            let indent = if self.group_by_branch { "  " } else { "" };
+                            let item = ListItem::new(format!(
+                                "{}{}stash@{{{}}}: {}{}",
+                                indent, checkbox, s.index, s.message, origin
+                            ));
+                            if is_stale(s, self.config.stale_after_days, now) {
+                                item.style(Style::default().fg(WARNING))
+                            } else {
+                                item
+                            }
                         })
                         .collect();
 
@@ -995,7 +4219,28 @@ impl App {
                                 .borders(Borders::ALL)
                                 .border_type(BorderType::Rounded)
                                 .border_style(Style::default().fg(DIM))
-                                .title("Stash List")
+                                .title({
+                                    let order = if self.group_by_branch {
+                                        format!("{}, grouped by branch", self.stash_sort.label())
+                                    } else {
+                                        self.stash_sort.label().to_string()
+                                    };
+                                    let mut title = if self.stash_filter.is_empty() {
+                                        format!("Stash List ({})", order)
+                                    } else {
+                                        format!(
+                                            "Stash List ({}, filter: {} — {} of {})",
+                                            order,
+                                            self.stash_filter,
+                                            self.visible_stashes.len(),
+                                            self.stashes.len()
+                                        )
+                                    };
+                                    if let Some(ref name) = self.worktree_name {
+                                        title.push_str(&format!(" in worktree {}", name));
+                                    }
+                                    title
+                                })
                                 .title_style(Style::default().fg(ACCENT)),
                         )
                         .highlight_style(
@@ -1007,9 +4252,12 @@ impl App {
                         .highlight_symbol(" > ");
 
                     frame.render_stateful_widget(list, chunks[0], &mut self.stash_list_state);
+                    (self.list_area, self.diff_area) = (chunks[0], chunks[1]);
 
-                    // Render diff preview on the right
-                    Self::render_diff_panel(frame, chunks[1], &self.diff_content, self.diff_scroll);
+                    // Render diff preview (or the comparison) on the right
+                    let title = self.manage_diff_title();
+                    let content = if diff_view.stat { &self.diff_stat } else { &self.diff_content };
+                    Self::render_diff_panel(frame, chunks[1], content, self.diff_scroll, diff_view, &self.diff_search, self.diff_summary, &title);
                 }
             }
         }
@@ -1042,13 +4290,26 @@ impl App {
         // Render help text (changes based on popup visibility and active tab)
         let help_style = Style::default().fg(DIM);
         let help_text = if self.show_message_input {
-            Line::from(Span::styled("Enter: Create Stash | Esc: Cancel | Type your stash message", help_style))
+            Line::from(Span::styled(self.input_purpose.help(), help_style))
         } else if self.show_confirm_popup {
             Line::from(Span::styled("y: Confirm | n/Esc: Cancel", help_style))
-        } else if self.selected_tab == SelectedTab::Create {
-            Line::from(Span::styled("q: Quit | Tab: Switch Tab | Up/Down: Navigate | Space: Toggle | s: Stash Selected", help_style))
+        } else if self.conflict_warning.is_some() {
+            let stash = if self.conflict_warning.as_ref().is_some_and(|w| w.dirty) { " | s: Stash My Changes First" } else { "" };
+            Line::from(Span::styled(format!("y: Apply Anyway{} | n/Esc: Cancel", stash), help_style))
+        } else if self.show_help {
+            let keymap = &self.config.keymap;
+            Line::from(Span::styled(
+                keymap.help_line(&[(&[Action::Up, Action::Down], "Scroll"), (&[Action::Help], "Close")]) + " | Esc: Close",
+                help_style,
+            ))
+        } else if self.show_log {
+            let keymap = &self.config.keymap;
+            Line::from(Span::styled(
+                keymap.help_line(&[(&[Action::Up, Action::Down], "Scroll"), (&[Action::Log], "Close")]) + " | Esc: Close",
+                help_style,
+            ))
         } else {
-            Line::from(Span::styled("q: Quit | Tab: Switch Tab | Up/Down: Navigate | a: Apply | p: Pop | d: Drop", help_style))
+            Line::from(Span::styled(self.help_line(), help_style))
         };
         frame.render_widget(help_text, help_area);
 
@@ -1057,56 +4318,530 @@ impl App {
             self.render_confirm_popup(frame, area);
         }
 
+        if let Some(warning) = &self.conflict_warning {
+            self.render_conflict_popup(frame, area, warning);
+        }
+
+        if self.show_help {
+            self.render_help_popup(frame, area);
+        }
+
+        if self.show_log {
+            self.render_log_popup(frame, area);
+        }
+
         // Render message input popup overlay if visible
         if self.show_message_input {
             self.render_message_input_popup(frame, area);
         }
     }
 
-    /// Render a diff panel with syntax highlighting (shared by both tabs)
-    fn render_diff_panel(frame: &mut Frame, area: ratatui::layout::Rect, content: &str, scroll: u16) {
-        let lines: Vec<Line> = content
-            .lines()
-            .map(|line| {
-                if line.starts_with('+') {
-                    Line::from(Span::styled(line, Style::default().fg(SUCCESS)))
-                } else if line.starts_with('-') {
-                    Line::from(Span::styled(line, Style::default().fg(ERROR)))
-                } else if line.starts_with("@@") {
-                    Line::from(Span::styled(line, Style::default().fg(DIFF_HUNK)))
-                } else if line.starts_with("diff ") || line.starts_with("index ") {
-                    Line::from(Span::styled(line, Style::default().fg(DIM).add_modifier(Modifier::BOLD)))
-                } else {
-                    Line::from(line)
-                }
-            })
-            .collect();
+    /// Footer help for the current tab, built from the active key bindings
+    fn help_line(&self) -> String {
+        let keymap = &self.config.keymap;
+        if self.selected_tab == SelectedTab::Create && self.hunk_list_state.is_some() {
+            keymap.help_line(&[
+                (&[Action::Quit], "Quit"),
+                (&[Action::Help], "Help"),
+                (&[Action::Up, Action::Down], "Navigate"),
+                (&[Action::Toggle], "Toggle Hunk"),
+                (&[Action::Hunks, Action::Clear], "Back to Files"),
+                (&[Action::Stash], "Stash Selected"),
+            ])
+        } else if self.selected_tab == SelectedTab::Manage && self.dropped_list_state.is_some() {
+            keymap.help_line(&[
+                (&[Action::Quit], "Quit"),
+                (&[Action::Help], "Help"),
+                (&[Action::Up, Action::Down], "Navigate"),
+                (&[Action::ScrollUp, Action::ScrollDown], "Scroll"),
+                (&[Action::Files], "Restore"),
+                (&[Action::Recover, Action::Clear], "Back to Stashes"),
+            ])
+        } else if self.selected_tab == SelectedTab::Manage && self.stash_file_list_state.is_some() {
+            keymap.help_line(&[
+                (&[Action::Quit], "Quit"),
+                (&[Action::Help], "Help"),
+                (&[Action::Up, Action::Down], "Jump to File"),
+                (&[Action::ScrollUp, Action::ScrollDown], "Scroll"),
+                (&[Action::Toggle], "Mark"),
+                (&[Action::Apply], "Apply Files"),
+                (&[Action::Files, Action::Clear], "Back to Stashes"),
+            ])
+        } else if self.selected_tab == SelectedTab::Create {
+            keymap.help_line(&[
+                (&[Action::Quit], "Quit"),
+                (&[Action::Help], "Help"),
+                (&[Action::NextTab], "Switch Tab"),
+                (&[Action::Up, Action::Down], "Navigate"),
+                (&[Action::Toggle], "Toggle"),
+                (&[Action::SelectAll, Action::SelectNone], "All/None"),
+                (&[Action::Hunks], "Hunks"),
+                (&[Action::Stash, Action::StashAll], "Stash Selected/All"),
+            ])
+        } else {
+            keymap.help_line(&[
+                (&[Action::Quit], "Quit"),
+                (&[Action::Help], "Help"),
+                (&[Action::NextTab], "Switch Tab"),
+                (&[Action::Up, Action::Down], "Navigate"),
+                (&[Action::Apply, Action::ApplyIndex], "Apply (with index)"),
+                (&[Action::Pop, Action::PopIndex], "Pop (with index)"),
+                (&[Action::Toggle], "Mark"),
+                (&[Action::Files], "Files"),
+                (&[Action::Drop], "Drop"),
+                (&[Action::Recover], "Recover"),
+                (&[Action::Branch], "Branch"),
+                (&[Action::Export], "Export"),
+                (&[Action::Filter], "Filter"),
+                (&[Action::Sort], "Sort"),
+            ])
+        }
+    }
+
+    /// `3 files (1 untracked)  +10 -2`, for the diff panel's border
+    fn summary_line(summary: DiffSummary) -> Line<'static> {
+        let mut files = format!(" {} file{}", summary.files, if summary.files == 1 { "" } else { "s" });
+        if summary.untracked > 0 {
+            files.push_str(&format!(" ({} untracked)", summary.untracked));
+        }
+        Line::from(vec![
+            Span::styled(files, Style::default().fg(ACCENT)),
+            Span::styled(format!("  +{}", summary.insertions), Style::default().fg(SUCCESS)),
+            Span::styled(format!(" -{} ", summary.deletions), Style::default().fg(ERROR)),
+        ])
+    }
+
+    /// Every binding for the help popup, grouped by where it applies
+    fn help_sections() -> Vec<(&'static str, Vec<HelpEntry>)> {
+        vec![
+            ("Global", vec![
+                (&[Action::Quit], "Quit"),
+                (&[Action::Help], "Show this help"),
+                (&[Action::Log], "Show the log of recent operations"),
+                (&[Action::NextTab], "Switch to next tab"),
+                (&[Action::PrevTab], "Switch to previous tab"),
+            ]),
+            ("Diff preview", vec![
+                (&[Action::ScrollUp], "Scroll up"),
+                (&[Action::ScrollDown], "Scroll down"),
+                (&[Action::HalfPageUp], "Scroll up half a page"),
+                (&[Action::HalfPageDown], "Scroll down half a page"),
+                (&[Action::PageUp], "Scroll up a page"),
+                (&[Action::PageDown], "Scroll down a page"),
+                (&[Action::ScrollLeft], "Scroll left"),
+                (&[Action::ScrollRight], "Scroll right"),
+                (&[Action::Wrap], "Wrap long lines on/off"),
+                (&[Action::SideBySide], "Side-by-side view on/off"),
+                (&[Action::Stat], "Diffstat summary on/off (Manage tab)"),
+                (&[Action::Search], "Search the diff"),
+                (&[Action::SearchNext], "Jump to the next match"),
+                (&[Action::SearchPrev], "Jump to the previous match"),
+                (&[Action::Top], "Jump to the top (press twice)"),
+                (&[Action::Bottom], "Jump to the bottom"),
+            ]),
+            ("Create Stash", vec![
+                (&[Action::Up], "Move selection up"),
+                (&[Action::Down], "Move selection down"),
+                (&[Action::Toggle], "Toggle file selection"),
+                (&[Action::SelectAll], "Select every listed file, or none if all are"),
+                (&[Action::SelectNone], "Clear every selection"),
+                (&[Action::Hunks], "Open the file's hunks"),
+                (&[Action::FileFilter], "Show untracked / ignored files too"),
+                (&[Action::Stash], "Create stash from selected files"),
+                (&[Action::StashAll], "Select every listed file and stash them"),
+                (&[Action::ShowCreated], "Show the stash just created in the Manage tab"),
+            ]),
+            ("Hunk list", vec![
+                (&[Action::Up], "Move selection up"),
+                (&[Action::Down], "Move selection down"),
+                (&[Action::Toggle], "Toggle hunk selection"),
+                (&[Action::SelectAll], "Select every hunk, or none if all are"),
+                (&[Action::SelectNone], "Clear every hunk"),
+                (&[Action::Hunks, Action::Clear], "Back to the file list"),
+                (&[Action::Stash], "Create stash from selected files and hunks"),
+            ]),
+            ("Manage Stashes", vec![
+                (&[Action::Up], "Move selection up"),
+                (&[Action::Down], "Move selection down"),
+                (&[Action::Apply], "Apply selected stash"),
+                (&[Action::ApplyIndex], "Apply, restoring staged changes"),
+                (&[Action::Pop], "Pop selected stash"),
+                (&[Action::PopIndex], "Pop, restoring staged changes"),
+                (&[Action::Toggle], "Mark/unmark stash (or a branch's stashes) for bulk drop"),
+                (&[Action::Files], "List the stash's files, or collapse/expand a branch"),
+                (&[Action::Drop], "Drop marked or selected stashes"),
+                (&[Action::Cleanup], "Mark stale stashes and review dropping them"),
+                (&[Action::Recover], "Show recently dropped stashes"),
+                (&[Action::Branch], "Create a branch from selected stash"),
+                (&[Action::Worktree], "Apply selected stash in a new worktree"),
+                (&[Action::Commit], "Commit selected stash (current or new branch)"),
+                (&[Action::Rename], "Rename selected stash"),
+                (&[Action::Compare], "Compare selected stash with the marked one"),
+                (&[Action::AgainstCurrent], "Preview what applying the stash now would change"),
+                (&[Action::Export], "Export selected stash as a patch"),
+                (&[Action::Backup], "Back up every stash to a folder of patches"),
+                (&[Action::Pager], "Open the diff in your pager"),
+                (&[Action::Difftool], "Open the diff in git difftool"),
+                (&[Action::Filter], "Filter stashes"),
+                (&[Action::Sort], "Sort by index, date, branch, or size"),
+                (&[Action::Goto], "Go to a stash by index (0-9 jump straight there)"),
+                (&[Action::Group], "Group stashes under branch headings"),
+                (&[Action::Clear], "Clear marks, the filter and the search"),
+            ]),
+            ("Stash file list", vec![
+                (&[Action::Up], "Jump to previous file"),
+                (&[Action::Down], "Jump to next file"),
+                (&[Action::Toggle], "Mark/unmark file"),
+                (&[Action::Difftool], "Open the file in git difftool"),
+                (&[Action::Apply], "Apply marked (or highlighted) files only"),
+                (&[Action::Files, Action::Clear], "Back to the stash list"),
+            ]),
+            ("Recently dropped", vec![
+                (&[Action::Up], "Move selection up"),
+                (&[Action::Down], "Move selection down"),
+                (&[Action::Files], "Restore as stash@{0}"),
+                (&[Action::Recover, Action::Clear], "Back to the stash list"),
+            ]),
+        ]
+    }
 
-        let diff_paragraph = Paragraph::new(lines)
+    /// Render the help popup listing every key binding
+    fn render_help_popup(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let keymap = &self.config.keymap;
+        let sections = Self::help_sections();
+        let keys_width = sections
+            .iter()
+            .flat_map(|(_, entries)| entries.iter())
+            .map(|(actions, _)| Self::help_keys(keymap, actions).chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let heading = Style::default().fg(ACCENT).add_modifier(Modifier::BOLD);
+        let mut lines: Vec<Line> = Vec::new();
+        for (title, entries) in sections {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::from(Span::styled(title, heading)));
+            for (actions, description) in entries {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<width$}  ", Self::help_keys(keymap, actions), width = keys_width), Style::default().fg(HIGHLIGHT_FG)),
+                    Span::raw(description),
+                ]));
+            }
+        }
+        lines.push(Line::default());
+        lines.push(Line::from(Span::styled("Popups", heading)));
+        lines.push(Line::from(Span::styled(
+            "  Enter confirms a prompt, Esc cancels; y/n answer a confirmation; Up/Down recall recent stash messages",
+            Style::default().fg(DIM),
+        )));
+        lines.push(Line::default());
+        lines.push(Line::from(Span::styled("Mouse", heading)));
+        lines.push(Line::from(Span::styled(
+            "  Click a tab or list row to select it; the wheel moves the list or scrolls the diff",
+            Style::default().fg(DIM),
+        )));
+
+        let popup_area = {
+            let vertical = Layout::vertical([Constraint::Percentage(80)]).flex(Flex::Center).split(area);
+            Layout::horizontal([Constraint::Percentage(70)]).flex(Flex::Center).split(vertical[0])[0]
+        };
+
+        // Keep the last line at the bottom of the popup at most
+        let max_scroll = (lines.len() as u16).saturating_sub(popup_area.height.saturating_sub(2));
+        self.help_scroll = self.help_scroll.min(max_scroll);
+
+        frame.render_widget(Clear, popup_area);
+        let popup = Paragraph::new(lines)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded)
-                    .border_style(Style::default().fg(DIM))
-                    .title("Diff Preview")
-                    .title_style(Style::default().fg(ACCENT)),
+                    .border_style(Style::default().fg(ACCENT))
+                    .title("Keybindings")
+                    .title_style(heading),
+            )
+            .scroll((self.help_scroll, 0));
+        frame.render_widget(popup, popup_area);
+    }
+
+    /// Render the operation log, most recent first
+    fn render_log_popup(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let lines: Vec<Line> = if self.op_log.is_empty() {
+            vec![Line::from(Span::styled("Nothing done yet in this session", Style::default().fg(DIM)))]
+        } else {
+            self.op_log
+                .entries()
+                .map(|entry| {
+                    let (mark, color) = if entry.ok { ("✓", SUCCESS) } else { ("✗", ERROR) };
+                    Line::from(vec![
+                        Span::styled(format!("{}  ", local_date_time(entry.time).1), Style::default().fg(DIM)),
+                        Span::styled(format!("{} ", mark), Style::default().fg(color)),
+                        Span::raw(entry.text.clone()),
+                    ])
+                })
+                .collect()
+        };
+
+        let popup_area = {
+            let vertical = Layout::vertical([Constraint::Percentage(60)]).flex(Flex::Center).split(area);
+            Layout::horizontal([Constraint::Percentage(80)]).flex(Flex::Center).split(vertical[0])[0]
+        };
+
+        // Long messages wrap; count the rows they take so the last one can be scrolled into view
+        let width = usize::from(popup_area.width.saturating_sub(2)).max(1);
+        let rows: usize = lines.iter().map(|line| line.width().div_ceil(width).max(1)).sum();
+        let max_scroll = (rows as u16).saturating_sub(popup_area.height.saturating_sub(2));
+        self.log_scroll = self.log_scroll.min(max_scroll);
+
+        frame.render_widget(Clear, popup_area);
+        let popup = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(ACCENT))
+                    .title("Operation Log")
+                    .title_style(Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)),
             )
-            .scroll((scroll, 0));
+            .wrap(Wrap { trim: false })
+            .scroll((self.log_scroll, 0));
+        frame.render_widget(popup, popup_area);
+    }
+
+    /// The keys of one or more actions for the help popup
+    fn help_keys(keymap: &crate::keymap::Keymap, actions: &[Action]) -> String {
+        actions.iter().map(|a| keymap.keys_label(*a)).collect::<Vec<_>>().join(" / ")
+    }
+
+    /// Render a diff panel with syntax highlighting (shared by both tabs)
+    /// Render a diff preview scrolled down to `scroll` (a line, or a row when side by side)
+    #[allow(clippy::too_many_arguments)]
+    fn render_diff_panel(
+        frame: &mut Frame,
+        area: ratatui::layout::Rect,
+        content: &str,
+        scroll: u16,
+        view: DiffView,
+        search: &DiffSearch,
+        summary: Option<DiffSummary>,
+        title: &str,
+    ) {
+        if view.side_by_side {
+            let height = area.height.saturating_sub(2) as usize;
+            let visible = scroll as usize..scroll as usize + height;
+            let lines = side_by_side::lines(content, area.width.saturating_sub(2) as usize, visible, view.hscroll as usize, search);
+            let total = side_by_side::row_count(content);
+            let title = Self::diff_title(title, scroll, total, height, view);
+            frame.render_widget(Paragraph::new(lines).block(Self::diff_block(title, summary)), area);
+            return;
+        }
+
+        let mut lines: Vec<Line> = if view.stat {
+            content.lines().map(Self::stat_line).collect()
+        } else {
+            content
+                .lines()
+                .map(|line| {
+                    if line.starts_with('+') {
+                        Line::from(Span::styled(line, Style::default().fg(SUCCESS)))
+                    } else if line.starts_with('-') {
+                        Line::from(Span::styled(line, Style::default().fg(ERROR)))
+                    } else if line.starts_with("@@") {
+                        Line::from(Span::styled(line, Style::default().fg(DIFF_HUNK)))
+                    } else if line.starts_with("Untracked files (") {
+                        Line::from(Span::styled(line, Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)))
+                    } else if line.starts_with("Submodule ") || line.starts_with("Binary file ") {
+                        Line::from(Span::styled(line, Style::default().fg(ACCENT)))
+                    } else if line.starts_with("Would not apply cleanly (") {
+                        Line::from(Span::styled(line, Style::default().fg(ERROR).add_modifier(Modifier::BOLD)))
+                    } else if line.starts_with("diff ") || line.starts_with("index ") {
+                        Line::from(Span::styled(line, Style::default().fg(DIM).add_modifier(Modifier::BOLD)))
+                    } else {
+                        Line::from(line)
+                    }
+                })
+                .collect()
+        };
+
+        let visible = scroll as usize..(scroll as usize + area.height as usize).min(lines.len());
+        if !view.stat {
+            Self::highlight_changed_words(&mut lines, content, visible.clone());
+        }
+        if !search.query.is_empty() {
+            for number in visible {
+                let line = std::mem::take(&mut lines[number]);
+                lines[number] = search.highlight(line, number);
+            }
+        }
+
+        let title = Self::diff_title(title, scroll, lines.len(), area.height.saturating_sub(2) as usize, view);
+        let mut diff_paragraph = Paragraph::new(lines).block(Self::diff_block(title, summary));
+        diff_paragraph = if view.wrap {
+            diff_paragraph.wrap(Wrap { trim: false }).scroll((scroll, 0))
+        } else {
+            diff_paragraph.scroll((scroll, view.hscroll))
+        };
 
         frame.render_widget(diff_paragraph, area);
     }
 
+    /// A `--stat` line with its bar in the diff colors
+    fn stat_line(line: &str) -> Line<'_> {
+        if line.starts_with("Untracked files (") {
+            return Line::from(Span::styled(line, Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)));
+        }
+        // " path | 12 +++---": the bar is the last word (binary files have none)
+        match line.rsplit_once(' ') {
+            Some((head, bar)) if line.contains(" | ") && !bar.is_empty() && bar.chars().all(|c| c == '+' || c == '-') => {
+                let insertions = bar.chars().filter(|&c| c == '+').count();
+                Line::from(vec![
+                    Span::raw(format!("{} ", head)),
+                    Span::styled(&bar[..insertions], Style::default().fg(SUCCESS)),
+                    Span::styled(&bar[insertions..], Style::default().fg(ERROR)),
+                ])
+            }
+            _ if line.contains(" | ") => Line::from(line),
+            _ => Line::from(Span::styled(line, Style::default().fg(DIM))),
+        }
+    }
+
+    /// The diff panel's title with where we are, once there is more than fits
+    fn diff_title(title: &str, scroll: u16, total: usize, height: usize, view: DiffView) -> String {
+        let mut position = Vec::new();
+        if view.stat {
+            position.push("stat".to_string());
+        }
+        if total > height {
+            position.push(format!("line {} of {}", (scroll as usize + 1).min(total), total));
+        }
+        if view.wrap && !view.side_by_side {
+            position.push("wrapped".to_string());
+        } else if view.hscroll > 0 {
+            position.push(format!("column {}", view.hscroll + 1));
+        }
+        if position.is_empty() {
+            title.to_string()
+        } else {
+            format!("{} — {}", title, position.join(", "))
+        }
+    }
+
+    /// The diff panel's border, with the stash summary on the right
+    fn diff_block(title: String, summary: Option<DiffSummary>) -> Block<'static> {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(DIM))
+            .title(title)
+            .title_style(Style::default().fg(ACCENT));
+        if let Some(summary) = summary {
+            block = block.title(Self::summary_line(summary).right_aligned());
+        }
+        block
+    }
+
+    /// Highlight the changed words of paired -/+ lines (a run of removals followed by a run of
+    /// additions, paired in order). Only runs reaching into `visible` are compared.
+    fn highlight_changed_words<'a>(lines: &mut [Line<'a>], content: &'a str, visible: std::ops::Range<usize>) {
+        let raw: Vec<&str> = content.lines().collect();
+        let mut in_hunk = false;
+        let mut i = 0;
+        while i < raw.len() && i < visible.end {
+            let line = raw[i];
+            if line.starts_with("@@") {
+                in_hunk = true;
+            } else if line.starts_with("diff ") || line.starts_with("Untracked files (") {
+                in_hunk = false;
+            }
+            if !in_hunk || !line.starts_with('-') {
+                i += 1;
+                continue;
+            }
+
+            let removed_start = i;
+            while i < raw.len() && raw[i].starts_with('-') {
+                i += 1;
+            }
+            let added_start = i;
+            while i < raw.len() && raw[i].starts_with('+') {
+                i += 1;
+            }
+            if i <= visible.start {
+                continue;
+            }
+
+            for (old, new) in (removed_start..added_start).zip(added_start..i) {
+                if let Some(changes) = word_diff::changed_ranges(&raw[old][1..], &raw[new][1..]) {
+                    lines[old] = Self::word_highlighted(raw[old], &changes.old, ERROR, WORD_DEL_BG);
+                    lines[new] = Self::word_highlighted(raw[new], &changes.new, SUCCESS, WORD_ADD_BG);
+                }
+            }
+        }
+    }
+
+    /// A diff line with the given ranges (relative to the text after the origin column)
+    /// drawn on a background
+    fn word_highlighted<'a>(line: &'a str, changed: &[std::ops::Range<usize>], fg: Color, bg: Color) -> Line<'a> {
+        let base = Style::default().fg(fg);
+        let mut spans = vec![Span::styled(&line[..1], base)];
+        let mut pos = 1;
+        for range in changed {
+            let (start, end) = (range.start + 1, range.end + 1);
+            if start > pos {
+                spans.push(Span::styled(&line[pos..start], base));
+            }
+            spans.push(Span::styled(&line[start..end], base.bg(bg).add_modifier(Modifier::BOLD)));
+            pos = end;
+        }
+        if pos < line.len() {
+            spans.push(Span::styled(&line[pos..], base));
+        }
+        Line::from(spans)
+    }
+
     /// Render the confirmation popup overlay
     fn render_confirm_popup(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
-        // Calculate centered popup area: 60% width, 20% height
+        /// Stashes listed by name in a bulk drop popup before summarizing the rest
+        const MAX_LISTED: usize = 10;
+
+        let footer = format!(
+            "Dropped stashes can be restored with {} until git gc removes them.\n\nPress 'y' to confirm, 'n' or Esc to cancel",
+            self.config.keymap.key_label(Action::Recover)
+        );
+
+        // Get the stash message(s) for display
+        let stash = |oid: &git2::Oid| self.stashes.iter().find(|s| s.oid == *oid);
+        let message = match self.confirm_stashes.as_slice() {
+            [oid] => match stash(oid) {
+                Some(stash) => format!("Drop stash@{{{}}}: {}?\n\n{}", stash.index, stash.message, footer),
+                None => format!("Drop stash?\n\n{}", footer),
+            },
+            oids => {
+                let mut message = format!("Drop these {} stashes?\n\n", oids.len());
+                for stash in oids.iter().filter_map(stash).take(MAX_LISTED) {
+                    message.push_str(&format!("stash@{{{}}}: {}\n", stash.index, stash.message));
+                }
+                if oids.len() > MAX_LISTED {
+                    message.push_str(&format!("... and {} more\n", oids.len() - MAX_LISTED));
+                }
+                message.push('\n');
+                message.push_str(&footer);
+                message
+            }
+        };
+
+        // Calculate centered popup area: 60% width, 20% height (taller when listing stashes)
+        let height = if self.confirm_stashes.len() > 1 {
+            Constraint::Length(message.lines().count() as u16 + 2)
+        } else {
+            Constraint::Percentage(20)
+        };
         let popup_area = {
-            let vertical = Layout::vertical([
-                Constraint::Percentage(40),
-                Constraint::Percentage(20),
-                Constraint::Percentage(40),
-            ])
-            .flex(Flex::Center)
-            .split(area);
+            let vertical = Layout::vertical([Constraint::Fill(1), height, Constraint::Fill(1)])
+                .flex(Flex::Center)
+                .split(area);
 
             Layout::horizontal([
                 Constraint::Percentage(20),
@@ -1117,18 +4852,6 @@ impl App {
             .split(vertical[1])[1]
         };
 
-        // Get the stash message for display
-        let message = if let Some(index) = self.confirm_stash_index
-            && let Some(stash) = self.stashes.get(index)
-        {
-            format!(
-                "Drop stash@{{{}}}: {}?\n\nThis cannot be undone.\n\nPress 'y' to confirm, 'n' or Esc to cancel",
-                index, stash.message
-            )
-        } else {
-            "Drop stash?\n\nThis cannot be undone.\n\nPress 'y' to confirm, 'n' or Esc to cancel".to_string()
-        };
-
         // Clear the background
         frame.render_widget(Clear, popup_area);
 
@@ -1147,6 +4870,104 @@ impl App {
         frame.render_widget(popup, popup_area);
     }
 
+    /// Render the warning shown before an apply or pop that would conflict
+    fn render_conflict_popup(&self, frame: &mut Frame, area: ratatui::layout::Rect, warning: &ConflictWarning) {
+        /// Files listed by name before summarizing the rest
+        const MAX_LISTED: usize = 10;
+
+        let verb = if warning.pop { "Popping" } else { "Applying" };
+        let mut message = String::new();
+        if let Some(ref worktree) = warning.worktree {
+            let branch = self.stashes.get(warning.index).map_or("", |s| s.branch.as_str());
+            message.push_str(&format!(
+                "stash@{{{}}} was made on '{}', which is checked out in worktree '{}'\n({}).\n{} it here brings that work into this worktree.\n\n",
+                warning.index,
+                branch,
+                worktree.name,
+                worktree.path.display(),
+                verb
+            ));
+        }
+        if !warning.conflicts.is_empty() {
+            message.push_str(&format!(
+                "{} stash@{{{}}} will not go cleanly for {} file{}:\n\n",
+                verb,
+                warning.index,
+                warning.conflicts.len(),
+                if warning.conflicts.len() == 1 { "" } else { "s" }
+            ));
+            for conflict in warning.conflicts.iter().take(MAX_LISTED) {
+                message.push_str(&format!("{} ({})\n", conflict.path, conflict.kind));
+            }
+            if warning.conflicts.len() > MAX_LISTED {
+                message.push_str(&format!("... and {} more\n", warning.conflicts.len() - MAX_LISTED));
+            }
+            message.push('\n');
+        }
+        if !warning.overlapping.is_empty() {
+            let other = if warning.conflicts.is_empty() { "" } else { "other " };
+            message.push_str(&format!(
+                "You have uncommitted changes to {} {}file{} that stash@{{{}}} also changes:\n\n",
+                warning.overlapping.len(),
+                other,
+                if warning.overlapping.len() == 1 { "" } else { "s" },
+                warning.index
+            ));
+            for path in warning.overlapping.iter().take(MAX_LISTED) {
+                message.push_str(&format!("{}\n", path));
+            }
+            if warning.overlapping.len() > MAX_LISTED {
+                message.push_str(&format!("... and {} more\n", warning.overlapping.len() - MAX_LISTED));
+            }
+            message.push('\n');
+        }
+        if warning.dirty {
+            message.push_str("Press 's' to stash your changes first, 'y' to continue anyway,\n'n' or Esc to cancel");
+        } else {
+            message.push_str("Press 'y' to continue anyway, 'n' or Esc to cancel");
+        }
+        let (title, color) = if !warning.conflicts.is_empty() {
+            ("Conflicts Expected", ERROR)
+        } else if !warning.overlapping.is_empty() {
+            ("Uncommitted Changes", WARNING)
+        } else {
+            ("Stash From Another Worktree", WARNING)
+        };
+
+        let popup_area = {
+            let vertical = Layout::vertical([
+                Constraint::Fill(1),
+                Constraint::Length(message.lines().count() as u16 + 2),
+                Constraint::Fill(1),
+            ])
+            .flex(Flex::Center)
+            .split(area);
+
+            Layout::horizontal([
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ])
+            .flex(Flex::Center)
+            .split(vertical[1])[1]
+        };
+
+        frame.render_widget(Clear, popup_area);
+
+        let popup = Paragraph::new(message)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(color))
+                    .title(title)
+                    .title_style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            )
+            .centered();
+
+        frame.render_widget(popup, popup_area);
+    }
+
     /// Render the message input popup overlay
     fn render_message_input_popup(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
         // Calculate centered popup area: 60% width, 20% height
@@ -1178,7 +4999,7 @@ impl App {
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded)
                     .border_style(Style::default().fg(ACCENT))
-                    .title("Enter Stash Message (Enter: confirm, Esc: cancel)")
+                    .title(self.input_purpose.title())
                     .title_style(Style::default().fg(ACCENT)),
             );
 
@@ -1191,6 +5012,31 @@ impl App {
         ));
     }
 
+    /// Show how an operation went in the status bar and keep it in the operation log
+    fn report(&mut self, outcome: Result<String, String>) {
+        let (ok, text) = match outcome {
+            Ok(text) => (true, text),
+            Err(text) => (false, text),
+        };
+        self.op_log.push(unix_now(), ok, text.clone());
+        self.status_message = Some(text);
+    }
+
+    /// Open the text input popup for the given purpose
+    fn open_input(&mut self, purpose: InputPurpose) {
+        self.show_message_input = true;
+        self.input_purpose = purpose;
+        self.message_input = MessageInputState::new();
+    }
+
+    /// Open the stash message popup prefilled from the message template
+    fn open_stash_message_input(&mut self) {
+        self.open_input(InputPurpose::StashMessage);
+        let branch = hunks::current_branch(&self.repo).unwrap_or_else(|_| "(no branch)".to_string());
+        self.message_input = MessageInputState::with_value(&expand_template(&self.config.message_template, &branch));
+        self.message_history = MessageHistory::load();
+    }
+
     /// Create a stash from the selected files with the entered message
     fn create_stash(&mut self) {
         // Validate repository state first
@@ -1215,55 +5061,103 @@ impl App {
             return;
         }
 
-        // Get the message
-        let message = self.message_input.value();
-
-        // Require a message (don't allow empty)
-        if message.trim().is_empty() {
-            self.status_message = Some("Please enter a stash message".to_string());
-            // Don't close popup so user can type
-            return;
-        }
+        // An empty message gets one describing the files and branch
+        let typed = !self.message_input.value().trim().is_empty();
+        let message = if typed {
+            self.message_input.value().to_string()
+        } else {
+            let branch = hunks::current_branch(&self.repo).unwrap_or_else(|_| "(no branch)".to_string());
+            auto_message(&selected_paths, &branch)
+        };
 
         // Get signature (handle error gracefully)
         let signature = match self.repo.signature() {
             Ok(sig) => sig,
             Err(_) => {
-                self.status_message = Some("Stash failed: git user.name/email not configured".to_string());
+                self.report(Err("Stash failed: git user.name/email not configured".to_string()));
                 self.show_message_input = false;
                 self.message_input = MessageInputState::new();
                 return;
             }
         };
 
-        // Create stash with pathspecs
-        let mut opts = StashSaveOptions::new(signature);
-        for path in &selected_paths {
-            opts.pathspec(path);
+        // Hunk-level selections go through a hand-built stash; whole files use pathspecs
+        let partial = self.file_list_state.as_ref().is_some_and(|f| f.has_partial_selection());
+        let selected_status = self
+            .file_list_state
+            .iter()
+            .flat_map(|f| f.files.iter())
+            .filter(|f| f.selected)
+            .fold(Status::empty(), |acc, f| acc | f.status);
+        if partial && selected_status.intersects(Status::WT_NEW | Status::IGNORED) {
+            self.report(Err(
+                "Stash failed: untracked or ignored files can't be stashed together with single hunks; stash them separately".to_string(),
+            ));
+            self.show_message_input = false;
+            self.message_input = MessageInputState::new();
+            return;
         }
+        let result = if partial {
+            let files: Vec<FileSelection> = self
+                .file_list_state
+                .iter()
+                .flat_map(|f| f.files.iter())
+                .filter(|f| f.selected)
+                .map(|f| FileSelection { path: &f.path, hunks: f.hunks.as_deref() })
+                .collect();
+            hunks::stash_selection(&self.repo, &files, &message).map(|_| None)
+        } else {
+            let mut opts = StashSaveOptions::new(signature);
+            for path in &selected_paths {
+                opts.pathspec(path);
+            }
+            let mut flags = git2::StashFlags::DEFAULT;
+            if selected_status.contains(Status::WT_NEW) {
+                flags |= git2::StashFlags::INCLUDE_UNTRACKED;
+            }
+            if selected_status.contains(Status::IGNORED) {
+                flags |= git2::StashFlags::INCLUDE_IGNORED;
+            }
+            opts.flags(Some(flags));
+            // git2 has no way to pass the message here, so put it on the new stash afterwards;
+            // the stash exists by then, so a failed rename only costs the message
+            self.repo.stash_save_ext(Some(&mut opts)).map(|_| {
+                hunks::current_branch(&self.repo)
+                    .and_then(|branch| Self::try_rename_stash(&self.repo, 0, &format!("On {}: {}", branch, message)))
+                    .err()
+            })
+        };
 
         // Execute stash creation
-        match self.repo.stash_save_ext(Some(&mut opts)) {
-            Ok(_oid) => {
+        match result {
+            Ok(rename_error) => {
                 // Success!
+                if typed {
+                    let _ = history::record_message(message.trim());
+                }
                 let count = selected_paths.len();
-                self.status_message = Some(format!("Stashed {} file(s): {}", count, message));
+                let mut text = if partial {
+                    format!("Stashed selected hunks from {} file(s): {}", count, message)
+                } else {
+                    format!("Stashed {} file(s): {}", count, message)
+                };
+                if let Some(e) = rename_error {
+                    text.push_str(&format!(" (but it kept git's default message: {})", friendly_error_message(&e)));
+                }
+                self.report(Ok(text));
+                if let Some(ref mut status) = self.status_message {
+                    status.push_str(&format!(" ({}: show it)", self.config.keymap.key_label(Action::ShowCreated)));
+                }
 
                 // Refresh file list to show updated working directory
                 self.refresh_file_list();
 
-                // Refresh stash list for Manage tab
-                self.stashes = Self::load_stashes(&mut self.repo);
-
-                // Update stash selection (new stash is at index 0)
-                if !self.stashes.is_empty() {
-                    self.stash_list_state.select(Some(0));
-                    self.diff_content = Self::get_stash_diff(&self.repo, self.stashes[0].oid);
-                    self.diff_scroll = 0;
-                }
+                // The new stash is stash@{0}; the Manage tab stays on the stash it showed
+                self.reload_stashes_following(Some(0));
+                self.created_stash = self.stashes.first().map(|s| s.oid);
             }
             Err(e) => {
-                self.status_message = Some(format!("Stash creation failed: {}", friendly_error_message(&e)));
+                self.report(Err(format!("Stash creation failed: {}", friendly_error_message(&e))));
             }
         }
 
@@ -1272,3 +5166,116 @@ impl App {
         self.message_input = MessageInputState::new();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hunks::test_repo;
+
+    /// Change `file` and stash it with git's default message
+    fn stash_change(repo: &mut git2::Repository, file: &str, content: &str) -> git2::Oid {
+        std::fs::write(repo.workdir().unwrap().join(file), content).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.stash_save(&signature, "", None).unwrap()
+    }
+
+    /// Each stash's commit and reflog message, newest first
+    fn stash_log(repo: &git2::Repository) -> Vec<(git2::Oid, String)> {
+        let reflog = repo.reflog("refs/stash").unwrap();
+        reflog.iter().map(|e| (e.id_new(), e.message().unwrap_or_default().to_string())).collect()
+    }
+
+    /// `git stash list` as commit and subject pairs
+    fn git_stash_list(dir: &std::path::Path) -> Vec<(git2::Oid, String)> {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["stash", "list", "--format=%H %gs"])
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let (oid, message) = line.split_once(' ').unwrap();
+                (git2::Oid::from_str(oid).unwrap(), message.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rename_keeps_the_other_stashes() {
+        let (dir, mut repo) = test_repo(&[("a.txt", "one\n")]);
+        for content in ["first\n", "second\n", "third\n"] {
+            stash_change(&mut repo, "a.txt", content);
+        }
+
+        for index in [0, 2] {
+            let before = stash_log(&repo);
+            let message = format!("On master: renamed {}", index);
+            App::try_rename_stash(&repo, index, &message).unwrap();
+            let after = stash_log(&repo);
+
+            assert_eq!(after.len(), before.len());
+            for (i, (old, new)) in before.iter().zip(&after).enumerate() {
+                if i == index {
+                    assert_ne!(new.0, old.0);
+                    assert_eq!(new.1, message);
+                    let (old, new) = (repo.find_commit(old.0).unwrap(), repo.find_commit(new.0).unwrap());
+                    assert_eq!(new.message(), Some(message.as_str()));
+                    assert_eq!(new.tree_id(), old.tree_id());
+                    assert_eq!(new.parent_ids().collect::<Vec<_>>(), old.parent_ids().collect::<Vec<_>>());
+                } else {
+                    assert_eq!(new, old);
+                }
+            }
+            assert_eq!(repo.refname_to_id("refs/stash").unwrap(), after[0].0);
+
+            let loaded: Vec<(git2::Oid, String)> =
+                App::load_stashes(&mut repo).into_iter().map(|s| (s.oid, s.message)).collect();
+            let listed = git_stash_list(dir.path());
+            assert_eq!(listed, after);
+            assert_eq!(loaded.iter().map(|(oid, _)| *oid).collect::<Vec<_>>(), listed.iter().map(|(oid, _)| *oid).collect::<Vec<_>>());
+            assert!(loaded[index].1.contains(&format!("renamed {}", index)));
+        }
+    }
+
+    #[test]
+    fn renamed_stashes_are_not_listed_as_dropped() {
+        let (_dir, mut repo) = test_repo(&[("a.txt", "one\n")]);
+        stash_change(&mut repo, "a.txt", "two\n");
+        App::try_rename_stash(&repo, 0, "On master: renamed").unwrap();
+
+        let live: Vec<git2::Oid> = App::load_stashes(&mut repo).iter().map(|s| s.oid).collect();
+        assert!(recover::dropped_stashes(&repo, &live, 10).unwrap().is_empty());
+
+        repo.stash_drop(0).unwrap();
+        assert!(!recover::dropped_stashes(&repo, &[], 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn drop_confirmation_follows_the_stash_when_the_list_shifts() {
+        let (dir, mut repo) = test_repo(&[("a.txt", "one\n")]);
+        stash_change(&mut repo, "a.txt", "older\n");
+        let target = stash_change(&mut repo, "a.txt", "newer\n");
+        let mut app = App::new(git2::Repository::open(dir.path()).unwrap(), Config::default());
+        app.initiate_drop_stash();
+        assert_eq!(app.confirm_stashes, [target]);
+
+        // Another terminal pushes a stash while the popup is open, moving the target to stash@{1}
+        let pushed = stash_change(&mut repo, "a.txt", "elsewhere\n");
+        app.confirm_drop_stash();
+        let left: Vec<git2::Oid> = App::load_stashes(&mut repo).iter().map(|s| s.oid).collect();
+        assert_eq!(left.len(), 2);
+        assert!(left.contains(&pushed) && !left.contains(&target));
+
+        // A stash dropped elsewhere aborts the whole drop
+        app.initiate_drop_stash();
+        let selected = app.confirm_stashes[0];
+        let index = App::load_stashes(&mut repo).iter().position(|s| s.oid == selected).unwrap();
+        repo.stash_drop(index).unwrap();
+        app.confirm_drop_stash();
+        assert_eq!(App::load_stashes(&mut repo).len(), 1);
+        assert!(app.status_message.as_deref().unwrap().contains("nothing was dropped"));
+    }
+}
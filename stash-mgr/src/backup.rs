@@ -0,0 +1,51 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use git2::Repository;
+
+use crate::app::{App, friendly_error_message, local_date_time, unix_now};
+
+/// Name of the file listing what each patch in a backup holds
+const MANIFEST: &str = "MANIFEST";
+
+/// Write every stash into `dir` as `stash-N.patch`, untracked files included, plus a
+/// `MANIFEST` with each one's base commit, date, author and message, so the stashes can be
+/// rebuilt after a re-clone or on another machine. `dir` is created and must not hold
+/// anything yet. Returns how many stashes were written.
+pub fn write(repo: &mut Repository, dir: &Path) -> Result<usize, String> {
+    let stashes = App::load_stashes(repo);
+    if stashes.is_empty() {
+        return Err("there are no stashes to back up".to_string());
+    }
+    if std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(format!("{} already exists and is not empty", dir.display()));
+    }
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let (date, time) = local_date_time(unix_now());
+    let mut manifest = format!(
+        "# stash-mgr backup of {}, {} {}\n\
+         # To restore a stash: check out its base commit, `git apply <patch>`,\n\
+         # then `git stash push --include-untracked -m <message>`.\n\
+         # file\tbase commit\tdate\tauthor\tmessage\n",
+        repo.workdir().unwrap_or(repo.path()).components().collect::<std::path::PathBuf>().display(),
+        date,
+        time
+    );
+    for stash in &stashes {
+        let file = format!("stash-{}.patch", stash.index);
+        let base = repo
+            .find_commit(stash.oid)
+            .and_then(|commit| commit.parent_id(0))
+            .map_err(|e| format!("stash@{{{}}}: {}", stash.index, friendly_error_message(&e)))?;
+        let patch = App::stash_patch(repo, stash.oid).map_err(|e| format!("stash@{{{}}}: {}", stash.index, friendly_error_message(&e)))?;
+        let path = dir.join(&file);
+        std::fs::write(&path, patch).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let (date, time) = local_date_time(stash.time);
+        let _ = writeln!(manifest, "{}\t{}\t{} {}\t{}\t{}", file, base, date, &time[..5], stash.author, stash.message);
+    }
+    let path = dir.join(MANIFEST);
+    std::fs::write(&path, manifest).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(stashes.len())
+}
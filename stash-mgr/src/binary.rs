@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use git2::{Delta, DiffDelta, DiffFile, Repository};
+
+/// Extensions whose content is read to show image dimensions
+const IMAGE_EXTENSIONS: &[&str] = &["png", "gif", "jpg", "jpeg", "bmp"];
+
+/// A byte count as `812 B`, `12.4 KB` or `3.1 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Width and height of a PNG, GIF, JPEG or BMP image, read from its header
+pub fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le16 = |at: usize| Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?));
+    let le32 = |at: usize| Some(i32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?).unsigned_abs());
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") && data.get(12..16) == Some(b"IHDR") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(b"BM") {
+        return Some((le32(18)?, le32(22)?));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        // Walk the segments to the frame header (SOFn), which holds the size
+        let mut at = 2;
+        while at + 4 <= data.len() {
+            if data[at] != 0xFF {
+                return None;
+            }
+            let marker = data[at + 1];
+            if marker == 0xFF {
+                at += 1; // fill byte
+                continue;
+            }
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Some((be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// Whether a path has an image extension whose dimensions can be shown
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Size and, for images, dimensions of one side of a change, e.g. `12.4 KB, 64×32`
+fn describe(repo: &Repository, file: &DiffFile) -> Option<String> {
+    let path = file.path()?;
+    let is_image = is_image(path);
+
+    // The working directory side of a diff has no blob yet
+    let content = match (file.id().is_zero(), is_image) {
+        (true, true) => std::fs::read(repo.workdir()?.join(path)).ok()?,
+        (true, false) => return Some(format_size(std::fs::metadata(repo.workdir()?.join(path)).ok()?.len())),
+        (false, true) => repo.find_blob(file.id()).ok()?.content().to_vec(),
+        (false, false) => {
+            let (size, _) = repo.odb().ok()?.read_header(file.id()).ok()?;
+            return Some(format_size(size as u64));
+        }
+    };
+
+    let size = format_size(content.len() as u64);
+    Some(match image_dimensions(&content).filter(|_| is_image) {
+        Some((width, height)) => format!("{}, {}×{}", size, width, height),
+        None => size,
+    })
+}
+
+/// The line shown for a binary file in place of git's `Binary files a/x and b/x differ`,
+/// e.g. `Binary file changed (12.4 KB → 13.1 KB)`
+pub fn change_line(repo: &Repository, delta: &DiffDelta) -> String {
+    match delta.status() {
+        Delta::Added | Delta::Untracked | Delta::Ignored => match describe(repo, &delta.new_file()) {
+            Some(new) => format!("Binary file added ({})", new),
+            None => "Binary file added".to_string(),
+        },
+        Delta::Deleted => match describe(repo, &delta.old_file()) {
+            Some(old) => format!("Binary file deleted ({})", old),
+            None => "Binary file deleted".to_string(),
+        },
+        _ => match (describe(repo, &delta.old_file()), describe(repo, &delta.new_file())) {
+            (Some(old), Some(new)) => format!("Binary file changed ({} → {})", old, new),
+            _ => "Binary file changed".to_string(),
+        },
+    }
+}
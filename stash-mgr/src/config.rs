@@ -0,0 +1,326 @@
+use std::path::PathBuf;
+
+use crate::keymap::{Action, KeyBinding, Keymap};
+
+/// Settings read from `config.toml`; everything has a default
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub keymap: Keymap,
+    /// Stashes older than this many days are shown as stale; 0 turns it off
+    pub stale_after_days: u64,
+    /// Prefilled into the stash message popup, with `{branch}`, `{date}` and `{time}` filled in
+    pub message_template: String,
+    /// Leave submodules out of the Create file list, so they are never stashed
+    pub exclude_submodules: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            keymap: Keymap::default(),
+            stale_after_days: 30,
+            message_template: String::new(),
+            exclude_submodules: false,
+        }
+    }
+}
+
+/// Placeholders a message template may use
+const TEMPLATE_PLACEHOLDERS: &[&str] = &["branch", "date", "time"];
+
+/// Check that every `{name}` in a message template is a known placeholder
+fn check_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            return Err("'message_template' has a '{' without a closing '}'".to_string());
+        };
+        let name = &rest[start + 1..start + end];
+        if !TEMPLATE_PLACEHOLDERS.contains(&name) {
+            return Err(format!("unknown placeholder '{{{}}}' in 'message_template' (use {{branch}}, {{date}} or {{time}})", name));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// A value on the right of `key = value`
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+/// One `key = value` line with the table it appeared under
+struct Entry {
+    table: String,
+    key: String,
+    value: Value,
+    line: usize,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/stash-mgr/config.toml`, falling back to `~/.config/stash-mgr/config.toml`
+    pub fn path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("stash-mgr").join("config.toml"))
+    }
+
+    /// Load the config file, or the defaults if there is none
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        for entry in parse_entries(text)? {
+            let at = |message: String| format!("line {}: {}", entry.line, message);
+            match entry.table.as_str() {
+                "keys" => {
+                    let action: Action = entry.key.parse().map_err(|_| at(format!("unknown action '{}'", entry.key)))?;
+                    let keys = match entry.value {
+                        Value::String(key) => vec![key],
+                        Value::Array(values) => values
+                            .into_iter()
+                            .map(|v| match v {
+                                Value::String(key) => Ok(key),
+                                other => Err(at(format!("keys must be strings, not {}", other.type_name()))),
+                            })
+                            .collect::<Result<_, _>>()?,
+                        other => return Err(at(format!("'{}' must be a key or a list of keys, not {}", entry.key, other.type_name()))),
+                    };
+                    let keys = keys
+                        .iter()
+                        .map(|key| key.parse::<KeyBinding>().map_err(&at))
+                        .collect::<Result<_, _>>()?;
+                    config.keymap.bind(action, keys);
+                }
+                "stashes" => match (entry.key.as_str(), entry.value) {
+                    ("stale_after_days", Value::Integer(days)) => {
+                        config.stale_after_days = u64::try_from(days).map_err(|_| at("'stale_after_days' can't be negative".to_string()))?;
+                    }
+                    ("stale_after_days", other) => {
+                        return Err(at(format!("'stale_after_days' must be an integer, not {}", other.type_name())));
+                    }
+                    ("message_template", Value::String(template)) => {
+                        check_template(&template).map_err(&at)?;
+                        config.message_template = template;
+                    }
+                    ("message_template", other) => {
+                        return Err(at(format!("'message_template' must be a string, not {}", other.type_name())));
+                    }
+                    ("exclude_submodules", Value::Boolean(exclude)) => config.exclude_submodules = exclude,
+                    ("exclude_submodules", other) => {
+                        return Err(at(format!("'exclude_submodules' must be true or false, not {}", other.type_name())));
+                    }
+                    (key, _) => return Err(at(format!("unknown setting '{}' in [stashes]", key))),
+                },
+                "" => return Err(at(format!("'{}' must be inside a table such as [keys]", entry.key))),
+                table => return Err(at(format!("unknown table [{}]", table))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Read the small subset of TOML the config uses: `[table]` headers, `key = value` lines
+/// with strings, integers, booleans or one-line arrays, and `#` comments.
+fn parse_entries(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let at = |message: String| format!("line {}: {}", line, message);
+        let content = raw.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = content.strip_prefix('[') {
+            let (name, rest) = header.split_once(']').ok_or_else(|| at("unclosed table header".to_string()))?;
+            if !is_comment_or_empty(rest) {
+                return Err(at(format!("unexpected text after [{}]", name)));
+            }
+            table = name.trim().to_string();
+            continue;
+        }
+
+        let (key, value) = content.split_once('=').ok_or_else(|| at(format!("expected key = value, found '{}'", content)))?;
+        let key = key.trim().trim_matches('"').to_string();
+        let (value, rest) = parse_value(value.trim_start()).map_err(&at)?;
+        if !is_comment_or_empty(rest) {
+            return Err(at(format!("unexpected text after the value of '{}'", key)));
+        }
+        entries.push(Entry { table: table.clone(), key, value, line });
+    }
+    Ok(entries)
+}
+
+fn is_comment_or_empty(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Parse a value at the start of `input`, returning it and the unparsed remainder
+fn parse_value(input: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = input.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(value), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(other) => return Err(format!("unsupported escape '\\{}'", other)),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+    if let Some(rest) = input.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or_else(|| "unterminated string".to_string())?;
+        return Ok((Value::String(rest[..end].to_string()), &rest[end + 1..]));
+    }
+    if let Some(mut rest) = input.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(values), after));
+            }
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected ',' or ']' in array".to_string());
+            }
+        }
+    }
+
+    let end = input.find(|c: char| c.is_whitespace() || c == ',' || c == ']' || c == '#').unwrap_or(input.len());
+    let (word, rest) = input.split_at(end);
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => Value::Integer(word.replace('_', "").parse().map_err(|_| format!("invalid value '{}'", word))?),
+    };
+    Ok((value, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(input: &str) -> Value {
+        let (value, rest) = parse_value(input).unwrap();
+        assert!(is_comment_or_empty(rest), "'{}' left '{}'", input, rest);
+        value
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn parses_strings_with_escapes() {
+        assert_eq!(value(r#""plain""#), string("plain"));
+        assert_eq!(value(r#""say \"hi\"\tand\\n""#), string("say \"hi\"\tand\\n"));
+        assert_eq!(value(r#""a # b" # comment"#), string("a # b"));
+        assert_eq!(parse_value(r#""\q""#).unwrap_err(), "unsupported escape '\\q'");
+        assert_eq!(parse_value(r#""open"#).unwrap_err(), "unterminated string");
+        assert_eq!(parse_value(r#""trailing\"#).unwrap_err(), "unterminated string");
+    }
+
+    #[test]
+    fn single_quoted_strings_are_literal() {
+        assert_eq!(value(r"'C:\path\n'"), string(r"C:\path\n"));
+        assert_eq!(value("'it\"s'"), string("it\"s"));
+        assert_eq!(parse_value("'open").unwrap_err(), "unterminated string");
+    }
+
+    #[test]
+    fn parses_scalars_and_arrays() {
+        assert_eq!(value("42"), Value::Integer(42));
+        assert_eq!(value("-1_000"), Value::Integer(-1000));
+        assert_eq!(value("true # yes"), Value::Boolean(true));
+        assert_eq!(value("false"), Value::Boolean(false));
+        assert_eq!(value("[]"), Value::Array(vec![]));
+        assert_eq!(value(r#"["j", 'down', ]"#), Value::Array(vec![string("j"), string("down")]));
+        assert_eq!(value(r#"[ "]", [1, 2] ] # nested"#), Value::Array(vec![string("]"), Value::Array(vec![Value::Integer(1), Value::Integer(2)])]));
+        assert_eq!(parse_value(r#"["a" "b"]"#).unwrap_err(), "expected ',' or ']' in array");
+        assert_eq!(parse_value("yes").unwrap_err(), "invalid value 'yes'");
+    }
+
+    #[test]
+    fn parses_tables_entries_and_comments() {
+        let text = "# leading comment\n\n[keys] # trailing\nquit = \"Q\" # after value\n  \"down\" = [\"j\", \"ctrl+n\"]\n[stashes]\nstale_after_days = 7\n";
+        let entries = parse_entries(text).unwrap();
+        let summary: Vec<(&str, &str, &Value, usize)> =
+            entries.iter().map(|e| (e.table.as_str(), e.key.as_str(), &e.value, e.line)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("keys", "quit", &string("Q"), 4),
+                ("keys", "down", &Value::Array(vec![string("j"), string("ctrl+n")]), 5),
+                ("stashes", "stale_after_days", &Value::Integer(7), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        assert_eq!(parse_entries("[keys\n").err().unwrap(), "line 1: unclosed table header");
+        assert_eq!(parse_entries("[keys] x\n").err().unwrap(), "line 1: unexpected text after [keys]");
+        assert_eq!(parse_entries("\n[keys]\nquit\n").err().unwrap(), "line 3: expected key = value, found 'quit'");
+        assert_eq!(parse_entries("quit = \"q\" \"w\"\n").err().unwrap(), "line 1: unexpected text after the value of 'quit'");
+    }
+
+    #[test]
+    fn applies_settings_and_rejects_unknown_names() {
+        let config = Config::parse("[keys]\nquit = [\"Q\", \"ctrl+c\"]\n[stashes]\nstale_after_days = 0\nexclude_submodules = true\nmessage_template = 'WIP {branch}'\n").unwrap();
+        assert_eq!(config.keymap.keys_label(Action::Quit), "Q / Ctrl+c");
+        assert_eq!(config.stale_after_days, 0);
+        assert!(config.exclude_submodules);
+        assert_eq!(config.message_template, "WIP {branch}");
+
+        assert_eq!(Config::parse("[colors]\nfg = 1\n").err().unwrap(), "line 2: unknown table [colors]");
+        assert_eq!(Config::parse("[keys]\nfly = \"f\"\n").err().unwrap(), "line 2: unknown action 'fly'");
+        assert_eq!(Config::parse("[keys]\nquit = \"hyper+q\"\n").err().unwrap(), "line 2: unknown modifier 'hyper' in 'hyper+q'");
+        assert_eq!(Config::parse("[keys]\nquit = 1\n").err().unwrap(), "line 2: 'quit' must be a key or a list of keys, not an integer");
+        assert_eq!(Config::parse("[stashes]\ncolour = 1\n").err().unwrap(), "line 2: unknown setting 'colour' in [stashes]");
+        assert_eq!(Config::parse("quit = \"q\"\n").err().unwrap(), "line 1: 'quit' must be inside a table such as [keys]");
+        assert_eq!(
+            Config::parse("[stashes]\nmessage_template = \"{user}\"\n").err().unwrap(),
+            "line 2: unknown placeholder '{user}' in 'message_template' (use {branch}, {date} or {time})"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use git2::{DiffOptions, Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+
+/// Why applying a stash would not go cleanly for a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConflictKind {
+    /// Both the stash and the index changed the same lines; the file gets conflict markers
+    Content,
+    /// The stash's staged changes clash with the current index (`--index` only)
+    Index,
+    /// Unstaged local changes to a file the stash modifies; the apply would be refused
+    LocalChanges,
+    /// A file the stash stored as untracked already exists
+    UntrackedExists,
+}
+
+impl fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConflictKind::Content => "conflict",
+            ConflictKind::Index => "staged changes conflict",
+            ConflictKind::LocalChanges => "local changes would be overwritten",
+            ConflictKind::UntrackedExists => "untracked file already exists",
+        })
+    }
+}
+
+/// A file that would not apply cleanly
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    pub path: String,
+    pub kind: ConflictKind,
+}
+
+/// Paths of the conflicted entries after merging `theirs` into `ours`
+fn merge_conflicts(repo: &Repository, ancestor: &Tree, ours: &Tree, theirs: &Tree) -> Result<Vec<String>, git2::Error> {
+    let index = repo.merge_trees(ancestor, ours, theirs, None)?;
+    let mut paths = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+        }
+    }
+    Ok(paths)
+}
+
+/// The current index as a tree, and that tree with the stash's changes merged in as
+/// `git stash apply` would; conflicted files keep their current contents
+pub fn applied_tree(repo: &Repository, stash_oid: Oid) -> Result<(Tree<'_>, Tree<'_>), git2::Error> {
+    /// Stage bits of an index entry's flags (0 outside a conflict)
+    const STAGE_MASK: u16 = 0x3000;
+
+    let stash = repo.find_commit(stash_oid)?;
+    let base = stash.parent(0)?.tree()?;
+    let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let mut merged = repo.merge_trees(&base, &index_tree, &stash.tree()?, None)?;
+
+    let conflicts = merged.conflicts()?.collect::<Result<Vec<_>, _>>()?;
+    for conflict in conflicts {
+        let Some(path) = conflict.our.as_ref().or(conflict.their.as_ref()).or(conflict.ancestor.as_ref()).map(|e| e.path.clone())
+        else {
+            continue;
+        };
+        merged.conflict_remove(Path::new(&*String::from_utf8_lossy(&path)))?;
+        if let Some(mut ours) = conflict.our {
+            ours.flags &= !STAGE_MASK;
+            merged.add(&ours)?;
+        }
+    }
+    let applied = repo.find_tree(merged.write_tree_to(repo)?)?;
+    Ok((index_tree, applied))
+}
+
+/// Predict the files that would conflict if the stash were applied now, without touching
+/// the index or working directory. This follows what `git stash apply` does: the stash is
+/// merged into the index, and checkout refuses to overwrite unstaged changes.
+pub fn predict(repo: &Repository, stash_oid: Oid, restore_index: bool) -> Result<Vec<Conflict>, git2::Error> {
+    let stash = repo.find_commit(stash_oid)?;
+    let base = stash.parent(0)?.tree()?;
+    let stash_tree = stash.tree()?;
+    let mut repo_index = repo.index()?;
+    let index_tree = repo.find_tree(repo_index.write_tree()?)?;
+
+    // Later kinds for the same path are less informative than earlier ones
+    let mut found: BTreeMap<String, ConflictKind> = BTreeMap::new();
+    let mut add = |path: String, kind: ConflictKind| {
+        found.entry(path).and_modify(|k| *k = (*k).min(kind)).or_insert(kind);
+    };
+
+    for path in merge_conflicts(repo, &base, &index_tree, &stash_tree)? {
+        add(path, ConflictKind::Content);
+    }
+
+    if restore_index {
+        let stashed_index = stash.parent(1)?.tree()?;
+        if stashed_index.id() != base.id() {
+            for path in merge_conflicts(repo, &base, &index_tree, &stashed_index)? {
+                add(path, ConflictKind::Index);
+            }
+        }
+    }
+
+    // Files the stash changes that also have unstaged changes
+    let stash_diff = repo.diff_tree_to_tree(Some(&base), Some(&stash_tree), None)?;
+    let mut opts = DiffOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let local_diff = repo.diff_index_to_workdir(Some(&repo_index), Some(&mut opts))?;
+    let local: Vec<_> = local_diff.deltas().filter_map(|d| d.new_file().path().map(|p| p.to_path_buf())).collect();
+    for delta in stash_diff.deltas() {
+        if let Some(path) = delta.new_file().path().or(delta.old_file().path())
+            && local.iter().any(|p| p == path)
+        {
+            add(path.to_string_lossy().into_owned(), ConflictKind::LocalChanges);
+        }
+    }
+
+    // Untracked files stored by `git stash -u` are restored only where nothing exists
+    if let Ok(untracked) = stash.parent(2) {
+        let workdir = repo.workdir().ok_or_else(|| git2::Error::from_str("repository has no working directory"))?;
+        untracked.tree()?.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob)
+                && let Some(name) = entry.name()
+            {
+                let path = format!("{}{}", dir, name);
+                if workdir.join(&path).symlink_metadata().is_ok() {
+                    add(path, ConflictKind::UntrackedExists);
+                }
+            }
+            TreeWalkResult::Ok
+        })?;
+    }
+
+    Ok(found.into_iter().map(|(path, kind)| Conflict { path, kind }).collect())
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use git2::Oid;
+
+use crate::app::{App, DiffSummary, friendly_error_message};
+use crate::worker::LatestWorker;
+
+/// A finished stash preview
+pub struct LoadedDiff {
+    pub diff: String,
+    pub summary: Option<DiffSummary>,
+    pub stat: String,
+}
+
+/// Builds stash previews on a worker thread so a huge stash doesn't stall navigation.
+/// Only the latest request matters: older ones still queued are skipped, and work in
+/// progress is abandoned between steps once a newer request (or a cancel) comes in.
+pub struct DiffLoader {
+    /// Takes the stash, and whether to diff it against the current index instead of its parent
+    worker: LatestWorker<(Oid, bool), LoadedDiff>,
+}
+
+impl DiffLoader {
+    pub fn new(repo_path: &Path) -> Self {
+        let worker = LatestWorker::new(repo_path, |repo, (oid, against_current), job| {
+            let repo = match repo {
+                Ok(repo) => repo,
+                Err(e) => {
+                    let diff = format!("Failed to generate diff: {}", friendly_error_message(e));
+                    job.send(LoadedDiff { stat: diff.clone(), diff, summary: None });
+                    return;
+                }
+            };
+            if against_current {
+                let (diff, stat, summary) = App::get_diff_against_current(repo, oid);
+                job.send(LoadedDiff { diff, summary, stat });
+                return;
+            }
+            let diff = App::get_stash_diff(repo, oid);
+            if job.stale() {
+                return;
+            }
+            let summary = App::stash_summary(repo, oid).ok();
+            if job.stale() {
+                return;
+            }
+            let stat = App::get_stash_stat(repo, oid);
+            job.send(LoadedDiff { diff, summary, stat });
+        });
+
+        Self { worker }
+    }
+
+    /// Start building the preview of a stash, or of applying it now, replacing any earlier request
+    pub fn request(&self, oid: Oid, against_current: bool) {
+        self.worker.request((oid, against_current));
+    }
+
+    /// Drop the pending request, e.g. when no stash is selected any more
+    pub fn cancel(&self) {
+        self.worker.cancel();
+    }
+
+    /// The preview for the latest request, once it is ready
+    pub fn try_recv(&self) -> Option<LoadedDiff> {
+        self.worker.ready().pop()
+    }
+}
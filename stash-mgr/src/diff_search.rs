@@ -0,0 +1,85 @@
+use std::ops::Range;
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+use crate::app::{SEARCH_CURRENT_BG, SEARCH_MATCH_BG};
+
+/// One occurrence of the search text: its line in the diff and byte range in that line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub line: usize,
+    pub range: Range<usize>,
+}
+
+/// Text searched for in the diff previews, and the match last jumped to
+#[derive(Default)]
+pub struct DiffSearch {
+    pub query: String,
+    pub current: Option<Match>,
+}
+
+impl DiffSearch {
+    /// Byte ranges of the query in `line`, ignoring ASCII case
+    pub fn line_matches(&self, line: &str) -> Vec<Range<usize>> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+        // ASCII lowercasing keeps byte offsets valid in the original line
+        let needle = self.query.to_ascii_lowercase();
+        line.to_ascii_lowercase().match_indices(&needle).map(|(at, m)| at..at + m.len()).collect()
+    }
+
+    /// Every match in `content`, top to bottom
+    pub fn matches(&self, content: &str) -> Vec<Match> {
+        content
+            .lines()
+            .enumerate()
+            .flat_map(|(line, text)| self.line_matches(text).into_iter().map(move |range| Match { line, range }))
+            .collect()
+    }
+
+    /// The current match's byte range if it is on `line`
+    pub fn current_on(&self, line: usize) -> Option<Range<usize>> {
+        self.current.as_ref().filter(|m| m.line == line).map(|m| m.range.clone())
+    }
+
+    /// `line` (line number `number` of the diff) with its matches on the search background,
+    /// the current one brighter; spans keep their colors otherwise
+    pub fn highlight(&self, line: Line<'_>, number: usize) -> Line<'static> {
+        let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        let found = self.line_matches(&text);
+        let current = self.current_on(number);
+
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        let mut offset = 0;
+        for span in &line.spans {
+            for (i, c) in span.content.char_indices() {
+                let at = offset + i;
+                let style = if current.as_ref().is_some_and(|r| r.contains(&at)) {
+                    current_style()
+                } else if found.iter().any(|r| r.contains(&at)) {
+                    match_style(span.style)
+                } else {
+                    span.style
+                };
+                match spans.last_mut() {
+                    Some(last) if last.style == style => last.content.to_mut().push(c),
+                    _ => spans.push(Span::styled(c.to_string(), style)),
+                }
+            }
+            offset += span.content.len();
+        }
+        Line::from(spans).style(line.style)
+    }
+}
+
+/// A match of the search, keeping the text's own color
+pub fn match_style(base: Style) -> Style {
+    base.bg(SEARCH_MATCH_BG)
+}
+
+/// The match last jumped to
+pub fn current_style() -> Style {
+    Style::default().fg(Color::Black).bg(SEARCH_CURRENT_BG)
+}
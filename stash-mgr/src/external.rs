@@ -0,0 +1,64 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use git2::{Oid, Repository};
+
+/// An external program to hand the terminal to
+pub enum Launch {
+    /// Show a patch in the user's pager
+    Pager(Vec<u8>),
+    /// Run `git difftool` for each `(base, target, paths)` in turn; no paths means all
+    Difftool(Vec<(Oid, Oid, Vec<String>)>),
+}
+
+/// The pager git would use: `$GIT_PAGER`, `core.pager`, `$PAGER`, then `less -R`
+fn pager_command(repo: &Repository) -> String {
+    let env = |name| std::env::var(name).ok().filter(|v: &String| !v.trim().is_empty());
+    env("GIT_PAGER")
+        .or_else(|| repo.config().ok().and_then(|c| c.get_string("core.pager").ok()).filter(|v| !v.trim().is_empty()))
+        .or_else(|| env("PAGER"))
+        .unwrap_or_else(|| "less -R".to_string())
+}
+
+/// Pipe a patch into the pager through the shell, as git does
+fn run_pager(command: &str, patch: &[u8]) -> io::Result<()> {
+    let mut child = Command::new("sh").arg("-c").arg(command).stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe; that is not an error
+        match stdin.write_all(patch) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// `git difftool --no-prompt <base> <target> [-- <paths>]` with the user's configured tool
+fn run_difftool(workdir: &Path, base: Oid, target: Oid, paths: &[String]) -> io::Result<()> {
+    let mut command = Command::new("git");
+    command.current_dir(workdir).args(["difftool", "--no-prompt", &base.to_string(), &target.to_string()]);
+    if !paths.is_empty() {
+        command.arg("--").args(paths);
+    }
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("git difftool exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Run the program in the foreground; the caller leaves and re-enters the TUI around it
+pub fn launch(repo: &Repository, launch: Launch) -> io::Result<()> {
+    match launch {
+        Launch::Pager(patch) => run_pager(&pager_command(repo), &patch),
+        Launch::Difftool(diffs) => {
+            let workdir = repo.workdir().unwrap_or(repo.path());
+            for (base, target, paths) in diffs {
+                run_difftool(workdir, base, target, &paths)?;
+            }
+            Ok(())
+        }
+    }
+}
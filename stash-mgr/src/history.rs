@@ -0,0 +1,68 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Repositories remembered for the picker
+const MAX_RECENT_REPOS: usize = 20;
+
+/// Stash messages remembered for the message popup
+const MAX_RECENT_MESSAGES: usize = 20;
+
+/// `$XDG_STATE_HOME/stash-mgr/<file>`, falling back to `~/.local/state/stash-mgr/<file>`
+fn path(file: &str) -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(base.join("stash-mgr").join(file))
+}
+
+/// Every remembered entry of a history file, most recent first
+fn read(file: &str) -> Vec<String> {
+    let Some(path) = path(file) else {
+        return Vec::new();
+    };
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Move an entry to the top of a history file, one entry per line, keeping at most `max`
+fn push(file: &str, entry: String, max: usize) -> io::Result<()> {
+    let Some(path) = path(file) else {
+        return Ok(());
+    };
+    let mut entries = read(file);
+    entries.retain(|e| *e != entry);
+    entries.insert(0, entry);
+    entries.truncate(max);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text: String = entries.iter().map(|e| format!("{}\n", e)).collect();
+    std::fs::write(path, text)
+}
+
+/// Recently used repositories that still exist, most recent first
+pub fn recent_repos() -> Vec<PathBuf> {
+    read("recent_repos").into_iter().map(PathBuf::from).filter(|repo| repo.is_dir()).collect()
+}
+
+/// Move a repository to the top of the history
+pub fn record_repo(repo: &Path) -> io::Result<()> {
+    let repo = repo.canonicalize()?;
+    push("recent_repos", repo.display().to_string(), MAX_RECENT_REPOS)
+}
+
+/// Messages of recently created stashes, most recent first
+pub fn recent_messages() -> Vec<String> {
+    read("recent_messages")
+}
+
+/// Move a stash message to the top of the history
+pub fn record_message(message: &str) -> io::Result<()> {
+    push("recent_messages", message.to_string(), MAX_RECENT_MESSAGES)
+}
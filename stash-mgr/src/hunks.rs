@@ -0,0 +1,316 @@
+use std::cell::Cell;
+use std::path::Path;
+
+use git2::build::CheckoutBuilder;
+use git2::{ApplyOptions, Diff, DiffFormat, DiffOptions, Oid, Repository};
+
+/// A single hunk of a working directory file's changes against HEAD
+#[derive(Clone, Debug)]
+pub struct Hunk {
+    pub header: String,
+    pub text: String,
+    pub selected: bool,
+}
+
+/// A file to stash and, when only part of it is chosen, which of its hunks
+pub struct FileSelection<'a> {
+    pub path: &'a str,
+    pub hunks: Option<&'a [bool]>,
+}
+
+/// Diff of HEAD against the working directory (through the index), the same changes
+/// `git stash -p` offers hunk by hunk
+fn diff_against_head<'r>(repo: &'r Repository, paths: &[&str]) -> Result<Diff<'r>, git2::Error> {
+    let mut opts = DiffOptions::new();
+    opts.disable_pathspec_match(true);
+    for path in paths {
+        opts.pathspec(path);
+    }
+    let head = repo.head()?.peel_to_tree()?;
+    repo.diff_tree_to_workdir_with_index(Some(&head), Some(&mut opts))
+}
+
+/// List the hunks of one file, all unselected
+pub fn file_hunks(repo: &Repository, path: &str) -> Result<Vec<Hunk>, git2::Error> {
+    let diff = diff_against_head(repo, &[path])?;
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    diff.print(DiffFormat::Patch, |_delta, hunk, line| {
+        let Some(hunk) = hunk else {
+            return true; // file header lines
+        };
+        let content = String::from_utf8_lossy(line.content());
+        match line.origin() {
+            'H' => hunks.push(Hunk {
+                header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                text: content.to_string(),
+                selected: false,
+            }),
+            origin @ (' ' | '+' | '-') => {
+                if let Some(current) = hunks.last_mut() {
+                    current.text.push(origin);
+                    current.text.push_str(&content);
+                }
+            }
+            _ => {
+                if let Some(current) = hunks.last_mut() {
+                    current.text.push_str(&content);
+                }
+            }
+        }
+        true
+    })?;
+
+    Ok(hunks)
+}
+
+/// Apply the chosen (or, with `invert`, the unchosen) hunks of `diff` onto `base`
+fn apply_selection(
+    repo: &Repository,
+    base: &git2::Tree,
+    diff: &Diff,
+    selections: &[Option<&[bool]>],
+    invert: bool,
+) -> Result<Oid, git2::Error> {
+    // Deltas and hunks are visited in diff order; track the position in both
+    let delta_index = Cell::new(usize::MAX);
+    let hunk_index = Cell::new(0usize);
+    let wanted = |delta: usize, hunk: Option<usize>| -> bool {
+        let chosen = match (selections.get(delta).copied().flatten(), hunk) {
+            (None, _) => true,
+            (Some(hunks), Some(hunk)) => hunks.get(hunk).copied().unwrap_or(false),
+            (Some(hunks), None) => {
+                // Skip the whole file when none of its hunks are wanted
+                return if invert { !hunks.iter().all(|h| *h) } else { hunks.iter().any(|h| *h) };
+            }
+        };
+        chosen != invert
+    };
+
+    let mut opts = ApplyOptions::new();
+    opts.delta_callback(|_delta| {
+        delta_index.set(delta_index.get().wrapping_add(1));
+        hunk_index.set(0);
+        wanted(delta_index.get(), None)
+    });
+    opts.hunk_callback(|_hunk| {
+        let hunk = hunk_index.get();
+        hunk_index.set(hunk + 1);
+        wanted(delta_index.get(), Some(hunk))
+    });
+
+    let mut index = repo.apply_to_tree(base, diff, Some(&mut opts))?;
+    index.write_tree_to(repo)
+}
+
+/// The checked out branch as stash messages name it, `(no branch)` when HEAD is detached
+pub fn current_branch(repo: &Repository) -> Result<String, git2::Error> {
+    Ok(match repo.head()?.shorthand() {
+        Some(name) if !repo.head_detached()? => name.to_string(),
+        _ => "(no branch)".to_string(),
+    })
+}
+
+/// Create a stash from the selected files and hunks, mirroring `git stash -p`: the stash
+/// records HEAD plus the chosen hunks, those hunks are reverted in the working directory,
+/// and the index is left as it was.
+pub fn stash_selection(repo: &Repository, files: &[FileSelection], message: &str) -> Result<Oid, git2::Error> {
+    let signature = repo.signature()?;
+    let head_commit = repo.head()?.peel_to_commit()?;
+    let head_tree = head_commit.tree()?;
+    let branch = current_branch(repo)?;
+
+    let paths: Vec<&str> = files.iter().map(|f| f.path).collect();
+    let diff = diff_against_head(repo, &paths)?;
+
+    // Line deltas up with the selections; refuse if a file's hunks changed since listing
+    let mut selections = Vec::new();
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        let path = delta.new_file().path().or(delta.old_file().path());
+        let file = files.iter().find(|f| path == Some(Path::new(f.path)));
+        let hunks = file.and_then(|f| f.hunks);
+        if let Some(hunks) = hunks {
+            let patch = git2::Patch::from_diff(&diff, delta_idx)?;
+            if patch.is_none_or(|p| p.num_hunks() != hunks.len()) {
+                return Err(git2::Error::from_str(&format!(
+                    "{} changed since its hunks were selected; reselect them",
+                    path.unwrap_or(Path::new("")).display()
+                )));
+            }
+        }
+        selections.push(hunks);
+    }
+    if selections.is_empty() {
+        return Err(git2::Error::from_str("the selected files have no changes against HEAD"));
+    }
+
+    // Index commit, then the working tree commit with HEAD and the index as parents
+    let short_head = head_commit.as_object().short_id()?;
+    let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let index_commit = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &format!(
+            "index on {}: {} {}",
+            branch,
+            short_head.as_str().unwrap_or_default(),
+            head_commit.summary().unwrap_or_default()
+        ),
+        &index_tree,
+        &[&head_commit],
+    )?;
+    let index_commit = repo.find_commit(index_commit)?;
+
+    let stash_tree = repo.find_tree(apply_selection(repo, &head_tree, &diff, &selections, false)?)?;
+    let stash_message = format!("On {}: {}", branch, message);
+    let stash_oid = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &stash_message,
+        &stash_tree,
+        &[&head_commit, &index_commit],
+    )?;
+
+    repo.reference_ensure_log("refs/stash")?;
+    repo.reference("refs/stash", stash_oid, true, &stash_message)?;
+
+    // Working directory keeps HEAD plus the hunks that were not stashed
+    let remaining = repo.find_tree(apply_selection(repo, &head_tree, &diff, &selections, true)?)?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().update_index(false).disable_pathspec_match(true);
+    let workdir = repo.workdir().ok_or_else(|| git2::Error::from_str("repository has no working directory"))?;
+    let mut restore = false;
+    for path in &paths {
+        if remaining.get_path(Path::new(path)).is_ok() {
+            checkout.path(path);
+            restore = true;
+        } else if let Err(e) = std::fs::remove_file(workdir.join(path))
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            return Err(git2::Error::from_str(&format!("could not remove {}: {}", path, e)));
+        }
+    }
+    // Without any paths a checkout would cover the whole tree
+    if restore {
+        repo.checkout_tree(remaining.as_object(), Some(&mut checkout))?;
+    }
+
+    Ok(stash_oid)
+}
+
+
+/// A repository in a temporary directory whose first commit holds `files`, for tests
+#[cfg(test)]
+pub fn test_repo(files: &[(&str, &str)]) -> (tempfile::TempDir, Repository) {
+    let dir = tempfile::TempDir::new().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Test").unwrap();
+    config.set_str("user.email", "test@example.com").unwrap();
+    for (path, content) in files {
+        std::fs::write(dir.path().join(path), content).unwrap();
+    }
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = repo.signature().unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[]).unwrap();
+    drop(tree);
+    (dir, repo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n17\n18\n19\n20\n";
+    const BOTH: &str = "one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n17\n18\n19\ntwenty\n";
+    const FIRST: &str = "one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n17\n18\n19\n20\n";
+    const SECOND: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n17\n18\n19\ntwenty\n";
+
+    fn stashed_file(repo: &Repository, stash: Oid, path: &str) -> Option<String> {
+        let tree = repo.find_commit(stash).unwrap().tree().unwrap();
+        let entry = tree.get_path(Path::new(path)).ok()?;
+        let blob = repo.find_blob(entry.id()).unwrap();
+        Some(String::from_utf8(blob.content().to_vec()).unwrap())
+    }
+
+    fn read(repo: &Repository, path: &str) -> String {
+        std::fs::read_to_string(repo.workdir().unwrap().join(path)).unwrap()
+    }
+
+    fn git(repo: &Repository, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(repo.workdir().unwrap())
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn stashes_only_the_chosen_hunks() {
+        let (_dir, repo) = test_repo(&[("a.txt", ORIGINAL)]);
+        std::fs::write(repo.workdir().unwrap().join("a.txt"), BOTH).unwrap();
+        assert_eq!(file_hunks(&repo, "a.txt").unwrap().len(), 2);
+
+        let chosen = [true, false];
+        let files = [FileSelection { path: "a.txt", hunks: Some(&chosen) }];
+        let stash = stash_selection(&repo, &files, "first hunk").unwrap();
+
+        assert_eq!(stashed_file(&repo, stash, "a.txt").as_deref(), Some(FIRST));
+        assert_eq!(read(&repo, "a.txt"), SECOND);
+        assert_eq!(repo.refname_to_id("refs/stash").unwrap(), stash);
+        let message = format!("On {}: first hunk", current_branch(&repo).unwrap());
+        assert_eq!(repo.find_commit(stash).unwrap().message(), Some(message.as_str()));
+    }
+
+    #[test]
+    fn stashing_a_staged_new_file_removes_it() {
+        let (_dir, repo) = test_repo(&[("a.txt", ORIGINAL)]);
+        std::fs::write(repo.workdir().unwrap().join("new.txt"), "new\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("new.txt")).unwrap();
+        index.write().unwrap();
+
+        let files = [FileSelection { path: "new.txt", hunks: None }];
+        let stash = stash_selection(&repo, &files, "new file").unwrap();
+
+        assert_eq!(stashed_file(&repo, stash, "new.txt").as_deref(), Some("new\n"));
+        assert!(!repo.workdir().unwrap().join("new.txt").exists());
+    }
+
+    #[test]
+    fn refuses_hunks_that_changed_since_listing() {
+        let (_dir, repo) = test_repo(&[("a.txt", ORIGINAL)]);
+        std::fs::write(repo.workdir().unwrap().join("a.txt"), BOTH).unwrap();
+
+        // Selected while only the first change existed
+        let chosen = [true];
+        let files = [FileSelection { path: "a.txt", hunks: Some(&chosen) }];
+        let error = stash_selection(&repo, &files, "stale").unwrap_err();
+
+        assert!(error.message().contains("changed since its hunks were selected"), "{}", error.message());
+        assert!(repo.refname_to_id("refs/stash").is_err());
+        assert_eq!(read(&repo, "a.txt"), BOTH);
+    }
+
+    #[test]
+    fn applying_the_stash_restores_the_chosen_hunks() {
+        let (_dir, repo) = test_repo(&[("a.txt", ORIGINAL)]);
+        std::fs::write(repo.workdir().unwrap().join("a.txt"), BOTH).unwrap();
+
+        let chosen = [false, true];
+        let files = [FileSelection { path: "a.txt", hunks: Some(&chosen) }];
+        stash_selection(&repo, &files, "second hunk").unwrap();
+        assert_eq!(read(&repo, "a.txt"), FIRST);
+
+        git(&repo, &["checkout", "--", "a.txt"]);
+        git(&repo, &["stash", "apply"]);
+        assert_eq!(read(&repo, "a.txt"), SECOND);
+    }
+}
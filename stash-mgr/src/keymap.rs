@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
+
+use crate::app::SelectedTab;
+
+/// Something a key can be bound to. Names are the snake_case keys of the `[keys]` table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum Action {
+    Quit,
+    NextTab,
+    PrevTab,
+    Down,
+    Up,
+    ScrollDown,
+    ScrollUp,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    ScrollLeft,
+    ScrollRight,
+    Wrap,
+    SideBySide,
+    Stat,
+    Top,
+    Bottom,
+    Toggle,
+    Hunks,
+    FileFilter,
+    Files,
+    Stash,
+    StashAll,
+    SelectAll,
+    SelectNone,
+    ShowCreated,
+    Apply,
+    ApplyIndex,
+    Pop,
+    PopIndex,
+    Drop,
+    Cleanup,
+    Recover,
+    Branch,
+    Worktree,
+    Commit,
+    Rename,
+    Compare,
+    AgainstCurrent,
+    Export,
+    Backup,
+    Pager,
+    Difftool,
+    Filter,
+    Sort,
+    Goto,
+    Group,
+    Search,
+    SearchNext,
+    SearchPrev,
+    Clear,
+    Log,
+    Help,
+}
+
+impl Action {
+    /// Whether the action does anything on the given tab
+    fn applies_to(self, tab: SelectedTab) -> bool {
+        match self {
+            Action::Hunks
+            | Action::FileFilter
+            | Action::Stash
+            | Action::StashAll
+            | Action::SelectAll
+            | Action::SelectNone
+            | Action::ShowCreated => tab == SelectedTab::Create,
+            Action::Apply
+            | Action::ApplyIndex
+            | Action::Pop
+            | Action::PopIndex
+            | Action::Drop
+            | Action::Cleanup
+            | Action::Recover
+            | Action::Files
+            | Action::Branch
+            | Action::Worktree
+            | Action::Commit
+            | Action::Rename
+            | Action::Compare
+            | Action::AgainstCurrent
+            | Action::Export
+            | Action::Backup
+            | Action::Pager
+            | Action::Difftool
+            | Action::Filter
+            | Action::Sort
+            | Action::Goto
+            | Action::Group
+            | Action::Stat
+            | Action::Clear => tab == SelectedTab::Manage,
+            _ => true,
+        }
+    }
+
+    /// The built-in bindings
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "Q"],
+            Action::NextTab => &["tab"],
+            Action::PrevTab => &["shift+tab"],
+            Action::Down => &["down", "j"],
+            Action::Up => &["up", "k"],
+            Action::ScrollDown => &["right", "l"],
+            Action::ScrollUp => &["left", "h"],
+            Action::HalfPageDown => &["ctrl+d"],
+            Action::HalfPageUp => &["ctrl+u"],
+            Action::PageDown => &["pagedown"],
+            Action::PageUp => &["pageup"],
+            Action::ScrollLeft => &["H", "shift+left"],
+            Action::ScrollRight => &["L", "shift+right"],
+            Action::Wrap => &["w"],
+            Action::SideBySide => &["|"],
+            Action::Stat => &["t"],
+            Action::Top => &["g"],
+            Action::Bottom => &["G"],
+            Action::Toggle => &["space"],
+            Action::Hunks => &["enter"],
+            Action::FileFilter => &["u"],
+            Action::Files => &["enter"],
+            Action::Stash => &["s"],
+            Action::StashAll => &["S"],
+            Action::SelectAll => &["a"],
+            Action::SelectNone => &["x"],
+            Action::ShowCreated => &["J"],
+            Action::Apply => &["a"],
+            Action::ApplyIndex => &["A"],
+            Action::Pop => &["p"],
+            Action::PopIndex => &["P"],
+            Action::Drop => &["d"],
+            Action::Cleanup => &["X"],
+            Action::Recover => &["R"],
+            Action::Branch => &["b"],
+            Action::Worktree => &["T"],
+            Action::Commit => &["C"],
+            Action::Compare => &["c"],
+            Action::AgainstCurrent => &["W"],
+            Action::Export => &["e"],
+            Action::Backup => &["E"],
+            Action::Pager => &["v"],
+            Action::Difftool => &["D"],
+            Action::Filter => &["/"],
+            Action::Sort => &["o"],
+            Action::Goto => &[":"],
+            Action::Group => &["B"],
+            Action::Search => &["f"],
+            Action::SearchNext => &["n"],
+            Action::SearchPrev => &["N"],
+            Action::Rename => &["m"],
+            Action::Clear => &["esc"],
+            Action::Log => &["O"],
+            Action::Help => &["?"],
+        }
+    }
+}
+
+/// A key with its modifiers, written like `ctrl+d`, `shift+tab`, `space` or `A`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn matches(&self, key: &KeyEvent) -> bool {
+        // Shift is already part of an uppercase or symbol character
+        let modifiers = match key.code {
+            KeyCode::Char(_) | KeyCode::BackTab => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        self.code == key.code && self.modifiers == modifiers
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut shift = false;
+        let mut rest = s;
+        while let Some((prefix, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" => modifiers |= KeyModifiers::CONTROL,
+                "alt" => modifiers |= KeyModifiers::ALT,
+                "shift" => shift = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", prefix, s)),
+            }
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(if shift { c.to_ascii_uppercase() } else { c }),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "tab" if shift => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{}'", s)),
+                },
+            },
+        };
+        if shift && !matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers |= KeyModifiers::SHIFT;
+        }
+        Ok(KeyBinding { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Active key bindings: the defaults with any actions from the config file replaced
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::iter()
+            .map(|action| {
+                let keys = action.default_keys().iter().map(|k| k.parse().expect("valid default key"));
+                (action, keys.collect())
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Replace the keys of an action
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyBinding>) {
+        self.bindings.insert(action, keys);
+    }
+
+    /// The action a key triggers on a tab; actions that apply to the tab win over others
+    /// bound to the same key
+    pub fn action(&self, key: &KeyEvent, tab: SelectedTab) -> Option<Action> {
+        let mut matching = Action::iter()
+            .filter(|action| self.bindings.get(action).is_some_and(|keys| keys.iter().any(|k| k.matches(key))));
+        let first = matching.next()?;
+        if first.applies_to(tab) {
+            return Some(first);
+        }
+        matching.find(|action| action.applies_to(tab)).or(Some(first))
+    }
+
+    /// The first key bound to an action, for help text
+    pub fn key_label(&self, action: Action) -> String {
+        match self.bindings.get(&action).and_then(|keys| keys.first()) {
+            Some(key) => key.to_string(),
+            None => "(unbound)".to_string(),
+        }
+    }
+
+    /// Every key bound to an action, for the help popup
+    pub fn keys_label(&self, action: Action) -> String {
+        match self.bindings.get(&action) {
+            Some(keys) if !keys.is_empty() => keys.iter().map(|k| k.to_string()).collect::<Vec<_>>().join(" / "),
+            _ => "(unbound)".to_string(),
+        }
+    }
+
+    /// A help line such as `q: Quit | Tab: Switch Tab`, built from the active bindings
+    pub fn help_line(&self, entries: &[(&[Action], &str)]) -> String {
+        entries
+            .iter()
+            .map(|(actions, label)| {
+                let keys: Vec<String> = actions.iter().map(|a| self.key_label(*a)).collect();
+                format!("{}: {}", keys.join("/"), label)
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_keys_are_unique_within_a_tab() {
+        let keymap = Keymap::default();
+        for tab in [SelectedTab::Create, SelectedTab::Manage] {
+            let actions: Vec<Action> = Action::iter().filter(|a| a.applies_to(tab)).collect();
+            for (i, a) in actions.iter().enumerate() {
+                for b in &actions[i + 1..] {
+                    let shared = keymap.bindings[a].iter().find(|key| keymap.bindings[b].contains(key));
+                    assert!(shared.is_none(), "{} and {} both default to {} on the {} tab", a, b, shared.unwrap(), tab);
+                }
+            }
+        }
+    }
+
+    fn key(s: &str) -> KeyBinding {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        assert_eq!(key("ctrl+d"), KeyBinding { code: KeyCode::Char('d'), modifiers: KeyModifiers::CONTROL });
+        assert_eq!(key("Ctrl+Alt+x"), KeyBinding { code: KeyCode::Char('x'), modifiers: KeyModifiers::CONTROL | KeyModifiers::ALT });
+        assert_eq!(key("shift+tab"), KeyBinding { code: KeyCode::BackTab, modifiers: KeyModifiers::NONE });
+        assert_eq!(key("shift+a"), key("A"));
+        assert_eq!(key("shift+up"), KeyBinding { code: KeyCode::Up, modifiers: KeyModifiers::SHIFT });
+    }
+
+    #[test]
+    fn parses_named_and_symbol_keys() {
+        assert_eq!(key("space"), KeyBinding { code: KeyCode::Char(' '), modifiers: KeyModifiers::NONE });
+        assert_eq!(key("+"), KeyBinding { code: KeyCode::Char('+'), modifiers: KeyModifiers::NONE });
+        assert_eq!(key("ctrl++"), KeyBinding { code: KeyCode::Char('+'), modifiers: KeyModifiers::CONTROL });
+        assert_eq!(key("Esc"), key("escape"));
+        assert_eq!(key("f5").code, KeyCode::F(5));
+        assert_eq!(key("pagedown").code, KeyCode::PageDown);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert_eq!("hyper+d".parse::<KeyBinding>(), Err("unknown modifier 'hyper' in 'hyper+d'".to_string()));
+        assert_eq!("f13".parse::<KeyBinding>(), Err("unknown key 'f13'".to_string()));
+        assert!("spacebar".parse::<KeyBinding>().is_err());
+        assert!("".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn shifted_characters_match_with_or_without_shift_reported() {
+        let binding = key("A");
+        assert!(binding.matches(&KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT)));
+        assert!(binding.matches(&KeyEvent::new(KeyCode::Char('A'), KeyModifiers::NONE)));
+        assert!(!binding.matches(&KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)));
+        assert!(key("shift+tab").matches(&KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)));
+    }
+}
@@ -1,21 +1,140 @@
 mod app;
+mod backup;
+mod binary;
+mod config;
+mod conflicts;
+mod diff_loader;
+mod diff_search;
+mod external;
+mod history;
+mod hunks;
+mod keymap;
+mod op_log;
+mod picker;
+mod recover;
+mod side_by_side;
+mod status_loader;
+mod submodules;
 mod tui;
+mod watcher;
+mod word_diff;
+mod worker;
+mod worktrees;
+
+use std::path::PathBuf;
 
 use color_eyre::Result;
 
+const USAGE: &str = "Usage: stash-mgr [--path <dir>]
+       stash-mgr backup <folder> [--path <dir>]
+
+Commands:
+  backup <folder>   Write every stash to <folder> as patch files with a MANIFEST, then exit
+
+Options:
+  -p, --path <dir>  Open the repository containing <dir> instead of the current directory
+  -h, --help        Print this help";
+
+/// What the command line asks for
+struct Args {
+    /// The `--path` argument, if given
+    path: Option<PathBuf>,
+    /// The folder of `backup <folder>`, which runs instead of the UI
+    backup: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut path = None;
+    let mut backup = None;
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy().into_owned();
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            "-p" | "--path" => {
+                path = Some(PathBuf::from(args.next().ok_or_else(|| format!("{} needs a directory", arg))?));
+            }
+            "backup" if backup.is_none() => {
+                backup = Some(PathBuf::from(args.next().ok_or_else(|| "backup needs a folder to write to".to_string())?));
+            }
+            _ => match arg.strip_prefix("--path=") {
+                Some(dir) => path = Some(PathBuf::from(dir)),
+                None => return Err(format!("unexpected argument '{}'", arg)),
+            },
+        }
+    }
+    Ok(Args { path, backup })
+}
+
 fn main() -> Result<()> {
     // Install panic hook BEFORE any terminal initialization
     tui::install_panic_hook();
 
-    // Verify we're in a git repository before entering TUI mode
-    let repo = match git2::Repository::discover(".") {
-        Ok(repo) => repo,
+    let Args { path, backup } = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // Load settings before touching the terminal so errors print normally
+    let config = match config::Config::load() {
+        Ok(config) => config,
         Err(e) => {
-            eprintln!("Error: {}", app::friendly_error_message(&e));
+            eprintln!("Error: invalid config file {}", e);
             std::process::exit(1);
         }
     };
 
+    // Verify we're in a git repository before entering TUI mode; outside one, offer the
+    // recently used repositories instead (an explicit --path is never second-guessed)
+    let repo = match git2::Repository::discover(path.as_deref().unwrap_or(".".as_ref())) {
+        Ok(repo) => repo,
+        Err(e) => {
+            let recent = history::recent_repos();
+            let not_a_repo = e.code() == git2::ErrorCode::NotFound && e.class() == git2::ErrorClass::Repository;
+            if path.is_some() || backup.is_some() || !not_a_repo || recent.is_empty() {
+                eprintln!("Error: {}", app::friendly_error_message(&e));
+                if path.is_none() && not_a_repo {
+                    eprintln!("Run stash-mgr inside a repository or pass --path <dir>.");
+                }
+                std::process::exit(1);
+            }
+            let mut terminal = tui::init()?;
+            let picked = picker::pick(&mut terminal, &recent, &config.keymap);
+            tui::restore()?;
+            let Some(dir) = picked? else {
+                return Ok(());
+            };
+            match git2::Repository::open(&dir) {
+                Ok(repo) => repo,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", dir.display(), app::friendly_error_message(&e));
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+
+    if let Some(dir) = backup {
+        let mut repo = repo;
+        match backup::write(&mut repo, &dir) {
+            Ok(count) => println!("Backed up {} stash(es) to {}", count, dir.display()),
+            Err(e) => {
+                eprintln!("Error: backup failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // Remember the repository for the picker; failing to is not worth stopping for
+    let _ = history::record_repo(repo.workdir().unwrap_or(repo.path()));
+
     // Check for detached HEAD state and warn user
     if repo.head_detached().unwrap_or(false) {
         eprintln!("Warning: Repository is in detached HEAD state. Stash operations will work but without a branch reference.");
@@ -25,7 +144,7 @@ fn main() -> Result<()> {
     let mut terminal = tui::init()?;
 
     // Create and run the app
-    let mut app = app::App::new(repo);
+    let mut app = app::App::new(repo, config);
     app.run(&mut terminal)?;
 
     // Restore terminal to normal state
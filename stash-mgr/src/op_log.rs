@@ -0,0 +1,36 @@
+use std::collections::VecDeque;
+
+/// Operations kept in the log; older ones fall off
+const MAX_LOG_ENTRIES: usize = 200;
+
+/// One finished operation, as reported in the status bar
+pub struct LogEntry {
+    /// Seconds since the epoch
+    pub time: i64,
+    pub ok: bool,
+    pub text: String,
+}
+
+/// Recent stash operations and how they went, for this session only
+#[derive(Default)]
+pub struct OpLog {
+    entries: VecDeque<LogEntry>,
+}
+
+impl OpLog {
+    pub fn push(&mut self, time: i64, ok: bool, text: String) {
+        if self.entries.len() == MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry { time, ok, text });
+    }
+
+    /// Most recent first
+    pub fn entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use color_eyre::Result;
+use crossterm::event::{self, Event, KeyEventKind, MouseButton, MouseEventKind};
+use ratatui::backend::Backend;
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use crate::app::{SelectedTab, ACCENT, DIM, HIGHLIGHT_BG, HIGHLIGHT_FG};
+use crate::keymap::{Action, Keymap};
+
+/// Shown when stash-mgr starts outside a repository: lists recently used repositories
+struct Picker<'a> {
+    repos: &'a [PathBuf],
+    list_state: ListState,
+    list_area: Rect,
+}
+
+impl Picker<'_> {
+    fn draw(&mut self, frame: &mut Frame, keymap: &Keymap) {
+        let [header, list, footer] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default();
+        let message = Paragraph::new(format!("{} is not inside a git repository. Pick a recent one:", cwd)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(DIM))
+                .title("stash-mgr")
+                .title_style(Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)),
+        );
+        frame.render_widget(message, header);
+
+        let items: Vec<ListItem> = self.repos.iter().map(|r| ListItem::new(r.display().to_string())).collect();
+        let repos = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(DIM))
+                    .title(format!("Recent Repositories ({})", self.repos.len()))
+                    .title_style(Style::default().fg(ACCENT)),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(HIGHLIGHT_BG)
+                    .fg(HIGHLIGHT_FG)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(" > ");
+        self.list_area = list;
+        frame.render_stateful_widget(repos, list, &mut self.list_state);
+
+        let help = keymap.help_line(&[
+            (&[Action::Up, Action::Down], "Navigate"),
+            (&[Action::Files], "Open"),
+            (&[Action::Quit, Action::Clear], "Quit"),
+        ]);
+        frame.render_widget(Line::from(Span::styled(help, Style::default().fg(DIM))), footer);
+    }
+
+    fn select_next(&mut self) {
+        let next = self.list_state.selected().map_or(0, |i| (i + 1) % self.repos.len());
+        self.list_state.select(Some(next));
+    }
+
+    fn select_previous(&mut self) {
+        let previous = self.list_state.selected().map_or(0, |i| (i + self.repos.len() - 1) % self.repos.len());
+        self.list_state.select(Some(previous));
+    }
+}
+
+/// Let the user choose one of `repos` (not empty); `None` if they quit instead
+pub fn pick(terminal: &mut Terminal<impl Backend>, repos: &[PathBuf], keymap: &Keymap) -> Result<Option<PathBuf>> {
+    let mut picker = Picker { repos, list_state: ListState::default().with_selected(Some(0)), list_area: Rect::default() };
+    loop {
+        terminal.draw(|f| picker.draw(f, keymap))?;
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match keymap.action(&key, SelectedTab::Manage) {
+                Some(Action::Down) => picker.select_next(),
+                Some(Action::Up) => picker.select_previous(),
+                Some(Action::Files) => return Ok(picker.list_state.selected().map(|i| repos[i].clone())),
+                Some(Action::Quit | Action::Clear) => return Ok(None),
+                _ => {}
+            },
+            Event::Mouse(mouse) => {
+                let inner = picker.list_area.inner(ratatui::layout::Margin::new(1, 1));
+                match mouse.kind {
+                    MouseEventKind::Down(MouseButton::Left) if inner.contains(Position::new(mouse.column, mouse.row)) => {
+                        let index = picker.list_state.offset() + (mouse.row - inner.y) as usize;
+                        if index < repos.len() {
+                            picker.list_state.select(Some(index));
+                        }
+                    }
+                    MouseEventKind::ScrollDown => picker.select_next(),
+                    MouseEventKind::ScrollUp => picker.select_previous(),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
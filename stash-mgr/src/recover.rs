@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use git2::{ObjectType, Oid, Repository};
+
+/// A stash commit no longer in the stash list
+#[derive(Clone, Debug)]
+pub struct DroppedStash {
+    pub oid: Oid,
+    pub message: String,
+    /// Commit time, seconds since the epoch
+    pub time: i64,
+}
+
+/// Whether a commit has the shape `git stash` gives it: a "WIP on"/"On" message, HEAD and
+/// an "index on" commit as parents, and optionally a third parent for untracked files
+fn is_stash_commit(commit: &git2::Commit) -> bool {
+    let message = commit.message().unwrap_or_default();
+    (message.starts_with("WIP on ") || message.starts_with("On "))
+        && (2..=3).contains(&commit.parent_count())
+        && commit.parent(1).is_ok_and(|index| index.message().unwrap_or_default().starts_with("index on "))
+}
+
+/// What a stash holds: its tree and parents. Renaming a stash copies its commit with a new
+/// message, leaving the old one behind with the same contents.
+fn contents(commit: &git2::Commit) -> (Oid, Vec<Oid>) {
+    (commit.tree_id(), commit.parent_ids().collect())
+}
+
+/// Stash commits among the loose objects, newest first, skipping `exclude` (the current
+/// stashes) and the commits renaming them left behind. Dropped stashes stay loose until
+/// `git gc` packs or prunes them, so only loose objects are scanned; that keeps this quick
+/// in large repositories.
+pub fn dropped_stashes(repo: &Repository, exclude: &[Oid], limit: usize) -> Result<Vec<DroppedStash>, git2::Error> {
+    let odb = repo.odb()?;
+    let objects = repo.commondir().join("objects");
+    let live: Vec<(Oid, Vec<Oid>)> = exclude.iter().filter_map(|oid| repo.find_commit(*oid).ok()).map(|c| contents(&c)).collect();
+    let mut found = Vec::new();
+
+    for oid in loose_object_ids(&objects) {
+        if exclude.contains(&oid) || !odb.read_header(oid).is_ok_and(|(_, kind)| kind == ObjectType::Commit) {
+            continue;
+        }
+        let commit = repo.find_commit(oid)?;
+        if is_stash_commit(&commit) && !live.contains(&contents(&commit)) {
+            found.push(DroppedStash {
+                oid,
+                message: commit.summary().unwrap_or_default().to_string(),
+                time: commit.time().seconds(),
+            });
+        }
+    }
+
+    found.sort_by_key(|s| std::cmp::Reverse(s.time));
+    found.truncate(limit);
+    Ok(found)
+}
+
+/// Ids of the objects stored as `objects/xx/yyyy...` files
+fn loose_object_ids(objects: &Path) -> Vec<Oid> {
+    let Ok(dirs) = std::fs::read_dir(objects) else {
+        return Vec::new();
+    };
+    let mut ids = Vec::new();
+    for dir in dirs.flatten() {
+        let prefix = dir.file_name().to_string_lossy().into_owned();
+        if prefix.len() != 2 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+        let Ok(files) = std::fs::read_dir(dir.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let rest = file.file_name().to_string_lossy().into_owned();
+            // Temporary files from writers in progress share the directory
+            if rest.len() == 38
+                && let Ok(oid) = Oid::from_str(&format!("{}{}", prefix, rest))
+            {
+                ids.push(oid);
+            }
+        }
+    }
+    ids
+}
+
+/// Put a stash commit back on top of the stash list, like `git stash store`
+pub fn restore(repo: &Repository, stash: &DroppedStash) -> Result<(), git2::Error> {
+    repo.reference_ensure_log("refs/stash")?;
+    repo.reference("refs/stash", stash.oid, true, &stash.message)?;
+    Ok(())
+}
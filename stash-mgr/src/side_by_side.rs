@@ -0,0 +1,261 @@
+use std::ops::Range;
+
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::app::{ACCENT, DIFF_HUNK, DIM, ERROR, SUCCESS, WORD_ADD_BG, WORD_DEL_BG};
+use crate::diff_search::{self, DiffSearch};
+use crate::word_diff;
+
+/// One side of a row: a line of the old or new file
+struct Side<'a> {
+    /// Line of the unified diff it comes from
+    line: usize,
+    number: usize,
+    /// `' '`, `'-'` or `'+'`
+    origin: char,
+    text: &'a str,
+}
+
+/// A row of the side-by-side view
+enum Row<'a> {
+    /// Shown across both columns: file and hunk headers, notes
+    Full(&'a str),
+    /// Old file on the left, new on the right; `None` leaves that side blank
+    Split(Option<Side<'a>>, Option<Side<'a>>),
+}
+
+/// A row and the unified diff line it starts at
+struct Placed<'a> {
+    line: usize,
+    row: Row<'a>,
+}
+
+/// Old and new start lines from a `@@ -12,3 +14,5 @@` header
+fn hunk_starts(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.strip_prefix("@@ ")?.split(' ');
+    let start = |range: &str, sign: char| range.strip_prefix(sign)?.split(',').next()?.parse().ok();
+    Some((start(ranges.next()?, '-')?, start(ranges.next()?, '+')?))
+}
+
+/// Lay a unified diff out in rows: context lines on both sides, and within a hunk each
+/// run of removals paired in order with the run of additions that follows it
+fn rows(content: &str) -> Vec<Placed<'_>> {
+    let raw: Vec<&str> = content.lines().collect();
+    let mut rows = Vec::new();
+    let mut hunk: Option<(usize, usize)> = None;
+    let mut i = 0;
+    while i < raw.len() {
+        let line = raw[i];
+        if line.starts_with("@@") {
+            hunk = hunk_starts(line);
+        } else if line.starts_with("diff ") || line.starts_with("Untracked files (") {
+            hunk = None;
+        }
+        let Some((ref mut old, ref mut new)) = hunk else {
+            rows.push(Placed { line: i, row: Row::Full(line) });
+            i += 1;
+            continue;
+        };
+
+        if let Some(text) = line.strip_prefix(' ') {
+            let row = Row::Split(
+                Some(Side { line: i, number: *old, origin: ' ', text }),
+                Some(Side { line: i, number: *new, origin: ' ', text }),
+            );
+            rows.push(Placed { line: i, row });
+            (*old, *new) = (*old + 1, *new + 1);
+            i += 1;
+        } else if line.starts_with('-') || line.starts_with('+') {
+            let removed_start = i;
+            while i < raw.len() && raw[i].starts_with('-') {
+                i += 1;
+            }
+            let added_start = i;
+            while i < raw.len() && raw[i].starts_with('+') {
+                i += 1;
+            }
+            let removed = &raw[removed_start..added_start];
+            let added = &raw[added_start..i];
+            for n in 0..removed.len().max(added.len()) {
+                let left = removed.get(n).map(|l| Side { line: removed_start + n, number: *old + n, origin: '-', text: &l[1..] });
+                let right = added.get(n).map(|l| Side { line: added_start + n, number: *new + n, origin: '+', text: &l[1..] });
+                rows.push(Placed { line: removed_start + n, row: Row::Split(left, right) });
+            }
+            (*old, *new) = (*old + removed.len(), *new + added.len());
+        } else {
+            rows.push(Placed { line: i, row: Row::Full(line) });
+            i += 1;
+        }
+    }
+    rows
+}
+
+/// Rows in the side-by-side view of a diff
+pub fn row_count(content: &str) -> usize {
+    rows(content).len()
+}
+
+/// The row showing a line of the unified diff (or the first one after it)
+pub fn row_of_line(content: &str, line: usize) -> usize {
+    let rows = rows(content);
+    rows.iter().position(|r| r.line >= line).unwrap_or(rows.len().saturating_sub(1))
+}
+
+/// The unified diff line a row starts at
+pub fn line_of_row(content: &str, row: usize) -> usize {
+    rows(content).get(row).map_or(0, |r| r.line)
+}
+
+/// `text` from character `skip` on, `width` characters wide, each character styled by its byte offset
+fn column_spans(text: &str, skip: usize, width: usize, style_at: impl Fn(usize) -> Style) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut used = 0;
+    for (at, c) in text.char_indices().skip(skip).take(width) {
+        let style = style_at(at);
+        match spans.last_mut() {
+            Some(last) if last.style == style => last.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+        used += 1;
+    }
+    spans.push(Span::raw(" ".repeat(width - used)));
+    spans
+}
+
+/// One side of a split row, exactly `width` characters wide, with changed words and search
+/// matches highlighted
+fn side_spans(side: &Option<Side>, changed: &[Range<usize>], width: usize, hscroll: usize, search: &DiffSearch) -> Vec<Span<'static>> {
+    /// Line number column plus the origin character
+    const GUTTER: usize = 6;
+    let Some(side) = side else {
+        return vec![Span::raw(" ".repeat(width))];
+    };
+    let (base, highlight) = match side.origin {
+        '-' => (Style::default().fg(ERROR), Style::default().fg(ERROR).bg(WORD_DEL_BG).add_modifier(Modifier::BOLD)),
+        '+' => (Style::default().fg(SUCCESS), Style::default().fg(SUCCESS).bg(WORD_ADD_BG).add_modifier(Modifier::BOLD)),
+        _ => (Style::default(), Style::default()),
+    };
+    let gutter = format!("{:>4} {}", side.number, side.origin);
+    let mut spans: Vec<Span> = vec![Span::styled(gutter.chars().take(width).collect::<String>(), Style::default().fg(DIM))];
+    if width > GUTTER {
+        // Search positions count the origin column of the unified line
+        let found = search.line_matches(side.text);
+        let current = search.current_on(side.line).map(|r| r.start.saturating_sub(1)..r.end.saturating_sub(1));
+        let style_at = |at: usize| {
+            let base = if changed.iter().any(|r| r.contains(&at)) { highlight } else { base };
+            if current.as_ref().is_some_and(|r| r.contains(&at)) {
+                diff_search::current_style()
+            } else if found.iter().any(|r| r.contains(&at)) {
+                diff_search::match_style(base)
+            } else {
+                base
+            }
+        };
+        spans.extend(column_spans(side.text, hscroll, width - GUTTER, style_at));
+    }
+    spans
+}
+
+/// The visible rows of the side-by-side view, `width` columns wide; each side's text is
+/// scrolled right by `hscroll` characters
+pub fn lines(content: &str, width: usize, visible: Range<usize>, hscroll: usize, search: &DiffSearch) -> Vec<Line<'static>> {
+    let half = width.saturating_sub(1) / 2;
+    let right_width = width.saturating_sub(half + 1);
+    rows(content)
+        .into_iter()
+        .skip(visible.start)
+        .take(visible.len())
+        .map(|placed| match placed.row {
+            Row::Full(text) => {
+                let style = if text.starts_with("@@") {
+                    Style::default().fg(DIFF_HUNK)
+                } else if text.starts_with("Untracked files (") {
+                    Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)
+                } else if text.starts_with("Submodule ") || text.starts_with("Binary file ") {
+                    Style::default().fg(ACCENT)
+                } else if text.starts_with("Would not apply cleanly (") {
+                    Style::default().fg(ERROR).add_modifier(Modifier::BOLD)
+                } else if text.starts_with("diff ") || text.starts_with("index ") {
+                    Style::default().fg(DIM).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                search.highlight(Line::from(Span::styled(text, style)), placed.line)
+            }
+            Row::Split(old, new) => {
+                // Changed words of a removed/added pair, as in the unified view
+                let changes = match (&old, &new) {
+                    (Some(o), Some(n)) if o.origin == '-' && n.origin == '+' => word_diff::changed_ranges(o.text, n.text),
+                    _ => None,
+                };
+                let (old_changed, new_changed) = changes.map_or((Vec::new(), Vec::new()), |c| (c.old, c.new));
+                let mut spans = side_spans(&old, &old_changed, half, hscroll, search);
+                spans.push(Span::styled("│", Style::default().fg(DIM)));
+                spans.extend(side_spans(&new, &new_changed, right_width, hscroll, search));
+                Line::from(spans)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each row as `old | new`, a side written as its line number, origin and text
+    fn layout(content: &str) -> Vec<String> {
+        let side = |side: &Option<Side>| side.as_ref().map_or(String::new(), |s| format!("{}{}{}", s.number, s.origin, s.text));
+        rows(content)
+            .iter()
+            .map(|placed| match &placed.row {
+                Row::Full(text) => text.to_string(),
+                Row::Split(old, new) => format!("{} | {}", side(old), side(new)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn more_removals_than_additions_leave_the_right_side_blank() {
+        let diff = "@@ -10,3 +10,1 @@\n-one\n-two\n-three\n+uno\n";
+        assert_eq!(layout(diff), ["@@ -10,3 +10,1 @@", "10-one | 10+uno", "11-two | ", "12-three | "]);
+    }
+
+    #[test]
+    fn more_additions_than_removals_leave_the_left_side_blank() {
+        let diff = "@@ -5,1 +5,3 @@\n-a\n+b\n+c\n+d\n";
+        assert_eq!(layout(diff), ["@@ -5,1 +5,3 @@", "5-a | 5+b", " | 6+c", " | 7+d"]);
+    }
+
+    #[test]
+    fn context_lines_between_changes_and_hunks_keep_both_line_numbers() {
+        let diff = "diff --git a/f b/f\n\
+                    @@ -1,3 +1,3 @@\n keep\n-old\n+new\n same\n\
+                    @@ -20,2 +20,3 @@\n ctx\n+added\n tail\n";
+        assert_eq!(
+            layout(diff),
+            [
+                "diff --git a/f b/f",
+                "@@ -1,3 +1,3 @@",
+                "1 keep | 1 keep",
+                "2-old | 2+new",
+                "3 same | 3 same",
+                "@@ -20,2 +20,3 @@",
+                "20 ctx | 20 ctx",
+                " | 21+added",
+                "21 tail | 22 tail",
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_map_back_to_unified_lines() {
+        let diff = "@@ -1,2 +1,1 @@\n-x\n-y\n+z\n ctx\n";
+        // `-y` (line 2) has a row of its own; `+z` (line 3) shares the first pair's row,
+        // so it maps to the next row at or after it, the context line
+        assert_eq!(row_count(diff), 4);
+        assert_eq!(line_of_row(diff, 2), 2);
+        assert_eq!(row_of_line(diff, 4), 3);
+        assert_eq!(row_of_line(diff, 3), 3);
+    }
+}
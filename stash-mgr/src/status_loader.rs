@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use git2::StatusShow;
+
+use crate::app::{App, FileEntry, FileFilter, friendly_error_message};
+use crate::worker::LatestWorker;
+
+/// Part or all of the Create tab's file list
+pub struct LoadedFiles {
+    pub files: Vec<FileEntry>,
+    /// False for the first batch, which only has the staged changes; the rest follows
+    pub complete: bool,
+    /// Why the working directory couldn't be scanned; `files` is empty then
+    pub error: Option<String>,
+}
+
+/// Collects the working directory's status on a worker thread, so a repository with
+/// hundreds of thousands of files doesn't freeze the UI. Staged changes come first,
+/// since comparing the index with HEAD is quick, then the full list once the working
+/// directory has been scanned. Like the diff loader, only the latest request matters.
+pub struct StatusLoader {
+    /// Takes the file filter, and whether to leave out submodules
+    worker: LatestWorker<(FileFilter, bool), LoadedFiles>,
+}
+
+impl StatusLoader {
+    pub fn new(repo_path: &Path) -> Self {
+        let worker = LatestWorker::new(repo_path, |repo, (filter, exclude_submodules), job| {
+            let repo = match repo {
+                Ok(repo) => repo,
+                Err(e) => {
+                    let error = Some(friendly_error_message(e));
+                    job.send(LoadedFiles { files: Vec::new(), complete: true, error });
+                    return;
+                }
+            };
+            let files = App::load_working_files(repo, filter, exclude_submodules, StatusShow::Index);
+            if !job.send(LoadedFiles { files, complete: false, error: None }) {
+                return;
+            }
+            let files = App::load_working_files(repo, filter, exclude_submodules, StatusShow::IndexAndWorkdir);
+            job.send(LoadedFiles { files, complete: true, error: None });
+        });
+
+        Self { worker }
+    }
+
+    /// Start listing the working directory's files, replacing any earlier request
+    pub fn request(&self, filter: FileFilter, exclude_submodules: bool) {
+        self.worker.request((filter, exclude_submodules));
+    }
+
+    /// The batches for the latest request that are ready, oldest first
+    pub fn try_recv(&self) -> Vec<LoadedFiles> {
+        self.worker.ready()
+    }
+}
@@ -0,0 +1,40 @@
+use git2::{DiffDelta, DiffFile, FileMode, Repository};
+
+/// Whether a diff entry is a submodule (a gitlink, mode 160000) on either side
+pub fn is_submodule(delta: &DiffDelta) -> bool {
+    delta.old_file().mode() == FileMode::Commit || delta.new_file().mode() == FileMode::Commit
+}
+
+/// Paths of the repository's submodules
+pub fn paths(repo: &Repository) -> Vec<String> {
+    repo.submodules()
+        .unwrap_or_default()
+        .iter()
+        .map(|submodule| submodule.path().to_string_lossy().into_owned())
+        .collect()
+}
+
+fn path(file: &DiffFile) -> String {
+    file.path().map_or_else(String::new, |p| p.to_string_lossy().into_owned())
+}
+
+fn short_id(file: &DiffFile) -> String {
+    file.id().to_string()[..7].to_string()
+}
+
+/// The file header and a one-line summary shown for a submodule in place of its
+/// `Subproject commit` patch, e.g. `Submodule lib commit change 1a2b3c4→5d6e7f8`
+pub fn change_text(delta: &DiffDelta) -> String {
+    let (old, new) = (delta.old_file(), delta.new_file());
+    let was = old.mode() == FileMode::Commit && !old.id().is_zero();
+    let is = new.mode() == FileMode::Commit && !new.id().is_zero();
+    let name = if is { path(&new) } else { path(&old) };
+    let summary = match (was, is) {
+        (false, _) => format!("Submodule {} added at {}", name, short_id(&new)),
+        (_, false) => format!("Submodule {} removed (was at {})", name, short_id(&old)),
+        // Same commit: only the files inside the submodule changed, which a stash can't hold
+        _ if old.id() == new.id() => format!("Submodule {} has uncommitted changes inside (not part of a stash)", name),
+        _ => format!("Submodule {} commit change {}→{}", name, short_id(&old), short_id(&new)),
+    };
+    format!("diff --git a/{} b/{}\n{}\n", path(&old), path(&new), summary)
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use git2::Repository;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// What changed on disk since the last check
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Changes {
+    /// `refs/stash` or its reflog: a stash was created or dropped
+    pub stashes: bool,
+    /// The index, HEAD, or a file in the working directory that is not ignored
+    pub files: bool,
+}
+
+/// Watches the repository for changes made outside the app, e.g. from another terminal.
+/// Each directory is watched on its own, leaving out ignored ones such as `target/` or
+/// `node_modules/`, so a big build tree doesn't use up the system's watch limit. The
+/// working directory is walked on a background thread so a large checkout doesn't hold
+/// up startup.
+pub struct RepoWatcher {
+    events: Receiver<notify::Result<Event>>,
+    /// Folders for the walker thread to watch, along with everything below them
+    walks: Sender<PathBuf>,
+    /// Where `HEAD` and `index` live, and where refs live (differ for linked worktrees)
+    git_dirs: Vec<PathBuf>,
+    workdir: Option<PathBuf>,
+}
+
+impl RepoWatcher {
+    pub fn new(repo: &Repository) -> notify::Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+
+        let workdir = repo.workdir().map(Path::to_path_buf);
+        let mut git_dirs = vec![repo.path().to_path_buf()];
+        if repo.commondir() != repo.path() {
+            git_dirs.push(repo.commondir().to_path_buf());
+        }
+
+        // `HEAD`, `index` and `packed-refs` sit at the top; `refs/stash` and its reflog below
+        for dir in &git_dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            for sub in ["refs", "logs/refs"] {
+                if dir.join(sub).is_dir() {
+                    watcher.watch(&dir.join(sub), RecursiveMode::NonRecursive)?;
+                }
+            }
+        }
+
+        // The walker owns the watcher, keeping it alive until this side hangs up
+        let (walks, requests) = mpsc::channel::<PathBuf>();
+        let mut walker = Walker { watcher, git_dirs: git_dirs.clone(), workdir: workdir.clone() };
+        let repo_path = repo.path().to_path_buf();
+        std::thread::spawn(move || {
+            // Without a repository to check ignores against, only the git dirs stay watched
+            let repo = Repository::open(&repo_path).ok();
+            for dir in requests {
+                if let Some(repo) = &repo
+                    && (walker.workdir.as_ref() == Some(&dir) || walker.tracks_dir(repo, &dir))
+                {
+                    walker.watch_tree(repo, &dir);
+                }
+            }
+        });
+        if let Some(workdir) = &workdir {
+            let _ = walks.send(workdir.clone());
+        }
+
+        Ok(Self { events, walks, git_dirs, workdir })
+    }
+
+    /// Drain pending events and sort them into stash and file changes
+    pub fn changes(&mut self, repo: &Repository) -> Changes {
+        let mut changes = Changes::default();
+        let events: Vec<Event> = self.events.try_iter().flatten().collect();
+        for event in events {
+            // Reads (including our own) show up as access events
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any) {
+                continue;
+            }
+            for path in &event.paths {
+                self.classify(repo, path, &mut changes);
+                // New folders need their own watch; if that fails they just go unnoticed
+                if matches!(event.kind, EventKind::Create(_)) && path.is_dir() {
+                    let _ = self.walks.send(path.clone());
+                }
+            }
+        }
+        changes
+    }
+
+    fn classify(&self, repo: &Repository, path: &Path, changes: &mut Changes) {
+        if let Some(relative) = self.git_dirs.iter().find_map(|dir| path.strip_prefix(dir).ok()) {
+            if relative == Path::new("refs/stash") || relative == Path::new("logs/refs/stash") || relative == Path::new("packed-refs") {
+                changes.stashes = true;
+            } else if relative == Path::new("index") || relative == Path::new("HEAD") {
+                changes.files = true;
+            }
+            return;
+        }
+        if let Some(relative) = self.workdir.as_ref().and_then(|w| path.strip_prefix(w).ok())
+            && !relative.as_os_str().is_empty()
+            && !repo.is_path_ignored(relative).unwrap_or(false)
+        {
+            changes.files = true;
+        }
+    }
+}
+
+/// The background side of [`RepoWatcher`], adding a watch for each folder it walks
+struct Walker {
+    watcher: RecommendedWatcher,
+    git_dirs: Vec<PathBuf>,
+    workdir: Option<PathBuf>,
+}
+
+impl Walker {
+    /// Watch `dir` and the folders below it that git doesn't ignore; a folder that can't be
+    /// watched (e.g. at the system's watch limit) is skipped along with what's below it
+    fn watch_tree(&mut self, repo: &Repository, dir: &Path) {
+        if self.watcher.watch(dir, RecursiveMode::NonRecursive).is_err() {
+            return;
+        }
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) && self.tracks_dir(repo, &path) {
+                self.watch_tree(repo, &path);
+            }
+        }
+    }
+
+    /// Whether `path` is a working directory folder whose files can show up in the file list
+    fn tracks_dir(&self, repo: &Repository, path: &Path) -> bool {
+        if path.file_name().is_some_and(|name| name == ".git") || self.git_dirs.iter().any(|dir| path.starts_with(dir)) {
+            return false;
+        }
+        self.workdir
+            .as_ref()
+            .and_then(|w| path.strip_prefix(w).ok())
+            .is_some_and(|relative| !relative.as_os_str().is_empty() && !repo.is_path_ignored(relative).unwrap_or(false))
+    }
+}
@@ -0,0 +1,164 @@
+use std::ops::Range;
+
+/// Token pairs above which the comparison is skipped (LCS is quadratic)
+const MAX_COMPARISONS: usize = 40_000;
+
+/// Split a line into words, whitespace runs, and single punctuation characters
+fn tokenize(line: &str) -> Vec<Range<usize>> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut tokens: Vec<Range<usize>> = Vec::new();
+    let mut current: Option<(Class, usize)> = None;
+    for (i, c) in line.char_indices() {
+        let c_class = class(c);
+        match current {
+            Some((ref open, _)) if *open == c_class && c_class != Class::Other => {}
+            Some((_, start)) => {
+                tokens.push(start..i);
+                current = Some((c_class, i));
+            }
+            None => current = Some((c_class, i)),
+        }
+    }
+    if let Some((_, start)) = current {
+        tokens.push(start..line.len());
+    }
+    tokens
+}
+
+/// Merge adjacent byte ranges
+fn coalesce(ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Byte ranges that differ in each line of a removed/added pair
+pub struct WordChanges {
+    pub old: Vec<Range<usize>>,
+    pub new: Vec<Range<usize>>,
+}
+
+/// Byte ranges of `old` and `new` that differ between the two lines, found by a longest
+/// common subsequence over their words. `None` when the lines are too long to compare or
+/// share no words at all (highlighting everything would be noise).
+pub fn changed_ranges(old: &str, new: &str) -> Option<WordChanges> {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    if old_tokens.len() * new_tokens.len() > MAX_COMPARISONS {
+        return None;
+    }
+
+    // lengths[i][j] = LCS length of old_tokens[i..] and new_tokens[j..]
+    let (n, m) = (old_tokens.len(), new_tokens.len());
+    let mut lengths = vec![vec![0u16; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if old[old_tokens[i].clone()] == new[new_tokens[j].clone()] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut old_changed = Vec::new();
+    let mut new_changed = Vec::new();
+    let mut common_words = false;
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[old_tokens[i].clone()] == new[new_tokens[j].clone()] {
+            common_words |= !old[old_tokens[i].clone()].trim().is_empty();
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            new_changed.push(new_tokens[j].clone());
+            j += 1;
+        } else {
+            old_changed.push(old_tokens[i].clone());
+            i += 1;
+        }
+    }
+
+    common_words.then(|| WordChanges { old: coalesce(old_changed), new: coalesce(new_changed) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words<'a>(line: &'a str, ranges: &[Range<usize>]) -> Vec<&'a str> {
+        ranges.iter().map(|r| &line[r.clone()]).collect()
+    }
+
+    fn changes(old: &str, new: &str) -> (Vec<String>, Vec<String>) {
+        let changes = changed_ranges(old, new).expect("lines share words");
+        let owned = |line, ranges| words(line, ranges).into_iter().map(str::to_string).collect();
+        (owned(old, &changes.old), owned(new, &changes.new))
+    }
+
+    #[test]
+    fn tokenize_splits_words_spaces_and_each_punctuation_character() {
+        let line = "foo_bar  ((x1)";
+        assert_eq!(words(line, &tokenize(line)), ["foo_bar", "  ", "(", "(", "x1", ")"]);
+    }
+
+    #[test]
+    fn added_words_only() {
+        let (old, new) = changes("let x = 1;", "let mut x = 1;");
+        assert!(old.is_empty());
+        assert_eq!(new, ["mut "]);
+    }
+
+    #[test]
+    fn removed_words_only() {
+        let (old, new) = changes("call(a, b, c)", "call(a, c)");
+        assert_eq!(old, ["b, "]);
+        assert!(new.is_empty());
+    }
+
+    #[test]
+    fn whitespace_only_change() {
+        let (old, new) = changes("if a  &&  b {", "if a && b {");
+        assert_eq!(old, ["  ", "  "]);
+        assert_eq!(new, [" ", " "]);
+    }
+
+    #[test]
+    fn different_token_counts() {
+        let (old, new) = changes("foo(bar)", "foo(bar, baz).unwrap()");
+        assert!(old.is_empty());
+        assert_eq!(new, [", baz", ".unwrap()"]);
+    }
+
+    #[test]
+    fn replaced_word_is_marked_on_both_sides() {
+        let (old, new) = changes("return total;", "return sum;");
+        assert_eq!(old, ["total"]);
+        assert_eq!(new, ["sum"]);
+    }
+
+    #[test]
+    fn lines_without_common_words_are_not_compared() {
+        assert!(changed_ranges("alpha beta", "gamma delta").is_none());
+    }
+}
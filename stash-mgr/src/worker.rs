@@ -0,0 +1,134 @@
+use std::cell::Cell;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use git2::Repository;
+
+/// A worker thread with its own repository, for work where only the latest request
+/// matters: older requests still queued are skipped, and the work closure can give up
+/// between steps once a newer request (or a cancel) comes in. The diff and status
+/// loaders are built on it.
+pub struct LatestWorker<Request, Output> {
+    requests: Sender<(u64, Request)>,
+    results: Receiver<(u64, Output)>,
+    latest: Arc<AtomicU64>,
+}
+
+/// The request the work closure is handling, for checking whether it is still wanted
+/// and sending results back
+pub struct Job<'a, Output> {
+    generation: u64,
+    latest: &'a AtomicU64,
+    results: &'a Sender<(u64, Output)>,
+    disconnected: &'a Cell<bool>,
+}
+
+impl<Output> Job<'_, Output> {
+    /// Whether a newer request or a cancel has replaced this one
+    pub fn stale(&self) -> bool {
+        self.latest.load(Ordering::SeqCst) != self.generation
+    }
+
+    /// Send a result unless the request has gone stale; false means the work should stop
+    pub fn send(&self, output: Output) -> bool {
+        if self.stale() {
+            return false;
+        }
+        if self.results.send((self.generation, output)).is_err() {
+            self.disconnected.set(true);
+            return false;
+        }
+        true
+    }
+}
+
+impl<Request: Send + 'static, Output: Send + 'static> LatestWorker<Request, Output> {
+    /// Start the worker; `work` gets the repository (or why it couldn't be opened) and each
+    /// request that is still the latest when the worker reaches it
+    pub fn new<F>(repo_path: &Path, mut work: F) -> Self
+    where
+        F: FnMut(Result<&Repository, &git2::Error>, Request, &Job<Output>) + Send + 'static,
+    {
+        let (requests, request_rx) = mpsc::channel::<(u64, Request)>();
+        let (result_tx, results) = mpsc::channel();
+        let latest = Arc::new(AtomicU64::new(0));
+
+        let repo_path = repo_path.to_path_buf();
+        let worker_latest = Arc::clone(&latest);
+        thread::spawn(move || {
+            // git2 repositories can't be shared across threads; the worker opens its own
+            let repo = Repository::open(&repo_path);
+            let disconnected = Cell::new(false);
+            while let Ok(mut request) = request_rx.recv() {
+                while let Ok(newer) = request_rx.try_recv() {
+                    request = newer;
+                }
+                let (generation, request) = request;
+                let job = Job { generation, latest: &worker_latest, results: &result_tx, disconnected: &disconnected };
+                if job.stale() {
+                    continue;
+                }
+                work(repo.as_ref(), request, &job);
+                if disconnected.get() {
+                    break;
+                }
+            }
+        });
+
+        Self { requests, results, latest }
+    }
+
+    /// Queue a request, replacing any earlier one
+    pub fn request(&self, request: Request) {
+        let generation = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.requests.send((generation, request));
+    }
+
+    /// Drop the pending request without making a new one
+    pub fn cancel(&self) {
+        self.latest.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The results for the latest request that are ready, oldest first
+    pub fn ready(&self) -> Vec<Output> {
+        let latest = self.latest.load(Ordering::SeqCst);
+        self.results.try_iter().filter(|(generation, _)| *generation == latest).map(|(_, output)| output).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn only_the_latest_request_is_answered() {
+        let (dir, _repo) = crate::hunks::test_repo(&[("a.txt", "a\n")]);
+        let worker = LatestWorker::new(&dir.path().join(".git"), |repo, n: u32, job| {
+            assert!(repo.is_ok());
+            thread::sleep(Duration::from_millis(20));
+            if job.send(n) {
+                job.send(n * 10);
+            }
+        });
+        for n in 1..=5 {
+            worker.request(n);
+        }
+
+        let mut results = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while results.len() < 2 && Instant::now() < deadline {
+            results.extend(worker.ready());
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(results, vec![5, 50]);
+
+        worker.request(6);
+        worker.cancel();
+        thread::sleep(Duration::from_millis(100));
+        assert!(worker.ready().is_empty());
+    }
+}
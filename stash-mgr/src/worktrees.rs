@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+
+/// A working tree of the repository, the main one or a linked one (`git worktree add`)
+#[derive(Clone, Debug)]
+pub struct Worktree {
+    /// The linked worktree's name, or the main working directory's folder name
+    pub name: String,
+    pub path: PathBuf,
+    /// Checked out branch; None when HEAD is detached
+    pub branch: Option<String>,
+    /// Whether this is the worktree the app was started in
+    pub current: bool,
+}
+
+/// The branch checked out in a repository, if HEAD is on one
+fn checked_out_branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    head.shorthand().map(str::to_string)
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Every working tree sharing this repository's stashes, the main one first. Empty when
+/// there are no linked worktrees, so callers can tell the usual single-worktree case apart.
+/// Worktrees whose directory is gone are left out.
+pub fn list(repo: &Repository) -> Vec<Worktree> {
+    let names = match repo.worktrees() {
+        Ok(names) if !names.is_empty() => names,
+        _ => return Vec::new(),
+    };
+    let current = repo.workdir();
+    let entry = |name: String, opened: &Repository| {
+        // Collecting the components drops the trailing slash of a working directory
+        let path: PathBuf = opened.workdir()?.components().collect();
+        Some(Worktree {
+            name,
+            current: current.is_some_and(|c| same_dir(c, &path)),
+            branch: checked_out_branch(opened),
+            path,
+        })
+    };
+
+    let mut worktrees = Vec::new();
+    // The common directory is the main repository's `.git`
+    if let Ok(main) = Repository::open(repo.commondir())
+        && let Some(workdir) = main.workdir()
+    {
+        let name = workdir.file_name().map_or_else(|| workdir.display().to_string(), |n| n.to_string_lossy().into_owned());
+        worktrees.extend(entry(name, &main));
+    }
+    for name in names.iter().flatten() {
+        if let Ok(worktree) = repo.find_worktree(name)
+            && worktree.validate().is_ok()
+            && let Ok(opened) = Repository::open_from_worktree(&worktree)
+        {
+            worktrees.extend(entry(name.to_string(), &opened));
+        }
+    }
+    worktrees
+}